@@ -0,0 +1,4 @@
+#!/usr/bin/env bash
+
+cargo build --target wasm32-unknown-unknown --release --package currency-token-archive && \
+ ic-cdk-optimizer ./target/wasm32-unknown-unknown/release/currency_token_archive.wasm -o ./target/wasm32-unknown-unknown/release/currency-token-archive-opt.wasm
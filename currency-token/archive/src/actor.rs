@@ -0,0 +1,90 @@
+use ic_cdk::export::candid::export_service;
+use ic_cdk_macros::{init, query, update};
+use union_utils::log;
+
+use currency_token_client::types::{
+    AppendEventsRequest, ArchiveInitRequest, GetEventsRequest, GetEventsResponse,
+    GetTransactionsRequest, GetTransactionsResponse, Transaction,
+};
+use token_common::event_log::MAX_EVENTS_PAGE_SIZE;
+
+use crate::common::archive::EventArchive;
+use crate::common::guards::token_guard;
+
+mod common;
+
+// ----------------- MAIN LOGIC ------------------
+
+#[init]
+fn init(request: ArchiveInitRequest) {
+    log("currency_token_archive.init()");
+
+    unsafe {
+        STATE = Some(EventArchive::new(request.token));
+    }
+}
+
+#[update(guard = "token_guard")]
+fn append_events(request: AppendEventsRequest) {
+    log("currency_token_archive.append_events()");
+
+    let appended = get_archive().append(request.events);
+
+    log(format!("Archived {} events", appended).as_str());
+}
+
+#[query]
+fn get_events(request: GetEventsRequest) -> GetEventsResponse {
+    log("currency_token_archive.get_events()");
+
+    let archive = get_archive();
+    let events = archive.get_events(
+        request.from_seq,
+        request.limit as usize,
+        &request.topic_filter,
+    );
+
+    GetEventsResponse {
+        events,
+        oldest_seq: archive.get_oldest_seq(),
+        next_seq: archive.get_next_seq(),
+    }
+}
+
+/// Serves the ranges the token points to in its own `get_transactions` response
+#[query]
+fn get_transactions(request: GetTransactionsRequest) -> GetTransactionsResponse {
+    log("currency_token_archive.get_transactions()");
+
+    let archive = get_archive();
+    let to_seq = request
+        .start
+        .saturating_add(request.length.min(MAX_EVENTS_PAGE_SIZE as u64));
+
+    let transactions = archive
+        .get_events_range(request.start, to_seq)
+        .into_iter()
+        .filter_map(Transaction::from_record)
+        .collect();
+
+    GetTransactionsResponse {
+        transactions,
+        first_seq: archive.get_oldest_seq(),
+        archived_ranges: Vec::new(),
+    }
+}
+
+// ------------------ STATE ----------------------
+
+export_service!();
+
+#[query(name = "__get_candid_interface_tmp_hack")]
+fn export_candid() -> String {
+    __export_service()
+}
+
+static mut STATE: Option<EventArchive> = None;
+
+pub fn get_archive() -> &'static mut EventArchive {
+    unsafe { STATE.as_mut().unwrap() }
+}
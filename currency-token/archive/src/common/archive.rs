@@ -0,0 +1,139 @@
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+
+use currency_token_client::types::{EventRecord, EventSeq, EventTopicFilter};
+use token_common::event_log::{matches_filter, MAX_EVENTS_PAGE_SIZE};
+
+/// Events evicted from the token's event log. The token retries batches which it failed to
+/// deliver, so events which are already stored are skipped.
+#[derive(CandidType, Deserialize)]
+pub struct EventArchive {
+    pub token: Principal,
+    pub events: BTreeMap<EventSeq, EventRecord>,
+}
+
+impl EventArchive {
+    pub fn new(token: Principal) -> EventArchive {
+        EventArchive {
+            token,
+            events: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the number of newly stored events
+    pub fn append(&mut self, events: Vec<EventRecord>) -> usize {
+        let mut appended = 0;
+
+        for event in events {
+            if let Entry::Vacant(entry) = self.events.entry(event.seq) {
+                entry.insert(event);
+                appended += 1;
+            }
+        }
+
+        appended
+    }
+
+    pub fn get_oldest_seq(&self) -> EventSeq {
+        self.events.keys().next().cloned().unwrap_or_default()
+    }
+
+    pub fn get_next_seq(&self) -> EventSeq {
+        self.events
+            .keys()
+            .next_back()
+            .map(|seq| seq + 1)
+            .unwrap_or_default()
+    }
+
+    /// Returns stored events with `from_seq <= seq < to_seq`
+    pub fn get_events_range(&self, from_seq: EventSeq, to_seq: EventSeq) -> Vec<&EventRecord> {
+        if from_seq >= to_seq {
+            return Vec::new();
+        }
+
+        self.events
+            .range(from_seq..to_seq)
+            .map(|(_, record)| record)
+            .collect()
+    }
+
+    pub fn get_events(
+        &self,
+        from_seq: EventSeq,
+        limit: usize,
+        filter: &Option<EventTopicFilter>,
+    ) -> Vec<EventRecord> {
+        self.events
+            .range(from_seq..)
+            .map(|(_, record)| record)
+            .filter(|record| match filter {
+                Some(f) => matches_filter(record, f),
+                None => true,
+            })
+            .take(limit.min(MAX_EVENTS_PAGE_SIZE))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use union_utils::random_principal_test;
+
+    use currency_token_client::types::{EventRecord, EventTopicFilter};
+
+    use crate::common::archive::EventArchive;
+
+    fn record(seq: u64, name: &str) -> EventRecord {
+        EventRecord {
+            seq,
+            name: String::from(name),
+            principals: vec![],
+            payload: vec![],
+            timestamp: seq,
+        }
+    }
+
+    #[test]
+    fn archive_works_fine() {
+        let mut archive = EventArchive::new(random_principal_test());
+
+        assert_eq!(archive.get_oldest_seq(), 0);
+        assert_eq!(archive.get_next_seq(), 0);
+
+        let batch = (10..20)
+            .map(|seq| {
+                record(
+                    seq,
+                    if seq % 2 == 0 {
+                        "TokenMoveEvent"
+                    } else {
+                        "InfoUpdateEvent"
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(archive.append(batch.clone()), 10);
+        assert_eq!(archive.append(batch), 0);
+
+        assert_eq!(archive.get_oldest_seq(), 10);
+        assert_eq!(archive.get_next_seq(), 20);
+        assert_eq!(archive.get_events_range(15, 100).len(), 5);
+        assert!(archive.get_events_range(15, 15).is_empty());
+
+        let filter = Some(EventTopicFilter {
+            name: Some(String::from("TokenMoveEvent")),
+            principal: None,
+        });
+        let events = archive.get_events(0, 3, &filter);
+
+        assert_eq!(
+            events.iter().map(|it| it.seq).collect::<Vec<_>>(),
+            vec![10, 12, 14]
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use ic_cdk::caller;
+
+use crate::get_archive;
+
+#[inline(always)]
+pub fn token_guard() -> Result<(), String> {
+    if caller() == get_archive().token {
+        Ok(())
+    } else {
+        Err(String::from(
+            "The caller is not the archived Currency Token",
+        ))
+    }
+}
@@ -1,34 +1,82 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::convert::TryInto;
 
-use ic_cdk::export::candid::{decode_args, export_service};
-use ic_cdk::{caller, id, trap};
-use ic_cdk_macros::{init, query, update};
+use ic_cdk::export::candid::{decode_args, encode_one, export_service, CandidType, Principal};
+use ic_cdk_macros::{heartbeat, init, query, update};
 use ic_cron::implement_cron;
-use ic_cron::types::ScheduledTask;
-use ic_event_hub_macros::{
-    implement_become_event_listener, implement_event_emitter, implement_get_event_listeners,
-    implement_stop_being_event_listener,
-};
-use union_utils::log;
+use ic_cron::types::{Iterations, ScheduledTask, SchedulingInterval, TaskId};
+use ic_event_hub::types::{AddEventListenersRequest, IEvent, RemoveEventListenersRequest};
+use ic_event_hub_macros::{implement_event_emitter, implement_get_event_listeners};
+use serde::de::DeserializeOwned;
 
 use currency_token_client::events::{
-    ControllerType, ControllersUpdateEvent, InfoUpdateEvent, TokenMoveEvent,
+    ControllerType, ControllersHandoverAcceptedEvent, ControllersHandoverCancelledEvent,
+    ControllersHandoverProposedEvent, ControllersUpdateCancelledEvent, ControllersUpdateEvent,
+    ControllersUpdateScheduledEvent, EscrowCreatedEvent, EscrowRefundedEvent, EscrowReleasedEvent,
+    InfoUpdateEvent, LoggedEvent, LowCyclesEvent, ProposalApprovedEvent, ProposalCreatedEvent,
+    ProposalExecutedEvent, RecurrentTaskCancelledEvent, RecurrentTaskCompletedEvent,
+    RecurrentTaskCreatedEvent, TokenMoveEvent,
 };
 use currency_token_client::types::{
-    BurnRequest, ControllerList, DequeueRecurrentTaskRequest, DequeueRecurrentTaskResponse,
-    GetBalanceOfRequest, GetBalanceOfResponse, GetControllersResponse, GetInfoResponse,
-    GetRecurrentMintTasksResponse, GetRecurrentTransferTasksRequest,
-    GetRecurrentTransferTasksResponse, GetTotalSupplyResponse, InitRequest, RecurrentMintTaskExt,
-    RecurrentTransferTaskExt, TransferRequest, UpdateControllersRequest, UpdateControllersResponse,
-    UpdateInfoRequest, UpdateInfoResponse,
+    ApprovalThreshold, ApproveProposalResponse, ArchiveCallback, ArchivedTransactionsRange,
+    AuditStateResponse, BurnFromRequest, BurnRequest, ClawbackRequest, ControllersChange,
+    ControllersHandoverRequest, ControllersUpdateDelay, ControllersUpdateId,
+    ControllersUpdateIdRequest, CreateEscrowRequest, CreateEscrowResponse,
+    DequeueRecurrentTaskRequest, DequeueRecurrentTaskResponse, EscrowIdRequest, EventSeq,
+    EventTopicFilter, ForcedMoveKind, ForcedMoveReason, GetApprovalThresholdsResponse,
+    GetBalanceOfRequest, GetBalanceOfResponse, GetBalancesOfRequest, GetBalancesOfResponse,
+    GetControllersHandoversResponse, GetControllersResponse, GetCyclesBalanceResponse,
+    GetEscrowResponse, GetEscrowsOfRequest, GetEscrowsOfResponse, GetEventsRequest,
+    GetEventsResponse, GetHoldersCountResponse, GetHoldersRequest, GetHoldersResponse,
+    GetInfoResponse, GetMetricsResponse, GetMintQuotaRequest, GetMintQuotaResponse,
+    GetProposalResponse, GetProposalsResponse, GetRecurrentMintTasksResponse,
+    GetRecurrentTransferTasksRequest, GetRecurrentTransferTasksResponse, GetRoleMembersRequest,
+    GetRoleMembersResponse, GetScheduledControllersUpdatesResponse, GetStateHashResponse,
+    GetTopHoldersRequest, GetTopHoldersResponse, GetTotalSupplyResponse, GetTransactionsRequest,
+    GetTransactionsResponse, HasRoleResponse, HolderEntry, HttpRequest, HttpResponse, InitRequest,
+    MetadataValue, ProposalAction, ProposalIdRequest, ProposeRequest, ProposeResponse,
+    RecurrentMintTaskExt, RecurrentTransferTaskExt, Role, RoleRequest, SetApprovalThresholdRequest,
+    SetApprovalThresholdResponse, SetCyclesLowWaterMarkRequest, SetEventArchiveRequest,
+    SetMintQuotaRequest, SetRoleAdminRequest, SetRoleAdminResponse, TokenInfo, TokenLogo,
+    Transaction, TransferRequest, UpdateControllersRequest, UpdateControllersResponse,
+    UpdateInfoRequest, UpdateInfoResponse, UpdateRoleControllersRequest, WalletReceiveResponse,
+    ADMIN_ROLE, BURN_ROLE, DEFAULT_ROLES, EVENT_LISTENERS_ROLE, INFO_ROLE, MINT_ROLE,
+};
+use token_common::event_log::MAX_EVENTS_PAGE_SIZE;
+use token_common::governance::{Governance, GovernanceEvent};
+use token_common::handover::HandoverRegistry;
+use token_common::http::{
+    error_response, get_num_param, get_principal_param, json_response, parse_principal, parse_url,
+    text_response, Json, DEFAULT_HTTP_PAGE_SIZE,
+};
+use token_common::metrics::{heap_memory_bytes, stable_memory_bytes, PrometheusWriter};
+use token_common::proposals::ProposalRegistry;
+use token_common::roles::RoleRegistry;
+use token_common::timelock::ControllersTimelock;
+
+use crate::common::certification::{certificate_header, certify_responses, CertifiedResponses};
+use crate::common::currency_token::{CurrencyToken, MAX_BATCH_QUERY_SIZE, MAX_EVENT_LISTENERS};
+use crate::common::env::{get_env, Environment, TaskPayload};
+use crate::common::guards::{
+    admin_guard, burn_guard, event_listeners_guard, info_guard, mint_guard, self_guard,
+};
+use crate::common::info::validate_info;
+use crate::common::types::{
+    ControllersUpdateTask, CronTaskKind, EscrowRefundTask, RecurrentMintTask, RecurrentTransferTask,
 };
 
-use crate::common::currency_token::CurrencyToken;
-use crate::common::guards::{info_guard, mint_guard, self_guard};
-use crate::common::types::{CronTaskKind, RecurrentMintTask, RecurrentTransferTask};
-
+#[cfg(test)]
+mod actor_tests;
 mod common;
+#[cfg(feature = "mock")]
+pub mod mock;
+
+// the mock environment fakes the caller, the time and the cycles, so a wasm built with it
+// would let anyone act as anyone
+#[cfg(all(feature = "mock", target_arch = "wasm32"))]
+compile_error!(
+    "the `mock` feature is for native tests only and must never be enabled for wasm builds"
+);
 
 // ----------------- MAIN LOGIC ------------------
 
@@ -36,42 +84,51 @@ mod common;
 fn init(request: InitRequest) {
     log("currency_token.init()");
 
-    let controllers = if let Some(default_controllers) = request.default_controllers {
-        ControllerList {
-            mint_controllers: default_controllers.clone(),
-            info_controllers: default_controllers,
-        }
-    } else {
-        ControllerList::single(Some(caller()))
-    };
+    let default_controllers = request
+        .default_controllers
+        .unwrap_or_else(|| vec![get_env().caller()]);
+    let roles = RoleRegistry::new(&DEFAULT_ROLES, &default_controllers);
 
-    let token = CurrencyToken {
-        balances: HashMap::new(),
-        total_supply: 0,
-        info: request.info,
-        controllers,
-        recurrent_mint_tasks: HashSet::new(),
-        recurrent_transfer_tasks: HashMap::new(),
-    };
+    if let Err(e) = validate_info(&request.info) {
+        get_env().trap(format!("Invalid token info - {}", e).as_str());
+    }
+
+    let delay_nano = request.controllers_update_delay_nano.unwrap_or_default();
+    if let Err(e) = ControllersTimelock::check_delay(delay_nano) {
+        get_env().trap(format!("Invalid controllers update delay - {}", e).as_str());
+    }
+
+    let mut token = CurrencyToken::new(request.info, roles);
+    token.timelock.delay_nano = delay_nano;
 
     unsafe {
         STATE = Some(token);
     }
+
+    certify_http_responses();
 }
 
 #[update(guard = "mint_guard")]
 fn mint(request: TransferRequest) {
     log("currency_token.mint()");
+    count_call("mint");
+
+    approval_not_required(MINT_ROLE);
+    _mint(request, get_env().caller());
+}
 
+fn _mint(request: TransferRequest, minter: Principal) {
     let token = get_token();
+    let now = get_env().time();
 
     for (idx, entry) in request.entries.into_iter().enumerate() {
-        match token.mint(entry.to, entry.qty) {
+        match token.mint_by(minter, entry.to, entry.qty, now) {
             Ok(_) => {
                 if let Some(recurrence) = entry.recurrence {
-                    let enqueue_result = cron_enqueue(
-                        CronTaskKind::RecurrentMint as u8,
+                    let enqueue_result = get_env().schedule(
+                        CronTaskKind::RecurrentMint,
                         RecurrentMintTask {
+                            minter,
                             to: entry.to,
                             qty: entry.qty,
                             event_payload: entry.event_payload.clone(),
@@ -82,6 +139,15 @@ fn mint(request: TransferRequest) {
                     match enqueue_result {
                         Ok(task_id) => {
                             token.register_recurrent_mint_task(task_id);
+
+                            let (to, qty) = (entry.to, entry.qty);
+                            emit_logged(|seq| RecurrentTaskCreatedEvent {
+                                seq,
+                                task_id,
+                                from: None,
+                                to,
+                                qty,
+                            });
                         }
                         Err(_) => {
                             log("Candid serialization error met during recurrent mint enqueue");
@@ -89,14 +155,21 @@ fn mint(request: TransferRequest) {
                     };
                 }
 
-                emit(TokenMoveEvent {
+                // the entry is partially moved by now, so its fields are captured separately
+                let (to, qty, event_payload) = (entry.to, entry.qty, entry.event_payload);
+
+                emit_logged(|seq| TokenMoveEvent {
+                    seq,
                     from: None,
-                    to: Some(entry.to),
-                    qty: entry.qty,
-                    event_payload: entry.event_payload,
+                    to: Some(to),
+                    qty,
+                    event_payload,
+                    forced: None,
                 });
             }
-            Err(e) => trap(format!("Error during minting entry #{} - {}", idx, e).as_str()),
+            Err(e) => {
+                get_env().trap(format!("Error during minting entry #{} - {}", idx, e).as_str())
+            }
         };
     }
 }
@@ -104,16 +177,17 @@ fn mint(request: TransferRequest) {
 #[update]
 fn transfer(request: TransferRequest) {
     log("currency_token.transfer()");
+    count_call("transfer");
 
     let token = get_token();
-    let caller = caller();
+    let caller = get_env().caller();
 
     for (idx, entry) in request.entries.into_iter().enumerate() {
         match token.transfer(caller, entry.to, entry.qty) {
             Ok(_) => {
                 if let Some(recurrence) = entry.recurrence {
-                    let enqueue_result = cron_enqueue(
-                        CronTaskKind::RecurrentTransfer as u8,
+                    let enqueue_result = get_env().schedule(
+                        CronTaskKind::RecurrentTransfer,
                         RecurrentTransferTask {
                             from: caller,
                             to: entry.to,
@@ -126,7 +200,15 @@ fn transfer(request: TransferRequest) {
                     match enqueue_result {
                         Ok(task_id) => {
                             token.register_recurrent_transfer_task(caller, task_id);
-                            log("Successfully registered transfer task");
+
+                            let (to, qty) = (entry.to, entry.qty);
+                            emit_logged(|seq| RecurrentTaskCreatedEvent {
+                                seq,
+                                task_id,
+                                from: Some(caller),
+                                to,
+                                qty,
+                            });
                         }
                         Err(_) => {
                             log("Candid serialization error met during recurrent transfer enqueue");
@@ -136,14 +218,21 @@ fn transfer(request: TransferRequest) {
                     log("Recurrence is not provided");
                 }
 
-                emit(TokenMoveEvent {
+                // the entry is partially moved by now, so its fields are captured separately
+                let (to, qty, event_payload) = (entry.to, entry.qty, entry.event_payload);
+
+                emit_logged(|seq| TokenMoveEvent {
+                    seq,
                     from: Some(caller),
-                    to: Some(entry.to),
-                    qty: entry.qty,
-                    event_payload: entry.event_payload,
+                    to: Some(to),
+                    qty,
+                    event_payload,
+                    forced: None,
                 });
             }
-            Err(e) => trap(format!("Error during transferring entry #{} - {}", idx, e).as_str()),
+            Err(e) => {
+                get_env().trap(format!("Error during transferring entry #{} - {}", idx, e).as_str())
+            }
         };
     }
 }
@@ -151,17 +240,84 @@ fn transfer(request: TransferRequest) {
 #[update]
 fn burn(request: BurnRequest) {
     log("currency_token.burn()");
+    count_call("burn");
 
-    let caller = caller();
+    let caller = get_env().caller();
 
     match get_token().burn(caller, request.qty) {
-        Ok(_) => emit(TokenMoveEvent {
+        Ok(_) => emit_logged(|seq| TokenMoveEvent {
+            seq,
             from: Some(caller),
             to: None,
             qty: request.qty,
             event_payload: request.event_payload,
+            forced: None,
         }),
-        Err(e) => trap(format!("Burning failed - {}", e).as_str()),
+        Err(e) => get_env().trap(format!("Burning failed - {}", e).as_str()),
+    }
+}
+
+#[update(guard = "burn_guard")]
+fn burn_from(request: BurnFromRequest) {
+    log("currency_token.burn_from()");
+    count_call("burn_from");
+
+    let caller = get_env().caller();
+
+    match get_token().burn(request.account, request.qty) {
+        Ok(_) => {
+            log(format!(
+                "{} burned {} from {} - {}",
+                caller, request.qty, request.account, request.reason
+            )
+            .as_str());
+
+            emit_logged(|seq| TokenMoveEvent {
+                seq,
+                from: Some(request.account),
+                to: None,
+                qty: request.qty,
+                event_payload: None,
+                forced: Some(ForcedMoveReason {
+                    kind: ForcedMoveKind::BurnFrom,
+                    executed_by: caller,
+                    reason: request.reason,
+                }),
+            });
+        }
+        Err(e) => get_env().trap(format!("Burning from account failed - {}", e).as_str()),
+    }
+}
+
+#[update(guard = "burn_guard")]
+fn clawback(request: ClawbackRequest) {
+    log("currency_token.clawback()");
+    count_call("clawback");
+
+    let caller = get_env().caller();
+
+    match get_token().transfer(request.from, request.to, request.qty) {
+        Ok(_) => {
+            log(format!(
+                "{} clawed back {} from {} to {} - {}",
+                caller, request.qty, request.from, request.to, request.reason
+            )
+            .as_str());
+
+            emit_logged(|seq| TokenMoveEvent {
+                seq,
+                from: Some(request.from),
+                to: Some(request.to),
+                qty: request.qty,
+                event_payload: None,
+                forced: Some(ForcedMoveReason {
+                    kind: ForcedMoveKind::Clawback,
+                    executed_by: caller,
+                    reason: request.reason,
+                }),
+            });
+        }
+        Err(e) => get_env().trap(format!("Clawback failed - {}", e).as_str()),
     }
 }
 
@@ -174,6 +330,25 @@ fn get_balance_of(request: GetBalanceOfRequest) -> GetBalanceOfResponse {
     GetBalanceOfResponse { balance }
 }
 
+#[query]
+fn get_balances_of(request: GetBalancesOfRequest) -> GetBalancesOfResponse {
+    log("currency_token.get_balances_of()");
+
+    batch_size_respected(request.account_owners.len());
+
+    let token = get_token();
+    let balances = request
+        .account_owners
+        .into_iter()
+        .map(|account_owner| HolderEntry {
+            account_owner,
+            balance: token.balance_of(&account_owner),
+        })
+        .collect();
+
+    GetBalancesOfResponse { balances }
+}
+
 #[query]
 fn get_total_supply() -> GetTotalSupplyResponse {
     log("currency_token.get_total_supply()");
@@ -183,6 +358,49 @@ fn get_total_supply() -> GetTotalSupplyResponse {
     GetTotalSupplyResponse { total_supply }
 }
 
+#[query]
+fn get_holders(request: GetHoldersRequest) -> GetHoldersResponse {
+    log("currency_token.get_holders()");
+
+    let (holders, next_cursor) = get_token().get_holders(request.cursor, request.limit as usize);
+
+    GetHoldersResponse {
+        holders: holders
+            .into_iter()
+            .map(|(account_owner, balance)| HolderEntry {
+                account_owner,
+                balance,
+            })
+            .collect(),
+        next_cursor,
+    }
+}
+
+#[query]
+fn get_holders_count() -> GetHoldersCountResponse {
+    log("currency_token.get_holders_count()");
+
+    let holders_count = get_token().get_holders_count() as u64;
+
+    GetHoldersCountResponse { holders_count }
+}
+
+#[query]
+fn get_top_holders(request: GetTopHoldersRequest) -> GetTopHoldersResponse {
+    log("currency_token.get_top_holders()");
+
+    let holders = get_token()
+        .get_top_holders(request.n as usize)
+        .into_iter()
+        .map(|(account_owner, balance)| HolderEntry {
+            account_owner,
+            balance,
+        })
+        .collect();
+
+    GetTopHoldersResponse { holders }
+}
+
 #[query]
 fn get_info() -> GetInfoResponse {
     log("currency_token.get_info()");
@@ -195,10 +413,20 @@ fn get_info() -> GetInfoResponse {
 #[update(guard = "info_guard")]
 fn update_info(request: UpdateInfoRequest) -> UpdateInfoResponse {
     log("currency_token.update_info()");
+    count_call("update_info");
+
+    approval_not_required(INFO_ROLE);
+    _update_info(request)
+}
 
-    let old_info = get_token().update_info(request.new_info.clone());
+fn _update_info(request: UpdateInfoRequest) -> UpdateInfoResponse {
+    let old_info = match get_token().update_info(request.new_info.clone()) {
+        Ok(info) => info,
+        Err(e) => get_env().trap(format!("Failed to update token info - {}", e).as_str()),
+    };
 
-    emit(InfoUpdateEvent {
+    emit_logged(|seq| InfoUpdateEvent {
+        seq,
         new_info: request.new_info,
     });
 
@@ -211,197 +439,1592 @@ fn update_info(request: UpdateInfoRequest) -> UpdateInfoResponse {
 fn get_controllers() -> GetControllersResponse {
     log("currency_token.get_controllers()");
 
-    let controllers = get_token().controllers.clone();
+    let controllers = get_token().roles.get_roles();
 
     GetControllersResponse { controllers }
 }
 
-#[update(guard = "info_guard")]
-fn update_info_controller(request: UpdateControllersRequest) -> UpdateControllersResponse {
-    log("currency_token.update_info_controller()");
+#[update]
+fn grant_role(request: RoleRequest) {
+    log("currency_token.grant_role()");
+    count_call("grant_role");
 
-    let old_controller = get_token().update_info_controllers(request.new_controllers.clone());
+    TokenGovernance::grant_role(request, get_env().caller());
+}
 
-    emit(ControllersUpdateEvent {
-        kind: ControllerType::Info,
-        new_controllers: request.new_controllers,
-    });
+#[update]
+fn revoke_role(request: RoleRequest) {
+    log("currency_token.revoke_role()");
+    count_call("revoke_role");
 
-    UpdateControllersResponse {
-        old_controllers: old_controller,
+    TokenGovernance::revoke_role(request, get_env().caller());
+}
+
+#[update]
+fn set_role_admin(request: SetRoleAdminRequest) -> SetRoleAdminResponse {
+    log("currency_token.set_role_admin()");
+    count_call("set_role_admin");
+
+    let (old_admin_role, scheduled_update_id) =
+        TokenGovernance::set_role_admin(request, get_env().caller());
+
+    SetRoleAdminResponse {
+        old_admin_role,
+        scheduled_update_id,
     }
 }
 
+#[query]
+fn has_role(request: RoleRequest) -> HasRoleResponse {
+    log("currency_token.has_role()");
+
+    let has_role = get_token()
+        .roles
+        .has_role(&request.role, &request.principal);
+
+    HasRoleResponse { has_role }
+}
+
+#[query]
+fn get_role_members(request: GetRoleMembersRequest) -> GetRoleMembersResponse {
+    log("currency_token.get_role_members()");
+
+    let members = get_token().roles.get_role_members(&request.role);
+
+    GetRoleMembersResponse { members }
+}
+
+#[update(guard = "info_guard")]
+fn update_info_controller(request: UpdateControllersRequest) -> UpdateControllersResponse {
+    log("currency_token.update_info_controller()");
+    count_call("update_info_controller");
+
+    _update_role_controllers(INFO_ROLE, request)
+}
+
 #[update(guard = "mint_guard")]
 fn update_mint_controller(request: UpdateControllersRequest) -> UpdateControllersResponse {
     log("currency_token.update_mint_controller()");
+    count_call("update_mint_controller");
 
-    let old_controller = get_token().update_mint_controllers(request.new_controllers.clone());
+    _update_role_controllers(MINT_ROLE, request)
+}
 
-    emit(ControllersUpdateEvent {
-        kind: ControllerType::Mint,
-        new_controllers: request.new_controllers,
-    });
+#[update(guard = "burn_guard")]
+fn update_burn_controller(request: UpdateControllersRequest) -> UpdateControllersResponse {
+    log("currency_token.update_burn_controller()");
+    count_call("update_burn_controller");
+
+    _update_role_controllers(BURN_ROLE, request)
+}
+
+#[update(guard = "event_listeners_guard")]
+fn update_event_listeners_controller(
+    request: UpdateControllersRequest,
+) -> UpdateControllersResponse {
+    log("currency_token.update_event_listeners_controller()");
+    count_call("update_event_listeners_controller");
+
+    _update_role_controllers(EVENT_LISTENERS_ROLE, request)
+}
+
+fn _update_role_controllers(
+    role: &str,
+    request: UpdateControllersRequest,
+) -> UpdateControllersResponse {
+    let (old_controllers, scheduled_update_id) = TokenGovernance::update_role_controllers(
+        UpdateRoleControllersRequest {
+            role: String::from(role),
+            new_controllers: request.new_controllers,
+            renounce: request.renounce,
+        },
+        get_env().caller(),
+    );
 
     UpdateControllersResponse {
-        old_controllers: old_controller,
+        old_controllers,
+        scheduled_update_id,
     }
 }
 
-// --------------- RECURRENCE ------------------
+// ------------------ HANDOVER -------------------
 
-implement_cron!();
+#[update]
+fn accept_controllers_handover(request: ControllersHandoverRequest) {
+    log("currency_token.accept_controllers_handover()");
+    count_call("accept_controllers_handover");
 
-fn _cron_task_handler(task: ScheduledTask) {
-    match task.get_kind().try_into() {
-        Ok(CronTaskKind::RecurrentTransfer) => _recurrent_transfer(task.get_payload().unwrap()),
-        Ok(CronTaskKind::RecurrentMint) => _recurrent_mint(task.get_payload().unwrap()),
-        Err(_) => log("Invalid cron task handler"),
-    }
+    TokenGovernance::accept_controllers_handover(&request.role, get_env().caller());
 }
 
-fn _recurrent_transfer(task: RecurrentTransferTask) {
-    log("currency_token._recurrent_transfer()");
+#[update]
+fn cancel_controllers_handover(request: ControllersHandoverRequest) {
+    log("currency_token.cancel_controllers_handover()");
+    count_call("cancel_controllers_handover");
 
-    match get_token().transfer(task.from, task.to, task.qty) {
-        Ok(_) => {
-            emit(TokenMoveEvent {
-                from: Some(task.from),
-                to: Some(task.to),
-                qty: task.qty,
-                event_payload: task.event_payload,
-            });
-        }
-        Err(e) => log(format!("Recurrent transferring failed with error: {}", e).as_str()),
-    };
+    TokenGovernance::cancel_controllers_handover(&request.role, get_env().caller());
 }
 
-fn _recurrent_mint(task: RecurrentMintTask) {
-    log("currency_token._recurrent_mint()");
+#[query]
+fn get_controllers_handovers() -> GetControllersHandoversResponse {
+    log("currency_token.get_controllers_handovers()");
 
-    match get_token().mint(task.to, task.qty) {
-        Ok(_) => {
-            emit(TokenMoveEvent {
-                from: None,
-                to: Some(task.to),
-                qty: task.qty,
-                event_payload: task.event_payload,
-            });
-        }
-        Err(e) => log(format!("Recurrent minting failed with error: {}", e).as_str()),
-    };
+    let handovers = get_token().handovers.get_handovers();
+
+    GetControllersHandoversResponse { handovers }
 }
 
+// ------------------ TIMELOCK -------------------
+
 #[update]
-fn dequeue_recurrent_transfer_tasks(
-    request: DequeueRecurrentTaskRequest,
-) -> DequeueRecurrentTaskResponse {
-    log("currency_token.dequeue_recurrent_transfer_tasks()");
+fn cancel_controllers_update(request: ControllersUpdateIdRequest) {
+    log("currency_token.cancel_controllers_update()");
+    count_call("cancel_controllers_update");
 
-    let caller = caller();
-    let mut succeed = vec![];
+    TokenGovernance::cancel_controllers_update(request.update_id, get_env().caller());
+}
 
-    for task_id in request.task_ids {
-        if get_token().unregister_recurrent_transfer_task(caller, task_id) {
-            cron_dequeue(task_id);
-            succeed.push(true);
+#[query]
+fn get_scheduled_controllers_updates() -> GetScheduledControllersUpdatesResponse {
+    log("currency_token.get_scheduled_controllers_updates()");
 
-            continue;
-        }
+    let updates = get_token().timelock.get_scheduled_updates();
 
-        succeed.push(false);
-    }
+    GetScheduledControllersUpdatesResponse { updates }
+}
 
-    DequeueRecurrentTaskResponse { succeed }
+#[update(guard = "admin_guard")]
+fn set_controllers_update_delay(request: ControllersUpdateDelay) {
+    log("currency_token.set_controllers_update_delay()");
+    count_call("set_controllers_update_delay");
+
+    TokenGovernance::set_controllers_update_delay(request.delay_nano, get_env().caller());
 }
 
 #[query]
-fn get_recurrent_transfer_tasks(
-    request: GetRecurrentTransferTasksRequest,
-) -> GetRecurrentTransferTasksResponse {
-    log("currency_token.get_recurrent_transfer_tasks()");
+fn get_controllers_update_delay() -> ControllersUpdateDelay {
+    log("currency_token.get_controllers_update_delay()");
 
-    let cron = get_cron_state();
+    ControllersUpdateDelay {
+        delay_nano: get_token().timelock.delay_nano,
+    }
+}
 
-    let tasks = get_token()
-        .get_recurrent_transfer_tasks(request.owner)
-        .into_iter()
-        .map(|id| {
-            let task = cron.get_task_by_id(&id).unwrap();
-            let task_payload = task.get_payload::<RecurrentTransferTask>().unwrap();
+fn batch_size_respected(size: usize) {
+    if size > MAX_BATCH_QUERY_SIZE {
+        get_env().trap(
+            format!(
+                "Too many principals in a single request, the limit is {}",
+                MAX_BATCH_QUERY_SIZE
+            )
+            .as_str(),
+        );
+    }
+}
 
-            RecurrentTransferTaskExt {
-                task_id: task.id,
-                from: task_payload.from,
-                to: task_payload.to,
-                qty: task_payload.qty,
-                event_payload: task_payload.event_payload,
-                scheduled_at: task.scheduled_at,
-                rescheduled_at: task.rescheduled_at,
-                scheduling_interval: task.scheduling_interval,
-            }
+/// Listeners the event hub currently delivers the token's events to
+pub fn get_event_listeners_count() -> usize {
+    get_token()
+        .event_listeners
+        .values()
+        .map(|it| it.len())
+        .sum()
+}
+
+/// Unsubscribes the principal's canister, once the principal is no longer allowed to manage
+/// event listeners
+fn remove_event_listeners_of(principal: &Principal) {
+    let hub = get_event_hub();
+
+    for (filter, method_name) in get_token()
+        .event_listeners
+        .remove(principal)
+        .unwrap_or_default()
+    {
+        hub.remove_event_listener(&filter, method_name, *principal)
+            .unwrap_or_else(|e| log(format!("Failed to remove event listener - {}", e).as_str()));
+    }
+}
+
+// ------------------ MULTISIG -------------------
+
+#[update]
+fn set_approval_threshold(request: SetApprovalThresholdRequest) -> SetApprovalThresholdResponse {
+    log("currency_token.set_approval_threshold()");
+    count_call("set_approval_threshold");
+
+    let old_threshold = TokenGovernance::set_approval_threshold(
+        &request.role,
+        request.threshold,
+        get_env().caller(),
+    );
+
+    SetApprovalThresholdResponse { old_threshold }
+}
+
+#[query]
+fn get_approval_thresholds() -> GetApprovalThresholdsResponse {
+    log("currency_token.get_approval_thresholds()");
+
+    let thresholds = get_token()
+        .proposals
+        .thresholds
+        .iter()
+        .map(|(role, threshold)| ApprovalThreshold {
+            role: role.clone(),
+            threshold: *threshold,
         })
         .collect();
 
-    GetRecurrentTransferTasksResponse { tasks }
+    GetApprovalThresholdsResponse { thresholds }
 }
 
-#[update(guard = "mint_guard")]
-fn dequeue_recurrent_mint_tasks(
-    request: DequeueRecurrentTaskRequest,
-) -> DequeueRecurrentTaskResponse {
-    log("currency_token.dequeue_recurrent_mint_tasks()");
+#[update]
+fn propose(request: ProposeRequest) -> ProposeResponse {
+    log("currency_token.propose()");
+    count_call("propose");
 
-    let mut succeed = vec![];
+    let (proposal_id, executed) =
+        TokenGovernance::propose(request.action, request.ttl_nano, get_env().caller());
 
-    for task_id in request.task_ids {
-        if get_token().unregister_recurrent_mint_task(task_id) {
-            cron_dequeue(task_id);
-            succeed.push(true);
+    ProposeResponse {
+        proposal_id,
+        executed,
+    }
+}
 
-            continue;
-        }
+#[update]
+fn approve_proposal(request: ProposalIdRequest) -> ApproveProposalResponse {
+    log("currency_token.approve_proposal()");
+    count_call("approve_proposal");
 
-        succeed.push(false);
-    }
+    let executed = TokenGovernance::approve_proposal(request.proposal_id, get_env().caller());
 
-    DequeueRecurrentTaskResponse { succeed }
+    ApproveProposalResponse { executed }
 }
 
 #[query]
-fn get_recurrent_mint_tasks() -> GetRecurrentMintTasksResponse {
-    log("currency_token.get_recurrent_mint_tasks()");
+fn get_proposal(request: ProposalIdRequest) -> GetProposalResponse {
+    log("currency_token.get_proposal()");
 
-    let cron = get_cron_state();
+    let proposal = get_token()
+        .proposals
+        .get_proposal(request.proposal_id, get_env().time());
 
-    let tasks = get_token()
-        .get_recurrent_mint_tasks()
-        .into_iter()
-        .map(|id| {
-            let task = cron.get_task_by_id(&id).unwrap();
-            let task_payload = task.get_payload::<RecurrentMintTask>().unwrap();
+    GetProposalResponse { proposal }
+}
 
-            RecurrentMintTaskExt {
-                task_id: task.id,
-                to: task_payload.to,
-                qty: task_payload.qty,
-                event_payload: task_payload.event_payload,
-                scheduled_at: task.scheduled_at,
-                rescheduled_at: task.rescheduled_at,
-                scheduling_interval: task.scheduling_interval,
-            }
-        })
-        .collect();
+#[query]
+fn get_pending_proposals() -> GetProposalsResponse {
+    log("currency_token.get_pending_proposals()");
 
-    GetRecurrentMintTasksResponse { tasks }
+    let proposals = get_token()
+        .proposals
+        .get_pending_proposals(get_env().time());
+
+    GetProposalsResponse { proposals }
 }
 
-// ------------------ EVENT HUB --------------------
+/// Traps if the role is configured to execute privileged operations only through proposals
+fn approval_not_required(role: &str) {
+    TokenGovernance::approval_not_required(role);
+}
 
-implement_event_emitter!();
-implement_become_event_listener!();
-implement_stop_being_event_listener!();
-implement_get_event_listeners!();
+/// Binds the shared governance logic to the currency token's state, environment and events
+struct TokenGovernance;
+
+impl Governance for TokenGovernance {
+    type Action = ProposalAction;
+
+    fn roles() -> &'static mut RoleRegistry {
+        &mut get_token().roles
+    }
+
+    fn proposals() -> &'static mut ProposalRegistry<ProposalAction> {
+        &mut get_token().proposals
+    }
+
+    fn timelock() -> &'static mut ControllersTimelock {
+        &mut get_token().timelock
+    }
+
+    fn handovers() -> &'static mut HandoverRegistry {
+        &mut get_token().handovers
+    }
+
+    fn time() -> u64 {
+        get_env().time()
+    }
+
+    fn trap(msg: &str) -> ! {
+        get_env().trap(msg)
+    }
+
+    fn log(msg: &str) {
+        log(msg);
+    }
+
+    fn schedule_update_task(update_id: ControllersUpdateId, delay_nano: u64) -> Option<TaskId> {
+        let enqueue_result = get_env().schedule(
+            CronTaskKind::ControllersUpdate,
+            ControllersUpdateTask { update_id },
+            SchedulingInterval {
+                duration_nano: delay_nano,
+                iterations: Iterations::Exact(1),
+            },
+        );
+
+        match enqueue_result {
+            Ok(task_id) => Some(task_id),
+            Err(_) => {
+                log("Candid serialization error met during controllers update enqueue");
+                None
+            }
+        }
+    }
+
+    fn dequeue_task(task_id: TaskId) {
+        get_env().dequeue(task_id);
+    }
+
+    fn emit(event: GovernanceEvent<ProposalAction>) {
+        match event {
+            GovernanceEvent::ControllersUpdated {
+                role,
+                new_controllers,
+            } => emit_logged(|seq| ControllersUpdateEvent {
+                seq,
+                kind: ControllerType::from(role.as_str()),
+                new_controllers,
+            }),
+            GovernanceEvent::ControllersUpdateScheduled(update) => {
+                emit_logged(|seq| ControllersUpdateScheduledEvent {
+                    seq,
+                    update_id: update.id,
+                    kind: ControllerType::from(update.change.get_role().as_str()),
+                    change: update.change,
+                    scheduled_by: update.scheduled_by,
+                    execute_at: update.execute_at,
+                })
+            }
+            GovernanceEvent::ControllersUpdateCancelled {
+                update_id,
+                role,
+                cancelled_by,
+            } => emit_logged(|seq| ControllersUpdateCancelledEvent {
+                seq,
+                update_id,
+                kind: ControllerType::from(role.as_str()),
+                cancelled_by,
+            }),
+            GovernanceEvent::HandoverProposed {
+                role,
+                new_controllers,
+                proposed_by,
+            } => emit_logged(|seq| ControllersHandoverProposedEvent {
+                seq,
+                kind: ControllerType::from(role.as_str()),
+                new_controllers,
+                proposed_by,
+            }),
+            GovernanceEvent::HandoverAccepted { role, accepted_by } => {
+                emit_logged(|seq| ControllersHandoverAcceptedEvent {
+                    seq,
+                    kind: ControllerType::from(role.as_str()),
+                    accepted_by,
+                })
+            }
+            GovernanceEvent::HandoverCancelled { role, cancelled_by } => {
+                emit_logged(|seq| ControllersHandoverCancelledEvent {
+                    seq,
+                    kind: ControllerType::from(role.as_str()),
+                    cancelled_by,
+                })
+            }
+            GovernanceEvent::ProposalCreated {
+                proposal_id,
+                proposer,
+                role,
+                action,
+                expires_at,
+            } => emit_logged(|seq| ProposalCreatedEvent {
+                seq,
+                proposal_id,
+                proposer,
+                role,
+                action,
+                expires_at,
+            }),
+            GovernanceEvent::ProposalApproved {
+                proposal_id,
+                approver,
+                approvals,
+            } => emit_logged(|seq| ProposalApprovedEvent {
+                seq,
+                proposal_id,
+                approver,
+                approvals,
+            }),
+            GovernanceEvent::ProposalExecuted { proposal_id } => {
+                emit_logged(|seq| ProposalExecutedEvent { seq, proposal_id })
+            }
+        }
+    }
+
+    fn required_role(action: &ProposalAction) -> Role {
+        let roles = &get_token().roles;
+
+        match action {
+            ProposalAction::Mint(_) => String::from(MINT_ROLE),
+            ProposalAction::UpdateInfo(_) => String::from(INFO_ROLE),
+            ProposalAction::UpdateControllers(r) => roles.get_admin_role(&r.role),
+            ProposalAction::GrantRole(r) => roles.get_admin_role(&r.role),
+            ProposalAction::RevokeRole(r) => roles.get_admin_role(&r.role),
+            ProposalAction::SetApprovalThreshold(r) => roles.get_admin_role(&r.role),
+        }
+    }
+
+    fn execute_action(action: ProposalAction, proposer: Principal) {
+        match action {
+            ProposalAction::Mint(request) => _mint(request, proposer),
+            ProposalAction::UpdateInfo(request) => {
+                _update_info(request);
+            }
+            ProposalAction::UpdateControllers(request) => {
+                Self::propose_role_controllers(request, proposer);
+            }
+            ProposalAction::GrantRole(request) => {
+                Self::schedule_controllers_change(ControllersChange::GrantRole(request), proposer);
+            }
+            ProposalAction::RevokeRole(request) => {
+                Self::schedule_role_revocation(request, proposer);
+            }
+            ProposalAction::SetApprovalThreshold(request) => {
+                Self::apply_approval_threshold(&request.role, request.threshold);
+            }
+        };
+    }
+
+    fn on_role_members_removed(role: &str, removed: &[Principal]) {
+        if role == EVENT_LISTENERS_ROLE {
+            removed.iter().for_each(remove_event_listeners_of);
+        }
+    }
+}
+
+// ----------------- ESCROW --------------------
+
+#[update]
+fn create_escrow(request: CreateEscrowRequest) -> CreateEscrowResponse {
+    log("currency_token.create_escrow()");
+    count_call("create_escrow");
+
+    let token = get_token();
+    let caller = get_env().caller();
+    let now = get_env().time();
+
+    let escrow_id = match token.create_escrow(caller, request.clone(), now) {
+        Ok(id) => id,
+        Err(e) => get_env().trap(format!("Escrow creation failed - {}", e).as_str()),
+    };
+
+    let enqueue_result = get_env().schedule(
+        CronTaskKind::EscrowRefund,
+        EscrowRefundTask { escrow_id },
+        SchedulingInterval {
+            duration_nano: request.deadline - now,
+            iterations: Iterations::Exact(1),
+        },
+    );
+
+    match enqueue_result {
+        Ok(task_id) => token.register_escrow_refund_task(escrow_id, task_id),
+        Err(_) => log("Candid serialization error met during escrow refund enqueue"),
+    };
+
+    emit_logged(|seq| EscrowCreatedEvent {
+        seq,
+        escrow_id,
+        payer: caller,
+        payee: request.payee,
+        arbiter: request.arbiter,
+        qty: request.qty,
+        deadline: request.deadline,
+        event_payload: request.event_payload,
+    });
+
+    CreateEscrowResponse { escrow_id }
+}
+
+#[update]
+fn release_escrow(request: EscrowIdRequest) {
+    log("currency_token.release_escrow()");
+    count_call("release_escrow");
+
+    let token = get_token();
+    let caller = get_env().caller();
+    let refund_task_id = token.get_escrow_refund_task(request.escrow_id);
+
+    match token.release_escrow(caller, request.escrow_id) {
+        Ok(escrow) => {
+            if let Some(task_id) = refund_task_id {
+                get_env().dequeue(task_id);
+            }
+
+            emit_logged(|seq| EscrowReleasedEvent {
+                seq,
+                escrow_id: escrow.id,
+                payee: escrow.payee,
+                released_by: caller,
+                qty: escrow.qty,
+                event_payload: escrow.event_payload,
+            });
+        }
+        Err(e) => get_env().trap(format!("Escrow release failed - {}", e).as_str()),
+    }
+}
+
+#[update]
+fn refund_escrow(request: EscrowIdRequest) {
+    log("currency_token.refund_escrow()");
+    count_call("refund_escrow");
+
+    let token = get_token();
+    let refund_task_id = token.get_escrow_refund_task(request.escrow_id);
+
+    match token.refund_escrow(request.escrow_id, get_env().time()) {
+        Ok(escrow) => {
+            if let Some(task_id) = refund_task_id {
+                get_env().dequeue(task_id);
+            }
+
+            emit_logged(|seq| EscrowRefundedEvent {
+                seq,
+                escrow_id: escrow.id,
+                payer: escrow.payer,
+                qty: escrow.qty,
+                event_payload: escrow.event_payload,
+            });
+        }
+        Err(e) => get_env().trap(format!("Escrow refund failed - {}", e).as_str()),
+    }
+}
+
+#[query]
+fn get_escrow(request: EscrowIdRequest) -> GetEscrowResponse {
+    log("currency_token.get_escrow()");
+
+    let escrow = get_token().get_escrow(request.escrow_id);
+
+    GetEscrowResponse { escrow }
+}
+
+#[query]
+fn get_escrows_of(request: GetEscrowsOfRequest) -> GetEscrowsOfResponse {
+    log("currency_token.get_escrows_of()");
+
+    let escrows = get_token().get_escrows_of(&request.participant);
+
+    GetEscrowsOfResponse { escrows }
+}
+
+// --------------- RECURRENCE ------------------
+
+implement_cron!();
+
+/// Records the heartbeat before dispatching the due tasks, so the heartbeat is observed even
+/// when there is nothing to execute
+#[heartbeat]
+fn heartbeat() {
+    let now = get_env().time();
+    get_token().metrics.record_heartbeat(now);
+
+    for task in get_cron_state().iterate(now) {
+        _cron_task_handler(task);
+    }
+}
+
+fn _cron_task_handler(task: ScheduledTask) {
+    _execute_cron_task(task.id, task.get_kind(), &task);
+}
+
+fn _execute_cron_task<P: TaskPayload>(task_id: TaskId, kind: u8, task: &P) {
+    check_cycles();
+
+    match kind.try_into() {
+        Ok(CronTaskKind::RecurrentTransfer) => {
+            _recurrent_transfer(task_id, task.decode_payload().unwrap())
+        }
+        Ok(CronTaskKind::RecurrentMint) => _recurrent_mint(task_id, task.decode_payload().unwrap()),
+        Ok(CronTaskKind::EscrowRefund) => _escrow_refund(task.decode_payload().unwrap()),
+        Ok(CronTaskKind::ControllersUpdate) => _controllers_update(task.decode_payload().unwrap()),
+        Err(_) => log("Invalid cron task handler"),
+    }
+}
+
+fn _recurrent_transfer(task_id: TaskId, task: RecurrentTransferTask) {
+    log("currency_token._recurrent_transfer()");
+
+    let (from, to) = (task.from, task.to);
+
+    if get_token().cycles.is_suspended() {
+        log("Recurrent transferring skipped - the canister is low on cycles");
+    } else {
+        _execute_recurrent_transfer(task);
+    }
+
+    if is_recurrent_task_completed(task_id) {
+        get_token().unregister_recurrent_transfer_task(from, task_id);
+
+        emit_logged(|seq| RecurrentTaskCompletedEvent {
+            seq,
+            task_id,
+            from: Some(from),
+            to,
+        });
+    }
+}
+
+fn _execute_recurrent_transfer(task: RecurrentTransferTask) {
+    match get_token().transfer(task.from, task.to, task.qty) {
+        Ok(_) => {
+            emit_logged(|seq| TokenMoveEvent {
+                seq,
+                from: Some(task.from),
+                to: Some(task.to),
+                qty: task.qty,
+                event_payload: task.event_payload,
+                forced: None,
+            });
+        }
+        Err(e) => {
+            log(format!("Recurrent transferring failed with error: {}", e).as_str());
+            get_token().metrics.record_failed_recurrent_execution();
+        }
+    };
+}
+
+fn _recurrent_mint(task_id: TaskId, task: RecurrentMintTask) {
+    log("currency_token._recurrent_mint()");
+
+    let to = task.to;
+
+    if get_token().cycles.is_suspended() {
+        log("Recurrent minting skipped - the canister is low on cycles");
+    } else {
+        _execute_recurrent_mint(task);
+    }
+
+    if is_recurrent_task_completed(task_id) {
+        get_token().unregister_recurrent_mint_task(task_id);
+
+        emit_logged(|seq| RecurrentTaskCompletedEvent {
+            seq,
+            task_id,
+            from: None,
+            to,
+        });
+    }
+}
+
+fn _execute_recurrent_mint(task: RecurrentMintTask) {
+    match get_token().mint_by(task.minter, task.to, task.qty, get_env().time()) {
+        Ok(_) => {
+            emit_logged(|seq| TokenMoveEvent {
+                seq,
+                from: None,
+                to: Some(task.to),
+                qty: task.qty,
+                event_payload: task.event_payload,
+                forced: None,
+            });
+        }
+        Err(e) => {
+            log(format!("Recurrent minting failed with error: {}", e).as_str());
+            get_token().metrics.record_failed_recurrent_execution();
+        }
+    };
+}
+
+/// The scheduler drops a task before executing its last iteration, so a task missing from
+/// the scheduler's state while being executed has no iterations left
+fn is_recurrent_task_completed(task_id: TaskId) -> bool {
+    !get_env().has_task(task_id)
+}
+
+fn _escrow_refund(task: EscrowRefundTask) {
+    log("currency_token._escrow_refund()");
+
+    match get_token().refund_escrow(task.escrow_id, get_env().time()) {
+        Ok(escrow) => {
+            emit_logged(|seq| EscrowRefundedEvent {
+                seq,
+                escrow_id: escrow.id,
+                payer: escrow.payer,
+                qty: escrow.qty,
+                event_payload: escrow.event_payload,
+            });
+        }
+        Err(e) => log(format!("Escrow refund failed with error: {}", e).as_str()),
+    };
+}
+
+fn _controllers_update(task: ControllersUpdateTask) {
+    log("currency_token._controllers_update()");
+
+    TokenGovernance::apply_due_controllers_update(task.update_id);
+}
+
+#[update]
+fn dequeue_recurrent_transfer_tasks(
+    request: DequeueRecurrentTaskRequest,
+) -> DequeueRecurrentTaskResponse {
+    log("currency_token.dequeue_recurrent_transfer_tasks()");
+    count_call("dequeue_recurrent_transfer_tasks");
+
+    let caller = get_env().caller();
+    let mut succeed = vec![];
+
+    for task_id in request.task_ids {
+        if get_token().unregister_recurrent_transfer_task(caller, task_id) {
+            let task_payload = get_env()
+                .get_task::<RecurrentTransferTask>(task_id)
+                .unwrap()
+                .payload;
+
+            get_env().dequeue(task_id);
+            succeed.push(true);
+
+            emit_logged(|seq| RecurrentTaskCancelledEvent {
+                seq,
+                task_id,
+                from: Some(task_payload.from),
+                to: task_payload.to,
+                cancelled_by: caller,
+            });
+
+            continue;
+        }
+
+        succeed.push(false);
+    }
+
+    DequeueRecurrentTaskResponse { succeed }
+}
+
+#[query]
+fn get_recurrent_transfer_tasks(
+    request: GetRecurrentTransferTasksRequest,
+) -> GetRecurrentTransferTasksResponse {
+    log("currency_token.get_recurrent_transfer_tasks()");
+
+    let tasks = get_token()
+        .get_recurrent_transfer_tasks(request.owner)
+        .into_iter()
+        .map(|id| {
+            let task = get_env().get_task::<RecurrentTransferTask>(id).unwrap();
+            let task_payload = task.payload;
+
+            RecurrentTransferTaskExt {
+                task_id: task.id,
+                from: task_payload.from,
+                to: task_payload.to,
+                qty: task_payload.qty,
+                event_payload: task_payload.event_payload,
+                scheduled_at: task.scheduled_at,
+                rescheduled_at: task.rescheduled_at,
+                scheduling_interval: task.scheduling_interval,
+            }
+        })
+        .collect();
+
+    GetRecurrentTransferTasksResponse { tasks }
+}
+
+#[update(guard = "mint_guard")]
+fn dequeue_recurrent_mint_tasks(
+    request: DequeueRecurrentTaskRequest,
+) -> DequeueRecurrentTaskResponse {
+    log("currency_token.dequeue_recurrent_mint_tasks()");
+    count_call("dequeue_recurrent_mint_tasks");
+
+    let caller = get_env().caller();
+    let mut succeed = vec![];
+
+    for task_id in request.task_ids {
+        if get_token().unregister_recurrent_mint_task(task_id) {
+            let task_payload = get_env()
+                .get_task::<RecurrentMintTask>(task_id)
+                .unwrap()
+                .payload;
+
+            get_env().dequeue(task_id);
+            succeed.push(true);
+
+            emit_logged(|seq| RecurrentTaskCancelledEvent {
+                seq,
+                task_id,
+                from: None,
+                to: task_payload.to,
+                cancelled_by: caller,
+            });
+
+            continue;
+        }
+
+        succeed.push(false);
+    }
+
+    DequeueRecurrentTaskResponse { succeed }
+}
+
+#[query]
+fn get_recurrent_mint_tasks() -> GetRecurrentMintTasksResponse {
+    log("currency_token.get_recurrent_mint_tasks()");
+
+    let tasks = get_token()
+        .get_recurrent_mint_tasks()
+        .into_iter()
+        .map(|id| {
+            let task = get_env().get_task::<RecurrentMintTask>(id).unwrap();
+            let task_payload = task.payload;
+
+            RecurrentMintTaskExt {
+                task_id: task.id,
+                minter: task_payload.minter,
+                to: task_payload.to,
+                qty: task_payload.qty,
+                event_payload: task_payload.event_payload,
+                scheduled_at: task.scheduled_at,
+                rescheduled_at: task.rescheduled_at,
+                scheduling_interval: task.scheduling_interval,
+            }
+        })
+        .collect();
+
+    GetRecurrentMintTasksResponse { tasks }
+}
+
+// ----------------- MINT QUOTAS -----------------
+
+#[update(guard = "admin_guard")]
+fn set_mint_quota(request: SetMintQuotaRequest) {
+    log("currency_token.set_mint_quota()");
+    count_call("set_mint_quota");
+
+    approval_not_required(ADMIN_ROLE);
+
+    if let Some(rate_limit) = &request.rate_limit {
+        if rate_limit.period_nano == 0 {
+            get_env().trap("Mint rate limit period should be greater than zero");
+        }
+    }
+
+    get_token().mint_quotas.set_quota(
+        request.minter,
+        request.total_quota,
+        request.rate_limit,
+        get_env().time(),
+    );
+}
+
+#[query]
+fn get_mint_quota(request: GetMintQuotaRequest) -> GetMintQuotaResponse {
+    log("currency_token.get_mint_quota()");
+
+    let now = get_env().time();
+    let quotas = &get_token().mint_quotas;
+    let (remaining_total, remaining_in_period) = quotas.get_remaining(&request.minter, now);
+
+    GetMintQuotaResponse {
+        quota: quotas.get_quota(&request.minter, now),
+        remaining_total,
+        remaining_in_period,
+    }
+}
+
+// ------------------ CYCLES --------------------
+
+#[update]
+fn wallet_receive() -> WalletReceiveResponse {
+    log("currency_token.wallet_receive()");
+    count_call("wallet_receive");
+
+    let accepted = get_env().accept_cycles();
+    check_cycles();
+
+    WalletReceiveResponse { accepted }
+}
+
+#[query]
+fn get_cycles_balance() -> GetCyclesBalanceResponse {
+    log("currency_token.get_cycles_balance()");
+
+    let cycles = &get_token().cycles;
+
+    GetCyclesBalanceResponse {
+        balance: get_env().cycles_balance(),
+        low_water_mark: cycles.low_water_mark,
+        suspended: cycles.is_suspended(),
+    }
+}
+
+#[update(guard = "admin_guard")]
+fn set_cycles_low_water_mark(request: SetCyclesLowWaterMarkRequest) {
+    log("currency_token.set_cycles_low_water_mark()");
+    count_call("set_cycles_low_water_mark");
+
+    approval_not_required(ADMIN_ROLE);
+
+    let cycles = &mut get_token().cycles;
+    cycles.low_water_mark = request.low_water_mark;
+    cycles.suspend_on_low_cycles = request.suspend_on_low_cycles;
+
+    check_cycles();
+}
+
+/// Raises a `LowCyclesEvent` once the balance drops below the low-water mark. The alert
+/// itself is delivered to listeners even if the rest of event delivery is suspended.
+fn check_cycles() {
+    let balance = get_env().cycles_balance();
+
+    if let Some(low_water_mark) = get_token().cycles.check(balance) {
+        log(format!(
+            "Cycles balance {} dropped below the low-water mark {}",
+            balance, low_water_mark
+        )
+        .as_str());
+
+        let event = log_event(|seq| LowCyclesEvent {
+            seq,
+            balance,
+            low_water_mark,
+        });
+
+        get_env().emit(event);
+    }
+}
+
+// ------------------ METRICS --------------------
+
+#[query]
+fn get_metrics() -> GetMetricsResponse {
+    log("currency_token.get_metrics()");
+
+    _get_metrics()
+}
+
+fn _get_metrics() -> GetMetricsResponse {
+    let token = get_token();
+
+    GetMetricsResponse {
+        heap_memory_bytes: heap_memory_bytes(),
+        stable_memory_bytes: stable_memory_bytes(),
+        cycles_balance: get_env().cycles_balance(),
+        accounts_count: token.get_holders_count() as u64,
+        total_supply: token.total_supply,
+        recurrent_transfer_tasks_count: token
+            .recurrent_transfer_tasks
+            .values()
+            .map(|tasks| tasks.len() as u64)
+            .sum(),
+        recurrent_mint_tasks_count: token.recurrent_mint_tasks.len() as u64,
+        event_listeners_count: get_event_listeners_count() as u64,
+        call_counters: token.metrics.get_call_counters(),
+        failed_recurrent_executions: token.metrics.failed_recurrent_executions,
+        last_heartbeat_at: token.metrics.last_heartbeat_at,
+    }
+}
+
+fn encode_prometheus_metrics(metrics: &GetMetricsResponse) -> String {
+    PrometheusWriter::new("currency_token")
+        .gauge(
+            "heap_memory_bytes",
+            "Heap memory size",
+            metrics.heap_memory_bytes,
+        )
+        .gauge(
+            "stable_memory_bytes",
+            "Stable memory size",
+            metrics.stable_memory_bytes,
+        )
+        .gauge("cycles_balance", "Cycles balance", metrics.cycles_balance)
+        .gauge(
+            "accounts",
+            "Number of accounts with non-zero balance",
+            metrics.accounts_count,
+        )
+        .gauge("total_supply", "Total supply", metrics.total_supply)
+        .gauge(
+            "recurrent_transfer_tasks",
+            "Number of recurrent transfer tasks",
+            metrics.recurrent_transfer_tasks_count,
+        )
+        .gauge(
+            "recurrent_mint_tasks",
+            "Number of recurrent mint tasks",
+            metrics.recurrent_mint_tasks_count,
+        )
+        .gauge(
+            "event_listeners",
+            "Number of event listeners",
+            metrics.event_listeners_count,
+        )
+        .labeled_counter(
+            "calls_total",
+            "Update calls per method",
+            "method",
+            &metrics.call_counters,
+        )
+        .counter(
+            "failed_recurrent_executions_total",
+            "Failed recurrent task executions",
+            metrics.failed_recurrent_executions,
+        )
+        .gauge(
+            "last_heartbeat_timestamp_nanos",
+            "Last time the canister heartbeat ran",
+            metrics.last_heartbeat_at.unwrap_or_default(),
+        )
+        .finish()
+}
+
+fn log(msg: &str) {
+    get_env().log(msg);
+}
+
+fn count_call(method: &str) {
+    get_token().metrics.record_call(method);
+}
+
+// ------------------- AUDIT ---------------------
+
+/// Recomputes the supply from the balances and the escrows and checks that every scheduler
+/// task the token keeps track of is still scheduled with the expected kind and payload
+#[query]
+fn audit_state() -> AuditStateResponse {
+    log("currency_token.audit_state()");
+
+    let token = get_token();
+    let (balances_sum, escrowed_sum) = token.compute_supply();
+    let supply_consistent = token.is_supply_consistent();
+
+    let mut dangling_task_ids = vec![];
+
+    for (from, task_ids) in &token.recurrent_transfer_tasks {
+        for task_id in task_ids {
+            let scheduled = is_task_scheduled(
+                *task_id,
+                CronTaskKind::RecurrentTransfer,
+                |task: RecurrentTransferTask| task.from == *from,
+            );
+
+            if !scheduled {
+                dangling_task_ids.push(*task_id);
+            }
+        }
+    }
+
+    for task_id in &token.recurrent_mint_tasks {
+        let scheduled = is_task_scheduled(
+            *task_id,
+            CronTaskKind::RecurrentMint,
+            |_: RecurrentMintTask| true,
+        );
+
+        if !scheduled {
+            dangling_task_ids.push(*task_id);
+        }
+    }
+
+    for (escrow_id, task_id) in &token.escrow_refund_tasks {
+        let scheduled = is_task_scheduled(
+            *task_id,
+            CronTaskKind::EscrowRefund,
+            |task: EscrowRefundTask| {
+                task.escrow_id == *escrow_id && token.escrows.contains_key(escrow_id)
+            },
+        );
+
+        if !scheduled {
+            dangling_task_ids.push(*task_id);
+        }
+    }
+
+    for (update_id, task_id) in &token.timelock.update_tasks {
+        let scheduled = is_task_scheduled(
+            *task_id,
+            CronTaskKind::ControllersUpdate,
+            |task: ControllersUpdateTask| task.update_id == *update_id,
+        );
+
+        if !scheduled {
+            dangling_task_ids.push(*task_id);
+        }
+    }
+
+    dangling_task_ids.sort_unstable();
+
+    // the other way around - scheduled tasks none of the registries know about
+    let mut orphaned_task_ids = get_env()
+        .get_scheduled_tasks()
+        .into_iter()
+        .filter(|(task_id, kind)| !is_task_registered(*task_id, *kind))
+        .map(|(task_id, _)| task_id)
+        .collect::<Vec<_>>();
+
+    orphaned_task_ids.sort_unstable();
+
+    AuditStateResponse {
+        total_supply: token.total_supply,
+        balances_sum,
+        escrowed_sum,
+        supply_consistent,
+        consistent: supply_consistent
+            && dangling_task_ids.is_empty()
+            && orphaned_task_ids.is_empty(),
+        dangling_task_ids,
+        orphaned_task_ids,
+    }
+}
+
+fn is_task_registered(task_id: TaskId, kind: u8) -> bool {
+    let token = get_token();
+
+    match kind.try_into() {
+        Ok(CronTaskKind::RecurrentTransfer) => token
+            .recurrent_transfer_tasks
+            .values()
+            .any(|task_ids| task_ids.contains(&task_id)),
+        Ok(CronTaskKind::RecurrentMint) => token.recurrent_mint_tasks.contains(&task_id),
+        Ok(CronTaskKind::EscrowRefund) => {
+            token.escrow_refund_tasks.values().any(|it| *it == task_id)
+        }
+        Ok(CronTaskKind::ControllersUpdate) => token
+            .timelock
+            .update_tasks
+            .values()
+            .any(|it| *it == task_id),
+        Err(_) => false,
+    }
+}
+
+fn is_task_scheduled<T, F>(task_id: TaskId, kind: CronTaskKind, matches: F) -> bool
+where
+    T: CandidType + DeserializeOwned,
+    F: FnOnce(T) -> bool,
+{
+    let kind = kind as u8;
+
+    match get_env().get_task::<T>(task_id) {
+        Some(task) if task.kind == kind => matches(task.payload),
+        _ => false,
+    }
+}
+
+/// Deterministic hash of the balances, the token info and the controllers, letting auditors
+/// check that two deployments hold identical state without downloading it
+#[query]
+fn get_state_hash() -> GetStateHashResponse {
+    log("currency_token.get_state_hash()");
+
+    GetStateHashResponse {
+        hash: get_token().get_state_hash().to_vec(),
+    }
+}
+
+// -------------------- HTTP ----------------------
+
+/// Serves token data as JSON for browsers and explorers. `/info` and `/supply` responses are
+/// certified, the rest can only be fetched through the raw (uncertified) domain.
+#[query]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    log("currency_token.http_request()");
+
+    if request.method != "GET" {
+        return error_response(405, "Method not allowed");
+    }
+
+    let (segments, params) = parse_url(&request.url);
+
+    let result = match segments.as_slice() {
+        ["metrics"] => Ok(text_response(
+            200,
+            "text/plain; version=0.0.4",
+            encode_prometheus_metrics(&_get_metrics()),
+        )),
+        ["info"] => Ok(certified_http_response("/info")),
+        ["supply"] => Ok(certified_http_response("/supply")),
+        ["balance", account_owner] => http_balance(account_owner),
+        ["holders"] => http_holders(&params),
+        ["transactions"] => http_transactions(&params),
+        _ => Err(error_response(404, "Not found")),
+    };
+
+    result.unwrap_or_else(|e| e)
+}
+
+fn http_balance(account_owner: &str) -> Result<HttpResponse, HttpResponse> {
+    let account_owner = parse_principal(account_owner)?;
+    let balance = get_token().balance_of(&account_owner);
+
+    let body = Json::object(vec![
+        ("account_owner", account_owner.into()),
+        ("balance", balance.into()),
+    ]);
+
+    Ok(json_response(200, body.encode().into_bytes()))
+}
+
+fn http_holders(params: &[(&str, &str)]) -> Result<HttpResponse, HttpResponse> {
+    let cursor = get_principal_param(params, "cursor")?;
+    let limit = get_num_param(params, "limit")?.unwrap_or(DEFAULT_HTTP_PAGE_SIZE as u64);
+
+    let (holders, next_cursor) = get_token().get_holders(cursor, limit as usize);
+
+    let body = Json::object(vec![
+        (
+            "holders",
+            Json::Array(
+                holders
+                    .into_iter()
+                    .map(|(account_owner, balance)| {
+                        Json::object(vec![
+                            ("account_owner", account_owner.into()),
+                            ("balance", balance.into()),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        ("next_cursor", next_cursor.into()),
+    ]);
+
+    Ok(json_response(200, body.encode().into_bytes()))
+}
+
+/// Token moves are taken from the event log, so only the events it still holds are served
+fn http_transactions(params: &[(&str, &str)]) -> Result<HttpResponse, HttpResponse> {
+    let event_log = &get_token().event_log;
+
+    let start = get_num_param(params, "start")?.unwrap_or_else(|| event_log.get_oldest_seq());
+    // the log never returns more than a page, so a bigger limit would never yield `next`
+    let limit = get_num_param(params, "limit")?
+        .unwrap_or(DEFAULT_HTTP_PAGE_SIZE as u64)
+        .min(MAX_EVENTS_PAGE_SIZE as u64);
+
+    let filter = Some(EventTopicFilter {
+        name: Some(String::from("TokenMoveEvent")),
+        principal: None,
+    });
+    let records = event_log.get_events(start, limit as usize, &filter);
+
+    let next = if records.len() as u64 == limit {
+        records.last().map(|it| it.seq + 1)
+    } else {
+        None
+    };
+
+    let transactions = records
+        .iter()
+        .filter_map(Transaction::from_record)
+        .map(|tx| {
+            Json::object(vec![
+                ("seq", tx.seq.into()),
+                ("timestamp", tx.timestamp.into()),
+                ("from", tx.from.into()),
+                ("to", tx.to.into()),
+                ("qty", tx.qty.into()),
+            ])
+        })
+        .collect();
+
+    let body = Json::object(vec![
+        ("transactions", Json::Array(transactions)),
+        ("next", next.into()),
+    ]);
+
+    Ok(json_response(200, body.encode().into_bytes()))
+}
+
+fn certified_http_responses() -> CertifiedResponses {
+    let token = get_token();
+
+    let info = info_json(&token.info);
+    let supply = Json::object(vec![("total_supply", token.total_supply.into())]);
+
+    vec![
+        ("/info", info.encode().into_bytes()),
+        ("/supply", supply.encode().into_bytes()),
+    ]
+}
+
+fn certified_http_response(path: &str) -> HttpResponse {
+    let responses = certified_http_responses();
+
+    let body = responses
+        .iter()
+        .find(|(p, _)| *p == path)
+        .map(|(_, body)| body.clone())
+        .unwrap();
+
+    let mut response = json_response(200, body);
+
+    if let Some(header) = certificate_header(&responses, path) {
+        response.headers.push(header);
+    }
+
+    response
+}
+
+fn certify_http_responses() {
+    certify_responses(&certified_http_responses());
+}
+
+fn info_json(info: &TokenInfo) -> Json {
+    let logo = match &info.logo {
+        None => Json::Null,
+        Some(TokenLogo::DataUrl(url)) => url.as_str().into(),
+        Some(TokenLogo::Blob { content_type, data }) => Json::object(vec![
+            ("content_type", content_type.as_str().into()),
+            ("size", Json::Nat(data.len() as u32)),
+        ]),
+    };
+
+    let metadata = info
+        .metadata
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                MetadataValue::Nat(n) => (*n).into(),
+                MetadataValue::Int(i) => (*i).into(),
+                MetadataValue::Text(text) => text.as_str().into(),
+                MetadataValue::Blob(blob) => {
+                    Json::Array(blob.iter().map(|b| Json::Nat(*b as u32)).collect())
+                }
+            };
+
+            (key.clone(), value)
+        })
+        .collect();
+
+    Json::object(vec![
+        ("name", info.name.as_str().into()),
+        ("symbol", info.symbol.as_str().into()),
+        ("decimals", Json::Nat(info.decimals as u32)),
+        ("logo", logo),
+        ("description", info.description.clone().into()),
+        ("website", info.website.clone().into()),
+        ("metadata", Json::Object(metadata)),
+    ])
+}
+
+// ------------------ EVENT LOG --------------------
+
+#[query]
+fn get_events(request: GetEventsRequest) -> GetEventsResponse {
+    log("currency_token.get_events()");
+
+    let event_log = &get_token().event_log;
+    let events = event_log.get_events(
+        request.from_seq,
+        request.limit as usize,
+        &request.topic_filter,
+    );
+
+    GetEventsResponse {
+        events,
+        oldest_seq: event_log.get_oldest_seq(),
+        next_seq: event_log.get_next_seq(),
+    }
+}
+
+/// Attaches an already deployed archive canister - the token never spawns archives itself.
+#[update(guard = "admin_guard")]
+fn set_event_archive(request: SetEventArchiveRequest) {
+    log("currency_token.set_event_archive()");
+    count_call("set_event_archive");
+
+    if request.local_capacity == Some(0) {
+        get_env().trap("Local capacity of the event log should be positive");
+    }
+
+    let event_log = &mut get_token().event_log;
+    event_log.archive = request.archive;
+
+    if let Some(local_capacity) = request.local_capacity {
+        event_log.capacity = local_capacity as usize;
+    }
+}
+
+/// Returns token moves with `start <= seq < start + length`. Moves which were already handed
+/// over to archive canisters are not returned, the ranges to fetch from them are listed instead.
+#[query]
+fn get_transactions(request: GetTransactionsRequest) -> GetTransactionsResponse {
+    log("currency_token.get_transactions()");
+
+    let event_log = &get_token().event_log;
+    let to_seq = request
+        .start
+        .saturating_add(request.length.min(MAX_EVENTS_PAGE_SIZE as u64));
+
+    let transactions = event_log
+        .get_events_range(request.start, to_seq)
+        .into_iter()
+        .filter_map(Transaction::from_record)
+        .collect();
+
+    let archived_ranges = event_log
+        .get_archived_spans(request.start, to_seq)
+        .into_iter()
+        .map(|span| ArchivedTransactionsRange {
+            start: span.from_seq,
+            length: span.to_seq - span.from_seq,
+            callback: ArchiveCallback {
+                canister_id: span.archive,
+                method: String::from("get_transactions"),
+            },
+        })
+        .collect();
+
+    GetTransactionsResponse {
+        transactions,
+        first_seq: event_log.get_oldest_seq(),
+        archived_ranges,
+    }
+}
+
+/// Stores the event in the event log under the next sequence number and then delivers
+/// it to the listeners, unless event delivery is suspended because of low cycles
+fn emit_logged<T: LoggedEvent + IEvent, F: FnOnce(EventSeq) -> T>(make_event: F) {
+    check_cycles();
+
+    let event = log_event(make_event);
+
+    // every change of the supply or the info is followed by an event, while most of the
+    // events (transfers first of all) leave the certified responses intact
+    if std::mem::take(&mut get_token().certified_data_outdated) {
+        certify_http_responses();
+    }
+
+    if !get_token().cycles.is_suspended() {
+        get_env().emit(event);
+    }
+}
+
+/// Stores the event in the event log under the next sequence number, flushing evicted
+/// events to the archive if there is one
+fn log_event<T: LoggedEvent, F: FnOnce(EventSeq) -> T>(make_event: F) -> T {
+    let seq = get_token().event_log.get_next_seq();
+    let event = make_event(seq);
+
+    match encode_one(&event) {
+        Ok(payload) => {
+            get_token().event_log.push(
+                event.get_name(),
+                event.get_principals(),
+                payload,
+                get_env().time(),
+            );
+        }
+        Err(_) => get_env().trap("Candid serialization error met during event logging"),
+    };
+
+    if let Some((archive, batch)) = get_token().event_log.take_archive_batch() {
+        get_env().append_to_archive(archive, batch, move |accepted| {
+            if accepted {
+                get_token().event_log.confirm_archive_batch(archive);
+            } else {
+                log("Failed to archive evicted events, they will be retried with the next event");
+                get_token().event_log.return_archive_batch();
+
+                if get_token().event_log.is_archive_queue_full() {
+                    log("The archive queue is full, newly evicted events are dropped until the archive accepts them");
+                }
+            }
+        });
+    }
+
+    event
+}
+
+// ------------------ EVENT HUB --------------------
+
+implement_event_emitter!();
+implement_get_event_listeners!();
+
+/// Same as the event hub's own endpoint, but keeps track of the listeners in order to cap them
+/// and to unsubscribe them along with their role
+#[update(guard = "event_listeners_guard")]
+fn _add_event_listeners(request: AddEventListenersRequest) {
+    log("currency_token._add_event_listeners()");
+    count_call("_add_event_listeners");
+
+    let token = get_token();
+
+    let mut new_listeners = HashSet::new();
+    for listener in request.listeners {
+        let entry = (listener.filter, listener.endpoint.method_name);
+
+        let known = token
+            .event_listeners
+            .get(&listener.endpoint.canister_id)
+            .map(|it| it.contains(&entry))
+            .unwrap_or_default();
+
+        if !known {
+            new_listeners.insert((listener.endpoint.canister_id, entry));
+        }
+    }
+
+    if get_event_listeners_count() + new_listeners.len() > MAX_EVENT_LISTENERS {
+        get_env().trap(format!("Event listeners cap of {} exceeded", MAX_EVENT_LISTENERS).as_str());
+    }
+
+    let hub = get_event_hub();
+    for (canister_id, (filter, method_name)) in new_listeners {
+        hub.add_event_listener(filter.clone(), method_name.clone(), canister_id);
+
+        token
+            .event_listeners
+            .entry(canister_id)
+            .or_default()
+            .insert((filter, method_name));
+    }
+}
+
+#[update(guard = "event_listeners_guard")]
+fn _remove_event_listeners(request: RemoveEventListenersRequest) {
+    log("currency_token._remove_event_listeners()");
+    count_call("_remove_event_listeners");
+
+    let token = get_token();
+    let hub = get_event_hub();
+
+    for (idx, listener) in request.listeners.into_iter().enumerate() {
+        let canister_id = listener.endpoint.canister_id;
+        let entry = (listener.filter, listener.endpoint.method_name);
+
+        let removed = token
+            .event_listeners
+            .get_mut(&canister_id)
+            .map(|it| it.remove(&entry))
+            .unwrap_or_default();
+
+        if !removed {
+            get_env()
+                .trap(format!("Unable to remove listener #{} - no such listener", idx).as_str());
+        }
+
+        if token.event_listeners[&canister_id].is_empty() {
+            token.event_listeners.remove(&canister_id);
+        }
+
+        let (filter, method_name) = entry;
+        hub.remove_event_listener(&filter, method_name, canister_id)
+            .unwrap_or_else(|e| get_env().trap(e.as_str()));
+    }
+}
 
 // ------------------ STATE ----------------------
 
@@ -0,0 +1,1266 @@
+//! Endpoint tests, running the actor against `MockEnvironment` instead of a replica. Guards
+//! are not applied when endpoints are called directly, so they are tested separately.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::MutexGuard;
+
+use ic_cdk::export::candid::Principal;
+use ic_cron::types::{Iterations, SchedulingInterval, TaskId};
+use ic_event_hub::types::{
+    AddEventListenersRequest, EventListenerExt, IEventFilter, RemoveEventListenersRequest,
+};
+use union_utils::RemoteCallEndpoint;
+
+use currency_token_client::events::TokenMoveEventFilter;
+use currency_token_client::types::{
+    BurnFromRequest, BurnRequest, ClawbackRequest, ControllersHandoverRequest,
+    ControllersUpdateDelay, ControllersUpdateIdRequest, CreateEscrowRequest,
+    DequeueRecurrentTaskRequest, EscrowIdRequest, EventTopicFilter, ForcedMoveKind,
+    GetBalanceOfRequest, GetBalancesOfRequest, GetEscrowsOfRequest, GetEventsRequest,
+    GetHoldersRequest, GetMintQuotaRequest, GetRecurrentTransferTasksRequest,
+    GetRoleMembersRequest, GetTopHoldersRequest, GetTransactionsRequest, HttpRequest, InitRequest,
+    MintRateLimit, ProposalAction, ProposalIdRequest, ProposeRequest, RoleRequest,
+    SetApprovalThresholdRequest, SetCyclesLowWaterMarkRequest, SetEventArchiveRequest,
+    SetMintQuotaRequest, SetRoleAdminRequest, TokenInfo, TransferEntry, TransferRequest,
+    UpdateControllersRequest, UpdateInfoRequest, ADMIN_ROLE, BURN_ROLE, EVENT_LISTENERS_ROLE,
+    INFO_ROLE, MINT_ROLE,
+};
+use token_common::event_log::{ARCHIVE_BATCH_SIZE, MAX_EVENTS_PAGE_SIZE};
+use token_common::proposals::MAX_PROPOSAL_TTL_NANO;
+use token_common::timelock::MAX_CONTROLLERS_UPDATE_DELAY_NANO;
+
+use crate::common::currency_token::{MAX_BATCH_QUERY_SIZE, MAX_EVENT_LISTENERS};
+use crate::common::env::{get_env, set_env, MockEnvironment, ENV_LOCK};
+use crate::common::guards::{
+    admin_guard, burn_guard, event_listeners_guard, info_guard, mint_guard, self_guard,
+};
+use crate::*;
+
+fn test_info() -> TokenInfo {
+    TokenInfo {
+        name: String::from("test"),
+        symbol: String::from("TST"),
+        decimals: 8,
+        ..Default::default()
+    }
+}
+
+/// Initializes the canister on behalf of a fresh controller
+fn setup() -> (MutexGuard<'static, ()>, Principal) {
+    let lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let controller = random_principal();
+
+    set_env(MockEnvironment {
+        caller: controller,
+        time: 1000,
+        ..Default::default()
+    });
+
+    init(InitRequest {
+        info: test_info(),
+        default_controllers: None,
+        controllers_update_delay_nano: None,
+    });
+
+    (lock, controller)
+}
+
+fn set_caller(caller: Principal) {
+    get_env().caller = caller;
+}
+
+/// Unlike `union_utils::random_principal_test`, which is derived from the current time, never
+/// repeats itself and never produces an invalid principal
+fn random_principal() -> Principal {
+    static COUNTER: AtomicU64 = AtomicU64::new(1);
+
+    let mut bytes = COUNTER
+        .fetch_add(1, Ordering::Relaxed)
+        .to_be_bytes()
+        .to_vec();
+    // the opaque id class
+    bytes.push(1);
+
+    Principal::from_slice(&bytes)
+}
+
+fn traps<F: FnOnce()>(f: F) -> bool {
+    catch_unwind(AssertUnwindSafe(f)).is_err()
+}
+
+fn entry(to: Principal, qty: u64, recurrence: Option<SchedulingInterval>) -> TransferRequest {
+    TransferRequest {
+        entries: vec![TransferEntry {
+            to,
+            qty,
+            event_payload: None,
+            recurrence,
+        }],
+    }
+}
+
+fn twice() -> Option<SchedulingInterval> {
+    Some(SchedulingInterval {
+        duration_nano: 10,
+        iterations: Iterations::Exact(2),
+    })
+}
+
+fn balance_of(account_owner: Principal) -> u64 {
+    get_balance_of(GetBalanceOfRequest { account_owner }).balance
+}
+
+fn role_request(role: &str, principal: Principal) -> RoleRequest {
+    RoleRequest {
+        role: String::from(role),
+        principal,
+    }
+}
+
+fn fire_task(task_id: TaskId) {
+    let task = get_env().fire_task(task_id).unwrap();
+
+    _execute_cron_task(task_id, task.kind, &task);
+}
+
+fn last_event_name() -> String {
+    let names = get_env().get_emitted_event_names();
+
+    String::from(*names.last().unwrap())
+}
+
+fn get(url: &str) -> (u16, String) {
+    let response = http_request(HttpRequest {
+        method: String::from("GET"),
+        url: String::from(url),
+        headers: vec![],
+        body: vec![],
+    });
+
+    (
+        response.status_code,
+        String::from_utf8(response.body).unwrap(),
+    )
+}
+
+#[test]
+fn init_works_fine() {
+    let (_lock, controller) = setup();
+
+    for role in &[
+        ADMIN_ROLE,
+        MINT_ROLE,
+        INFO_ROLE,
+        BURN_ROLE,
+        EVENT_LISTENERS_ROLE,
+    ] {
+        assert!(has_role(role_request(role, controller)).has_role);
+    }
+
+    assert_eq!(get_info().info.name, "test");
+    assert_eq!(get_total_supply().total_supply, 0);
+    assert_eq!(get_controllers().controllers.len(), 5);
+    assert!(!get_env().certified_data.is_empty());
+
+    let default_controller = random_principal();
+    init(InitRequest {
+        info: test_info(),
+        default_controllers: Some(vec![default_controller]),
+        controllers_update_delay_nano: Some(100),
+    });
+
+    assert!(has_role(role_request(MINT_ROLE, default_controller)).has_role);
+    assert!(!has_role(role_request(MINT_ROLE, controller)).has_role);
+    assert_eq!(get_controllers_update_delay().delay_nano, 100);
+
+    assert!(traps(|| init(InitRequest {
+        info: test_info(),
+        default_controllers: None,
+        controllers_update_delay_nano: Some(MAX_CONTROLLERS_UPDATE_DELAY_NANO + 1),
+    })));
+
+    assert!(traps(|| init(InitRequest {
+        info: TokenInfo {
+            symbol: String::from("T S T"),
+            ..test_info()
+        },
+        default_controllers: None,
+        controllers_update_delay_nano: None,
+    })));
+}
+
+#[test]
+fn mint_works_fine() {
+    let (_lock, _) = setup();
+    let user = random_principal();
+
+    mint(entry(user, 100, None));
+
+    assert_eq!(balance_of(user), 100);
+    assert_eq!(get_total_supply().total_supply, 100);
+    assert_eq!(get_env().get_emitted_event_names(), vec!["TokenMoveEvent"]);
+    assert_eq!(
+        get_events(GetEventsRequest {
+            from_seq: 0,
+            limit: 10,
+            topic_filter: None
+        })
+        .events
+        .len(),
+        1
+    );
+
+    assert!(traps(|| mint(entry(user, 0, None))));
+}
+
+#[test]
+fn recurrent_mint_works_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    mint(entry(user, 100, twice()));
+
+    let tasks = get_recurrent_mint_tasks().tasks;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].minter, controller);
+    assert_eq!(tasks[0].to, user);
+
+    let task_id = tasks[0].task_id;
+
+    fire_task(task_id);
+    assert_eq!(balance_of(user), 200);
+    assert_eq!(get_recurrent_mint_tasks().tasks.len(), 1);
+
+    fire_task(task_id);
+    assert_eq!(balance_of(user), 300);
+    assert!(get_recurrent_mint_tasks().tasks.is_empty());
+    assert_eq!(last_event_name(), "RecurrentTaskCompletedEvent");
+
+    mint(entry(user, 100, twice()));
+    let task_id = get_recurrent_mint_tasks().tasks[0].task_id;
+
+    let response = dequeue_recurrent_mint_tasks(DequeueRecurrentTaskRequest {
+        task_ids: vec![task_id, task_id],
+    });
+
+    assert_eq!(response.succeed, vec![true, false]);
+    assert!(get_env().tasks.is_empty());
+    assert_eq!(last_event_name(), "RecurrentTaskCancelledEvent");
+}
+
+#[test]
+fn transfer_works_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    mint(entry(controller, 100, None));
+    transfer(entry(user, 60, None));
+
+    assert_eq!(balance_of(controller), 40);
+    assert_eq!(balance_of(user), 60);
+    assert_eq!(get_total_supply().total_supply, 100);
+
+    assert!(traps(|| transfer(entry(user, 50, None))));
+    assert!(traps(|| transfer(entry(user, 0, None))));
+
+    // transferring to self doesn't change anything
+    set_caller(user);
+    transfer(entry(user, 60, None));
+
+    assert_eq!(balance_of(user), 60);
+}
+
+#[test]
+fn recurrent_transfer_works_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    mint(entry(controller, 100, None));
+    transfer(entry(user, 10, twice()));
+
+    let tasks =
+        get_recurrent_transfer_tasks(GetRecurrentTransferTasksRequest { owner: controller }).tasks;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].to, user);
+    assert_eq!(tasks[0].qty, 10);
+
+    let task_id = tasks[0].task_id;
+
+    fire_task(task_id);
+    fire_task(task_id);
+
+    assert_eq!(balance_of(user), 30);
+    assert!(
+        get_recurrent_transfer_tasks(GetRecurrentTransferTasksRequest { owner: controller })
+            .tasks
+            .is_empty()
+    );
+
+    transfer(entry(user, 10, twice()));
+    let task_id =
+        get_recurrent_transfer_tasks(GetRecurrentTransferTasksRequest { owner: controller }).tasks
+            [0]
+        .task_id;
+
+    // only the owner is able to cancel the task
+    set_caller(user);
+    let response = dequeue_recurrent_transfer_tasks(DequeueRecurrentTaskRequest {
+        task_ids: vec![task_id],
+    });
+    assert_eq!(response.succeed, vec![false]);
+
+    set_caller(controller);
+    let response = dequeue_recurrent_transfer_tasks(DequeueRecurrentTaskRequest {
+        task_ids: vec![task_id],
+    });
+    assert_eq!(response.succeed, vec![true]);
+    assert!(!get_env().has_task(task_id));
+}
+
+#[test]
+fn failed_recurrent_transfer_is_counted() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    mint(entry(controller, 10, None));
+    transfer(entry(user, 10, twice()));
+
+    let task_id =
+        get_recurrent_transfer_tasks(GetRecurrentTransferTasksRequest { owner: controller }).tasks
+            [0]
+        .task_id;
+
+    fire_task(task_id);
+
+    assert_eq!(balance_of(user), 10);
+    assert_eq!(get_metrics().failed_recurrent_executions, 1);
+}
+
+#[test]
+fn burn_works_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    mint(entry(controller, 100, None));
+    mint(entry(user, 100, None));
+
+    burn(BurnRequest {
+        qty: 30,
+        event_payload: None,
+    });
+    assert_eq!(balance_of(controller), 70);
+
+    burn_from(BurnFromRequest {
+        account: user,
+        qty: 40,
+        reason: String::from("test"),
+    });
+    assert_eq!(balance_of(user), 60);
+
+    clawback(ClawbackRequest {
+        from: user,
+        to: controller,
+        qty: 60,
+        reason: String::from("test"),
+    });
+    assert_eq!(balance_of(user), 0);
+    assert_eq!(balance_of(controller), 130);
+    assert_eq!(get_total_supply().total_supply, 130);
+
+    assert!(traps(|| burn(BurnRequest {
+        qty: 1000,
+        event_payload: None,
+    })));
+    assert!(traps(|| burn_from(BurnFromRequest {
+        account: user,
+        qty: 1,
+        reason: String::from("test"),
+    })));
+    assert!(traps(|| clawback(ClawbackRequest {
+        from: user,
+        to: controller,
+        qty: 1,
+        reason: String::from("test"),
+    })));
+}
+
+#[test]
+fn forced_moves_work_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    mint(entry(user, 100, None));
+
+    burn_from(BurnFromRequest {
+        account: user,
+        qty: 40,
+        reason: String::from("burn"),
+    });
+    clawback(ClawbackRequest {
+        from: user,
+        to: controller,
+        qty: 60,
+        reason: String::from("clawback"),
+    });
+
+    let transactions = get_transactions(GetTransactionsRequest {
+        start: 0,
+        length: 1000,
+    })
+    .transactions;
+    assert_eq!(transactions.len(), 3);
+    assert!(transactions[0].forced.is_none());
+
+    let burned = transactions[1].forced.as_ref().unwrap();
+    assert!(matches!(burned.kind, ForcedMoveKind::BurnFrom));
+    assert_eq!(burned.executed_by, controller);
+    assert_eq!(burned.reason, "burn");
+    assert!(transactions[1].event_payload.is_none());
+
+    let clawed_back = transactions[2].forced.as_ref().unwrap();
+    assert!(matches!(clawed_back.kind, ForcedMoveKind::Clawback));
+    assert_eq!(clawed_back.executed_by, controller);
+    assert_eq!(clawed_back.reason, "clawback");
+    assert_eq!(transactions[2].to, Some(controller));
+}
+
+#[test]
+fn balance_queries_work_fine() {
+    let (_lock, _) = setup();
+    let (user_1, user_2) = (random_principal(), random_principal());
+
+    mint(entry(user_1, 100, None));
+    mint(entry(user_2, 200, None));
+
+    let balances = get_balances_of(GetBalancesOfRequest {
+        account_owners: vec![user_1, user_2],
+    })
+    .balances;
+    assert_eq!(balances[0].balance, 100);
+    assert_eq!(balances[1].balance, 200);
+
+    assert!(traps(|| {
+        get_balances_of(GetBalancesOfRequest {
+            account_owners: vec![user_1; MAX_BATCH_QUERY_SIZE + 1],
+        });
+    }));
+
+    assert_eq!(get_holders_count().holders_count, 2);
+
+    let response = get_holders(GetHoldersRequest {
+        cursor: None,
+        limit: 1,
+    });
+    assert_eq!(response.holders.len(), 1);
+    assert!(response.next_cursor.is_some());
+
+    let response = get_holders(GetHoldersRequest {
+        cursor: response.next_cursor,
+        limit: 1,
+    });
+    assert_eq!(response.holders.len(), 1);
+    assert!(response.next_cursor.is_none());
+
+    let top = get_top_holders(GetTopHoldersRequest { n: 1 }).holders;
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0].account_owner, user_2);
+}
+
+#[test]
+fn info_works_fine() {
+    let (_lock, _) = setup();
+    let certified_data = get_env().certified_data.clone();
+
+    let old_info = update_info(UpdateInfoRequest {
+        new_info: TokenInfo {
+            name: String::from("new"),
+            ..test_info()
+        },
+    })
+    .old_info;
+
+    assert_eq!(old_info.name, "test");
+    assert_eq!(get_info().info.name, "new");
+    assert_eq!(last_event_name(), "InfoUpdateEvent");
+    assert_ne!(get_env().certified_data, certified_data);
+
+    assert!(traps(|| {
+        update_info(UpdateInfoRequest {
+            new_info: TokenInfo {
+                name: String::new(),
+                ..test_info()
+            },
+        });
+    }));
+}
+
+#[test]
+fn roles_work_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    grant_role(role_request(MINT_ROLE, user));
+
+    assert!(has_role(role_request(MINT_ROLE, user)).has_role);
+    assert_eq!(
+        get_role_members(GetRoleMembersRequest {
+            role: String::from(MINT_ROLE),
+        })
+        .members
+        .len(),
+        2
+    );
+
+    revoke_role(role_request(MINT_ROLE, controller));
+    assert!(!has_role(role_request(MINT_ROLE, controller)).has_role);
+
+    // the last member can't be revoked, the role can only be renounced
+    set_caller(user);
+    assert!(traps(|| revoke_role(role_request(MINT_ROLE, user))));
+    assert!(traps(|| revoke_role(role_request(MINT_ROLE, controller))));
+    assert!(traps(|| grant_role(role_request(INFO_ROLE, user))));
+
+    // a role without members can't administer other roles
+    assert!(traps(|| {
+        set_role_admin(SetRoleAdminRequest {
+            role: String::from(MINT_ROLE),
+            admin_role: String::from("nobody"),
+        });
+    }));
+
+    let old_admin_role = set_role_admin(SetRoleAdminRequest {
+        role: String::from(MINT_ROLE),
+        admin_role: String::from(ADMIN_ROLE),
+    })
+    .old_admin_role;
+    assert_eq!(old_admin_role, MINT_ROLE);
+
+    // now the admins manage the mint role
+    set_caller(controller);
+    grant_role(role_request(MINT_ROLE, controller));
+    assert!(has_role(role_request(MINT_ROLE, controller)).has_role);
+
+    set_caller(user);
+    assert!(traps(|| {
+        set_role_admin(SetRoleAdminRequest {
+            role: String::from(INFO_ROLE),
+            admin_role: String::from(MINT_ROLE),
+        });
+    }));
+}
+
+fn listener(canister_id: Principal) -> EventListenerExt {
+    EventListenerExt {
+        filter: TokenMoveEventFilter {
+            from: None,
+            to: None,
+        }
+        .to_event_filter(),
+        endpoint: RemoteCallEndpoint {
+            canister_id,
+            method_name: String::from("events_callback"),
+        },
+    }
+}
+
+fn add_listener(canister_id: Principal) {
+    _add_event_listeners(AddEventListenersRequest {
+        listeners: vec![listener(canister_id)],
+    });
+}
+
+#[test]
+fn event_listeners_cap_respected() {
+    let (_lock, controller) = setup();
+
+    let listeners = (0..MAX_EVENT_LISTENERS)
+        .map(|_| random_principal())
+        .collect::<Vec<_>>();
+
+    for listener in &listeners {
+        grant_role(role_request(EVENT_LISTENERS_ROLE, *listener));
+        add_listener(*listener);
+    }
+
+    assert_eq!(
+        get_metrics().event_listeners_count,
+        MAX_EVENT_LISTENERS as u64
+    );
+    assert!(traps(|| add_listener(random_principal())));
+
+    // adding a known listener once again doesn't grow the listener set
+    add_listener(listeners[1]);
+
+    // revoked members are unsubscribed
+    revoke_role(role_request(EVENT_LISTENERS_ROLE, listeners[0]));
+    assert_eq!(
+        get_metrics().event_listeners_count,
+        MAX_EVENT_LISTENERS as u64 - 1
+    );
+    add_listener(controller);
+    assert_eq!(
+        get_metrics().event_listeners_count,
+        MAX_EVENT_LISTENERS as u64
+    );
+
+    _remove_event_listeners(RemoveEventListenersRequest {
+        listeners: vec![listener(controller)],
+    });
+    assert!(traps(|| _remove_event_listeners(
+        RemoveEventListenersRequest {
+            listeners: vec![listener(controller)],
+        }
+    )));
+
+    update_event_listeners_controller(UpdateControllersRequest {
+        new_controllers: vec![controller],
+        renounce: None,
+    });
+    assert_eq!(get_metrics().event_listeners_count, 0);
+}
+
+#[test]
+fn controllers_handover_works_fine() {
+    let (_lock, controller) = setup();
+    let (user_1, user_2) = (random_principal(), random_principal());
+
+    let response = update_mint_controller(UpdateControllersRequest {
+        new_controllers: vec![user_1, user_2],
+        renounce: None,
+    });
+
+    assert_eq!(response.old_controllers, vec![controller]);
+    assert!(response.scheduled_update_id.is_none());
+    assert_eq!(get_controllers_handovers().handovers.len(), 1);
+
+    let handover_request = || ControllersHandoverRequest {
+        role: String::from(MINT_ROLE),
+    };
+
+    set_caller(user_1);
+    accept_controllers_handover(handover_request());
+    assert!(traps(|| accept_controllers_handover(handover_request())));
+    assert!(has_role(role_request(MINT_ROLE, controller)).has_role);
+
+    set_caller(user_2);
+    accept_controllers_handover(handover_request());
+
+    assert!(!has_role(role_request(MINT_ROLE, controller)).has_role);
+    assert!(has_role(role_request(MINT_ROLE, user_1)).has_role);
+    assert!(has_role(role_request(MINT_ROLE, user_2)).has_role);
+    assert!(get_controllers_handovers().handovers.is_empty());
+    assert_eq!(last_event_name(), "ControllersUpdateEvent");
+
+    // the proposer accepts their own handover right away
+    set_caller(controller);
+    update_info_controller(UpdateControllersRequest {
+        new_controllers: vec![controller, user_1],
+        renounce: None,
+    });
+
+    let info_handover_request = || ControllersHandoverRequest {
+        role: String::from(INFO_ROLE),
+    };
+
+    set_caller(random_principal());
+    assert!(traps(|| cancel_controllers_handover(
+        info_handover_request()
+    )));
+
+    // the controller has handed the mint role over already
+    set_caller(controller);
+    assert!(traps(|| cancel_controllers_handover(handover_request())));
+
+    cancel_controllers_handover(info_handover_request());
+    assert!(get_controllers_handovers().handovers.is_empty());
+    assert_eq!(last_event_name(), "ControllersHandoverCancelledEvent");
+}
+
+#[test]
+fn roles_can_be_renounced() {
+    let (_lock, controller) = setup();
+
+    assert!(traps(|| {
+        update_burn_controller(UpdateControllersRequest {
+            new_controllers: vec![],
+            renounce: None,
+        });
+    }));
+
+    update_burn_controller(UpdateControllersRequest {
+        new_controllers: vec![],
+        renounce: Some(true),
+    });
+    assert!(!has_role(role_request(BURN_ROLE, controller)).has_role);
+
+    update_event_listeners_controller(UpdateControllersRequest {
+        new_controllers: vec![],
+        renounce: Some(true),
+    });
+    assert!(!has_role(role_request(EVENT_LISTENERS_ROLE, controller)).has_role);
+}
+
+#[test]
+fn timelock_works_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    set_controllers_update_delay(ControllersUpdateDelay { delay_nano: 100 });
+    assert_eq!(get_controllers_update_delay().delay_nano, 100);
+
+    grant_role(role_request(MINT_ROLE, user));
+    assert!(!has_role(role_request(MINT_ROLE, user)).has_role);
+
+    let updates = get_scheduled_controllers_updates().updates;
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].execute_at, 1100);
+    assert_eq!(last_event_name(), "ControllersUpdateScheduledEvent");
+
+    let task_id = get_token().timelock.get_update_task(updates[0].id).unwrap();
+
+    get_env().time = 1100;
+    fire_task(task_id);
+
+    assert!(has_role(role_request(MINT_ROLE, user)).has_role);
+    assert!(get_scheduled_controllers_updates().updates.is_empty());
+
+    // shortening the delay is a subject to the delay itself
+    set_controllers_update_delay(ControllersUpdateDelay { delay_nano: 10 });
+    assert_eq!(get_controllers_update_delay().delay_nano, 100);
+
+    let update_id = get_scheduled_controllers_updates().updates[0].id;
+
+    set_caller(user);
+    assert!(traps(|| cancel_controllers_update(
+        ControllersUpdateIdRequest { update_id }
+    )));
+
+    set_caller(controller);
+    cancel_controllers_update(ControllersUpdateIdRequest { update_id });
+
+    assert!(get_scheduled_controllers_updates().updates.is_empty());
+    assert!(get_env().tasks.is_empty());
+    assert!(traps(|| cancel_controllers_update(
+        ControllersUpdateIdRequest { update_id }
+    )));
+
+    // so is lengthening it, while the delay itself is bounded
+    set_controllers_update_delay(ControllersUpdateDelay { delay_nano: 200 });
+    assert_eq!(get_controllers_update_delay().delay_nano, 100);
+    assert_eq!(get_scheduled_controllers_updates().updates.len(), 1);
+
+    assert!(traps(|| set_controllers_update_delay(
+        ControllersUpdateDelay {
+            delay_nano: MAX_CONTROLLERS_UPDATE_DELAY_NANO + 1,
+        }
+    )));
+
+    // admin role changes wait for the delay as well
+    let response = set_role_admin(SetRoleAdminRequest {
+        role: String::from(MINT_ROLE),
+        admin_role: String::from(ADMIN_ROLE),
+    });
+    assert!(response.scheduled_update_id.is_some());
+    assert_eq!(get_token().roles.get_admin_role(MINT_ROLE), MINT_ROLE);
+}
+
+#[test]
+fn multisig_works_fine() {
+    let (_lock, controller) = setup();
+    let (minter, user) = (random_principal(), random_principal());
+
+    grant_role(role_request(MINT_ROLE, minter));
+
+    assert!(traps(|| {
+        set_approval_threshold(SetApprovalThresholdRequest {
+            role: String::from(MINT_ROLE),
+            threshold: 3,
+        });
+    }));
+
+    let old_threshold = set_approval_threshold(SetApprovalThresholdRequest {
+        role: String::from(MINT_ROLE),
+        threshold: 2,
+    })
+    .old_threshold;
+
+    assert_eq!(old_threshold, 1);
+    assert_eq!(get_approval_thresholds().thresholds.len(), 1);
+    assert!(traps(|| mint(entry(user, 100, None))));
+
+    let response = propose(ProposeRequest {
+        action: ProposalAction::Mint(entry(user, 100, None)),
+        ttl_nano: None,
+    });
+
+    assert!(!response.executed);
+    assert_eq!(get_pending_proposals().proposals.len(), 1);
+    assert_eq!(balance_of(user), 0);
+
+    let proposal_id = response.proposal_id;
+    assert!(traps(|| {
+        approve_proposal(ProposalIdRequest { proposal_id });
+    }));
+
+    set_caller(user);
+    assert!(traps(|| {
+        approve_proposal(ProposalIdRequest { proposal_id });
+    }));
+
+    set_caller(minter);
+    let response = approve_proposal(ProposalIdRequest { proposal_id });
+
+    assert!(response.executed);
+    assert_eq!(balance_of(user), 100);
+    assert!(get_pending_proposals().proposals.is_empty());
+    assert_eq!(last_event_name(), "ProposalExecutedEvent");
+
+    let proposal = get_proposal(ProposalIdRequest { proposal_id })
+        .proposal
+        .unwrap();
+    assert_eq!(proposal.proposer, controller);
+    assert_eq!(proposal.approvals, vec![controller, minter]);
+
+    set_caller(user);
+    assert!(traps(|| {
+        propose(ProposeRequest {
+            action: ProposalAction::Mint(entry(user, 100, None)),
+            ttl_nano: None,
+        });
+    }));
+
+    set_caller(controller);
+    assert!(traps(|| {
+        propose(ProposeRequest {
+            action: ProposalAction::Mint(entry(user, 100, None)),
+            ttl_nano: Some(MAX_PROPOSAL_TTL_NANO + 1),
+        });
+    }));
+}
+
+#[test]
+fn approval_threshold_stays_reachable() {
+    let (_lock, _controller) = setup();
+    let (user1, user2, user3) = (random_principal(), random_principal(), random_principal());
+
+    grant_role(role_request("custom", user1));
+    grant_role(role_request("custom", user2));
+
+    set_approval_threshold(SetApprovalThresholdRequest {
+        role: String::from("custom"),
+        threshold: 2,
+    });
+
+    // the remaining member alone couldn't approve anything
+    assert!(traps(|| revoke_role(role_request("custom", user1))));
+
+    grant_role(role_request("custom", user3));
+    set_controllers_update_delay(ControllersUpdateDelay { delay_nano: 100 });
+
+    // each revocation is fine on its own, but only the first one gets applied
+    revoke_role(role_request("custom", user1));
+    revoke_role(role_request("custom", user2));
+
+    let updates = get_scheduled_controllers_updates().updates;
+    assert_eq!(updates.len(), 2);
+
+    get_env().time = updates[1].execute_at;
+    for update in updates {
+        fire_task(get_token().timelock.get_update_task(update.id).unwrap());
+    }
+
+    assert!(!has_role(role_request("custom", user1)).has_role);
+    assert!(has_role(role_request("custom", user2)).has_role);
+    assert!(has_role(role_request("custom", user3)).has_role);
+}
+
+#[test]
+fn escrow_works_fine() {
+    let (_lock, controller) = setup();
+    let (payee, arbiter) = (random_principal(), random_principal());
+
+    mint(entry(controller, 300, None));
+
+    let create = |qty| {
+        create_escrow(CreateEscrowRequest {
+            payee,
+            arbiter,
+            qty,
+            deadline: 2000,
+            event_payload: None,
+        })
+        .escrow_id
+    };
+
+    let escrow_id = create(100);
+    let escrow = get_escrow(EscrowIdRequest { escrow_id }).escrow.unwrap();
+
+    assert_eq!(escrow.payer, controller);
+    assert_eq!(escrow.qty, 100);
+    assert_eq!(balance_of(controller), 200);
+    assert_eq!(
+        get_escrows_of(GetEscrowsOfRequest { participant: payee })
+            .escrows
+            .len(),
+        1
+    );
+    assert_eq!(get_env().tasks.len(), 1);
+
+    set_caller(payee);
+    assert!(traps(|| release_escrow(EscrowIdRequest { escrow_id })));
+
+    set_caller(arbiter);
+    release_escrow(EscrowIdRequest { escrow_id });
+
+    assert_eq!(balance_of(payee), 100);
+    assert!(get_escrow(EscrowIdRequest { escrow_id }).escrow.is_none());
+    assert!(get_env().tasks.is_empty());
+
+    set_caller(controller);
+    let escrow_id = create(100);
+
+    assert!(traps(|| refund_escrow(EscrowIdRequest { escrow_id })));
+
+    get_env().time = 2000;
+    refund_escrow(EscrowIdRequest { escrow_id });
+
+    assert_eq!(balance_of(controller), 200);
+    assert!(get_env().tasks.is_empty());
+    assert_eq!(last_event_name(), "EscrowRefundedEvent");
+
+    // the refund task fires once the deadline passes
+    get_env().time = 1000;
+    let escrow_id = create(100);
+    let task_id = get_token().get_escrow_refund_task(escrow_id).unwrap();
+
+    get_env().time = 2000;
+    fire_task(task_id);
+
+    assert_eq!(balance_of(controller), 200);
+    assert!(get_escrow(EscrowIdRequest { escrow_id }).escrow.is_none());
+
+    assert!(traps(|| {
+        create_escrow(CreateEscrowRequest {
+            payee,
+            arbiter,
+            qty: 100,
+            deadline: 1000,
+            event_payload: None,
+        });
+    }));
+}
+
+#[test]
+fn mint_quota_works_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    set_mint_quota(SetMintQuotaRequest {
+        minter: controller,
+        total_quota: Some(150),
+        rate_limit: None,
+    });
+
+    mint(entry(user, 100, None));
+    assert!(traps(|| mint(entry(user, 100, None))));
+
+    let response = get_mint_quota(GetMintQuotaRequest { minter: controller });
+    assert_eq!(response.remaining_total, Some(50));
+    assert_eq!(response.quota.unwrap().minted_total, 100);
+
+    assert!(traps(|| set_mint_quota(SetMintQuotaRequest {
+        minter: controller,
+        total_quota: None,
+        rate_limit: Some(MintRateLimit {
+            qty: 10,
+            period_nano: 0,
+        }),
+    })));
+}
+
+#[test]
+fn cycles_work_fine() {
+    let (_lock, controller) = setup();
+
+    get_env().cycles_balance = 100;
+    set_cycles_low_water_mark(SetCyclesLowWaterMarkRequest {
+        low_water_mark: Some(1000),
+        suspend_on_low_cycles: true,
+    });
+
+    let response = get_cycles_balance();
+    assert_eq!(response.balance, 100);
+    assert_eq!(response.low_water_mark, Some(1000));
+    assert!(response.suspended);
+    assert_eq!(get_env().get_emitted_event_names(), vec!["LowCyclesEvent"]);
+
+    // events are still logged, but not delivered
+    mint(entry(controller, 100, None));
+    assert_eq!(get_env().emitted_events.len(), 1);
+    assert_eq!(get_token().event_log.get_next_seq(), 2);
+
+    get_env().cycles_available = 10_000;
+    assert_eq!(wallet_receive().accepted, 10_000);
+
+    let response = get_cycles_balance();
+    assert_eq!(response.balance, 10_100);
+    assert!(!response.suspended);
+}
+
+#[test]
+fn metrics_work_fine() {
+    let (_lock, controller) = setup();
+
+    mint(entry(controller, 100, None));
+    mint(entry(random_principal(), 100, None));
+
+    let metrics = get_metrics();
+    assert_eq!(metrics.accounts_count, 2);
+    assert_eq!(metrics.total_supply, 200);
+    assert_eq!(metrics.cycles_balance, u64::MAX);
+    assert!(metrics
+        .call_counters
+        .iter()
+        .any(|(method, count)| method == "mint" && *count == 2));
+
+    assert!(metrics.last_heartbeat_at.is_none());
+
+    get_env().time = 100;
+    heartbeat();
+    assert_eq!(get_metrics().last_heartbeat_at, Some(100));
+
+    let (status, body) = get("/metrics");
+    assert_eq!(status, 200);
+    assert!(body.contains("currency_token_total_supply 200"));
+}
+
+#[test]
+fn audit_works_fine() {
+    let (_lock, controller) = setup();
+    let hash = get_state_hash().hash;
+
+    mint(entry(controller, 100, None));
+    transfer(entry(random_principal(), 10, twice()));
+
+    assert_ne!(get_state_hash().hash, hash);
+    assert_eq!(get_state_hash().hash, get_state_hash().hash);
+
+    let audit = audit_state();
+    assert!(audit.consistent);
+    assert_eq!(audit.total_supply, 100);
+    assert_eq!(audit.balances_sum, 100);
+
+    let task_id = get_token().get_recurrent_transfer_tasks(controller)[0];
+    let task = get_env().tasks.remove(&task_id).unwrap();
+
+    let audit = audit_state();
+    assert!(!audit.consistent);
+    assert!(audit.supply_consistent);
+    assert_eq!(audit.dangling_task_ids, vec![task_id]);
+    assert!(audit.orphaned_task_ids.is_empty());
+
+    // a scheduled task which the token doesn't track is reported as well
+    get_env().tasks.insert(task_id, task);
+    get_token().recurrent_transfer_tasks.clear();
+
+    let audit = audit_state();
+    assert!(!audit.consistent);
+    assert!(audit.dangling_task_ids.is_empty());
+    assert_eq!(audit.orphaned_task_ids, vec![task_id]);
+}
+
+#[test]
+fn http_transactions_pagination_works_fine() {
+    let (_lock, controller) = setup();
+
+    for _ in 0..=MAX_EVENTS_PAGE_SIZE {
+        mint(entry(controller, 1, None));
+    }
+
+    // limits above the page size are clamped, so the next page is still advertised
+    let (status, body) = get("/transactions?limit=5000");
+    assert_eq!(status, 200);
+    assert!(body.contains(format!("\"next\":\"{}\"", MAX_EVENTS_PAGE_SIZE).as_str()));
+}
+
+#[test]
+fn http_request_works_fine() {
+    let (_lock, controller) = setup();
+
+    mint(entry(controller, 100, None));
+
+    let (status, body) = get("/info");
+    assert_eq!(status, 200);
+    assert!(body.contains("\"symbol\":\"TST\""));
+
+    assert!(body.contains("\"decimals\":8"));
+
+    // amounts are strings, so JavaScript clients don't lose precision
+    let (status, body) = get("/supply");
+    assert_eq!(status, 200);
+    assert!(body.contains("\"total_supply\":\"100\""));
+
+    let (status, body) = get(format!("/balance/{}", controller.to_text()).as_str());
+    assert_eq!(status, 200);
+    assert!(body.contains("\"balance\":\"100\""));
+
+    assert_eq!(get("/balance/invalid").0, 400);
+    assert_eq!(get("/holders?limit=1").0, 200);
+    assert_eq!(get("/transactions").0, 200);
+    assert_eq!(get("/unknown").0, 404);
+
+    let response = http_request(HttpRequest {
+        method: String::from("POST"),
+        url: String::from("/info"),
+        headers: vec![],
+        body: vec![],
+    });
+    assert_eq!(response.status_code, 405);
+}
+
+#[test]
+fn http_recertification_works_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    let initial = get_env().certified_data.clone();
+    assert!(!initial.is_empty());
+
+    mint(entry(controller, 100, None));
+    let minted = get_env().certified_data.clone();
+    assert_ne!(minted, initial);
+
+    // neither the supply nor the info change, so nothing is recertified
+    get_env().certified_data = Vec::new();
+    transfer(entry(user, 10, None));
+    assert!(get_env().certified_data.is_empty());
+
+    burn(BurnRequest {
+        qty: 10,
+        event_payload: None,
+    });
+    assert!(!get_env().certified_data.is_empty());
+
+    get_env().certified_data = Vec::new();
+    update_info(UpdateInfoRequest {
+        new_info: TokenInfo {
+            name: String::from("renamed"),
+            ..test_info()
+        },
+    });
+    assert!(!get_env().certified_data.is_empty());
+}
+
+#[test]
+fn event_log_works_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    mint(entry(controller, 100, None));
+    update_info(UpdateInfoRequest {
+        new_info: test_info(),
+    });
+    transfer(entry(user, 10, None));
+
+    let response = get_events(GetEventsRequest {
+        from_seq: 0,
+        limit: 10,
+        topic_filter: None,
+    });
+    assert_eq!(response.events.len(), 3);
+    assert_eq!(response.oldest_seq, 0);
+    assert_eq!(response.next_seq, 3);
+
+    let response = get_events(GetEventsRequest {
+        from_seq: 0,
+        limit: 10,
+        topic_filter: Some(EventTopicFilter {
+            name: None,
+            principal: Some(user),
+        }),
+    });
+    assert_eq!(response.events.len(), 1);
+
+    let response = get_transactions(GetTransactionsRequest {
+        start: 0,
+        length: 10,
+    });
+    assert_eq!(response.transactions.len(), 2);
+    assert_eq!(response.transactions[1].to, Some(user));
+    assert!(response.archived_ranges.is_empty());
+}
+
+#[test]
+fn event_archive_works_fine() {
+    let (_lock, controller) = setup();
+    let archive = random_principal();
+
+    set_event_archive(SetEventArchiveRequest {
+        archive: Some(archive),
+        local_capacity: Some(1),
+    });
+
+    // the archive is unavailable, so the evicted events stay in the queue
+    get_env().archive_accepts = false;
+    for _ in 0..=ARCHIVE_BATCH_SIZE {
+        mint(entry(controller, 1, None));
+    }
+
+    assert!(get_env().archived_events.is_empty());
+    assert_eq!(
+        get_token().event_log.archive_queue.len(),
+        ARCHIVE_BATCH_SIZE
+    );
+
+    get_env().archive_accepts = true;
+    mint(entry(controller, 1, None));
+
+    let (archived_to, batch) = &get_env().archived_events[0];
+    assert_eq!(*archived_to, archive);
+    assert_eq!(batch.len(), ARCHIVE_BATCH_SIZE);
+
+    // the event which didn't fit into the batch is still served locally
+    let response = get_transactions(GetTransactionsRequest {
+        start: 0,
+        length: 1000,
+    });
+    assert_eq!(response.transactions.len(), 2);
+    assert_eq!(response.archived_ranges.len(), 1);
+    assert_eq!(response.archived_ranges[0].start, 0);
+    assert_eq!(
+        response.archived_ranges[0].length,
+        ARCHIVE_BATCH_SIZE as u64
+    );
+    assert_eq!(response.archived_ranges[0].callback.canister_id, archive);
+}
+
+#[test]
+fn guards_work_fine() {
+    let (_lock, controller) = setup();
+
+    assert!(mint_guard().is_ok());
+    assert!(info_guard().is_ok());
+    assert!(burn_guard().is_ok());
+    assert!(admin_guard().is_ok());
+    assert!(event_listeners_guard().is_ok());
+    assert!(self_guard().is_err());
+
+    set_caller(random_principal());
+
+    assert!(mint_guard().is_err());
+    assert!(info_guard().is_err());
+    assert!(burn_guard().is_err());
+    assert!(admin_guard().is_err());
+    assert!(event_listeners_guard().is_err());
+
+    set_caller(get_env().canister_id);
+    assert!(self_guard().is_ok());
+
+    set_caller(controller);
+    update_mint_controller(UpdateControllersRequest {
+        new_controllers: vec![],
+        renounce: Some(true),
+    });
+    assert!(mint_guard().is_err());
+}
@@ -0,0 +1,52 @@
+use ic_certified_map::{labeled, labeled_hash, AsHashTree, Hash, RbTree};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use currency_token_client::types::HeaderField;
+
+use crate::common::env::{get_env, Environment};
+
+const LABEL: &[u8] = b"http_assets";
+
+/// Bodies of the HTTP responses which are served certified, keyed by request path. Since the
+/// bodies are derived from the state, the tree is rebuilt from them instead of being stored.
+pub type CertifiedResponses = Vec<(&'static str, Vec<u8>)>;
+
+fn build_tree(responses: &CertifiedResponses) -> RbTree<&'static str, Hash> {
+    let mut tree = RbTree::new();
+
+    for (path, body) in responses {
+        tree.insert(*path, Sha256::digest(body).into());
+    }
+
+    tree
+}
+
+/// Must be called from update calls, whenever any of the certified responses may change
+pub fn certify_responses(responses: &CertifiedResponses) {
+    let tree = build_tree(responses);
+
+    get_env().set_certified_data(&labeled_hash(LABEL, &tree.root_hash()));
+}
+
+/// Returns the `IC-Certificate` header proving the response at `path`, if the certificate is
+/// available (it is not for replicated queries)
+pub fn certificate_header(responses: &CertifiedResponses, path: &str) -> Option<HeaderField> {
+    let certificate = get_env().data_certificate()?;
+
+    let tree = build_tree(responses);
+    let witness = labeled(LABEL, tree.witness(path.as_bytes()));
+
+    let mut serializer = serde_cbor::ser::Serializer::new(vec![]);
+    serializer.self_describe().ok()?;
+    witness.serialize(&mut serializer).ok()?;
+
+    Some((
+        String::from("IC-Certificate"),
+        format!(
+            "certificate=:{}:, tree=:{}:",
+            base64::encode(&certificate),
+            base64::encode(serializer.into_inner())
+        ),
+    ))
+}
@@ -1,24 +1,83 @@
+use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::ops::Bound::{Excluded, Unbounded};
 
-use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+use ic_cdk::export::candid::{encode_one, CandidType, Deserialize, Principal};
 use ic_cron::types::TaskId;
-
-use currency_token_client::types::{ControllerList, Controllers, TokenInfo};
-
+use ic_event_hub::types::EventFilter;
+use sha2::{Digest, Sha256};
+
+use currency_token_client::types::{
+    CreateEscrowRequest, Escrow, EscrowId, ProposalAction, TokenInfo,
+};
+use token_common::cycles::CyclesMonitor;
+use token_common::event_log::EventLog;
+use token_common::handover::HandoverRegistry;
+use token_common::metrics::Metrics;
+use token_common::proposals::ProposalRegistry;
+use token_common::roles::RoleRegistry;
+use token_common::timelock::ControllersTimelock;
+
+use crate::common::info::validate_info;
+use crate::common::mint_quota::MintQuotaRegistry;
 use crate::common::types::Error;
 
+pub const MAX_HOLDERS_PAGE_SIZE: usize = 1000;
+pub const MAX_BATCH_QUERY_SIZE: usize = 1000;
+pub const MAX_EVENT_LISTENERS: usize = 32;
+
 #[derive(CandidType, Deserialize)]
 pub struct CurrencyToken {
-    pub balances: HashMap<Principal, u64>,
+    pub balances: BTreeMap<Principal, u64>,
     pub total_supply: u64,
+    pub decimals_locked: bool,
     pub info: TokenInfo,
-    pub controllers: ControllerList,
+    pub roles: RoleRegistry,
+    pub mint_quotas: MintQuotaRegistry,
+    pub proposals: ProposalRegistry<ProposalAction>,
+    pub timelock: ControllersTimelock,
+    pub handovers: HandoverRegistry,
+    pub event_log: EventLog,
+    pub cycles: CyclesMonitor,
+    pub metrics: Metrics,
     pub recurrent_mint_tasks: HashSet<TaskId>,
     pub recurrent_transfer_tasks: HashMap<Principal, HashSet<TaskId>>,
+    pub escrows: HashMap<EscrowId, Escrow>,
+    pub escrow_refund_tasks: HashMap<EscrowId, TaskId>,
+    pub escrow_id_counter: EscrowId,
+    /// Mirrors the event hub's listeners (filter and callback method name) by the listening
+    /// canister, since the hub itself can't list them
+    pub event_listeners: HashMap<Principal, HashSet<(EventFilter, String)>>,
+    /// Set whenever the supply or the info change, since only they are served certified
+    pub certified_data_outdated: bool,
 }
 
 impl CurrencyToken {
+    pub fn new(info: TokenInfo, roles: RoleRegistry) -> CurrencyToken {
+        CurrencyToken {
+            balances: BTreeMap::new(),
+            total_supply: 0,
+            decimals_locked: false,
+            info,
+            roles,
+            mint_quotas: MintQuotaRegistry::default(),
+            proposals: ProposalRegistry::default(),
+            timelock: ControllersTimelock::default(),
+            handovers: HandoverRegistry::default(),
+            event_log: EventLog::default(),
+            cycles: CyclesMonitor::default(),
+            metrics: Metrics::default(),
+            recurrent_mint_tasks: HashSet::new(),
+            recurrent_transfer_tasks: HashMap::new(),
+            escrows: HashMap::new(),
+            escrow_refund_tasks: HashMap::new(),
+            escrow_id_counter: 0,
+            event_listeners: HashMap::new(),
+            certified_data_outdated: false,
+        }
+    }
+
     pub fn mint(&mut self, to: Principal, qty: u64) -> Result<(), Error> {
         if qty == 0 {
             return Err(Error::ZeroQuantity);
@@ -29,24 +88,42 @@ impl CurrencyToken {
 
         self.total_supply += qty;
         self.balances.insert(to, new_balance);
+        self.certified_data_outdated = true;
+
+        // balances are stored in base units, so the first mint pins their meaning
+        self.decimals_locked = true;
 
         Ok(())
     }
 
+    /// Mints on behalf of the minter, counting the quantity against their mint quota
+    pub fn mint_by(
+        &mut self,
+        minter: Principal,
+        to: Principal,
+        qty: u64,
+        now: u64,
+    ) -> Result<(), Error> {
+        if qty == 0 {
+            return Err(Error::ZeroQuantity);
+        }
+
+        self.mint_quotas.consume(minter, qty, now)?;
+        self.mint(to, qty)
+    }
+
     pub fn transfer(&mut self, from: Principal, to: Principal, qty: u64) -> Result<(), Error> {
         if qty == 0 {
             return Err(Error::ZeroQuantity);
         }
 
         let prev_from_balance = self.balance_of(&from);
-        let prev_to_balance = self.balance_of(&to);
 
         if prev_from_balance < qty {
             return Err(Error::InsufficientBalance);
         }
 
         let new_from_balance = prev_from_balance - qty;
-        let new_to_balance = prev_to_balance + qty;
 
         if new_from_balance == 0 {
             self.balances.remove(&from);
@@ -54,6 +131,8 @@ impl CurrencyToken {
             self.balances.insert(from, new_from_balance);
         }
 
+        // read after the debit, so transferring to oneself leaves the balance intact
+        let new_to_balance = self.balance_of(&to) + qty;
         self.balances.insert(to, new_to_balance);
 
         Ok(())
@@ -79,36 +158,87 @@ impl CurrencyToken {
         }
 
         self.total_supply -= qty;
+        self.certified_data_outdated = true;
 
         Ok(())
     }
 
-    pub fn update_info(&mut self, new_info: TokenInfo) -> TokenInfo {
+    pub fn update_info(&mut self, new_info: TokenInfo) -> Result<TokenInfo, Error> {
+        validate_info(&new_info)?;
+
+        if self.decimals_locked && new_info.decimals != self.info.decimals {
+            return Err(Error::DecimalsImmutable);
+        }
+
         let old_info = self.info.clone();
         self.info = new_info;
+        self.certified_data_outdated = true;
 
-        old_info
+        Ok(old_info)
     }
 
-    pub fn update_mint_controllers(&mut self, new_mint_controllers: Controllers) -> Controllers {
-        let old_controllers = self.controllers.mint_controllers.clone();
-        self.controllers.mint_controllers = new_mint_controllers;
-
-        old_controllers
+    pub fn balance_of(&self, account_owner: &Principal) -> u64 {
+        match self.balances.get(account_owner) {
+            None => 0,
+            Some(b) => *b,
+        }
     }
 
-    pub fn update_info_controllers(&mut self, new_info_controllers: Controllers) -> Controllers {
-        let old_controllers = self.controllers.info_controllers.clone();
-        self.controllers.info_controllers = new_info_controllers;
+    /// Returns at most `limit` holders ordered by principal, starting right after `cursor`,
+    /// together with the cursor of the next page (if there is one).
+    pub fn get_holders(
+        &self,
+        cursor: Option<Principal>,
+        limit: usize,
+    ) -> (Vec<(Principal, u64)>, Option<Principal>) {
+        let limit = limit.min(MAX_HOLDERS_PAGE_SIZE);
+
+        let mut iter = match cursor {
+            Some(c) => self.balances.range((Excluded(c), Unbounded)),
+            None => self.balances.range(..),
+        };
+
+        let holders = iter
+            .by_ref()
+            .take(limit)
+            .map(|(p, b)| (*p, *b))
+            .collect::<Vec<_>>();
 
-        old_controllers
+        let next_cursor = match iter.next() {
+            Some(_) => holders.last().map(|(p, _)| *p),
+            None => None,
+        };
+
+        (holders, next_cursor)
     }
 
-    pub fn balance_of(&self, account_owner: &Principal) -> u64 {
-        match self.balances.get(account_owner) {
-            None => 0,
-            Some(b) => *b,
+    pub fn get_holders_count(&self) -> usize {
+        self.balances.len()
+    }
+
+    /// Returns `n` biggest holders ordered by balance, ties are resolved by principal.
+    /// Only `n` holders are kept in memory at a time, so the whole balance map is never copied.
+    pub fn get_top_holders(&self, n: usize) -> Vec<(Principal, u64)> {
+        let n = n.min(MAX_HOLDERS_PAGE_SIZE);
+        if n == 0 {
+            return Vec::new();
+        }
+
+        // a min-heap of the best holders seen so far, its top is the one to evict first
+        let mut top = BinaryHeap::with_capacity(n + 1);
+
+        for (principal, balance) in self.balances.iter() {
+            top.push(Reverse((*balance, Reverse(*principal))));
+
+            if top.len() > n {
+                top.pop();
+            }
         }
+
+        top.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((balance, Reverse(principal)))| (principal, balance))
+            .collect()
     }
 
     pub fn register_recurrent_transfer_task(&mut self, from: Principal, task_id: TaskId) {
@@ -150,16 +280,177 @@ impl CurrencyToken {
     pub fn get_recurrent_mint_tasks(&self) -> Vec<TaskId> {
         self.recurrent_mint_tasks.iter().cloned().collect()
     }
+
+    /// Moves `qty` from the payer's balance into a new escrow. Escrowed funds stay
+    /// a part of `total_supply` until they are either released or refunded.
+    pub fn create_escrow(
+        &mut self,
+        payer: Principal,
+        request: CreateEscrowRequest,
+        now: u64,
+    ) -> Result<EscrowId, Error> {
+        let CreateEscrowRequest {
+            payee,
+            arbiter,
+            qty,
+            deadline,
+            event_payload,
+        } = request;
+
+        if qty == 0 {
+            return Err(Error::ZeroQuantity);
+        }
+
+        if deadline <= now {
+            return Err(Error::InvalidDeadline);
+        }
+
+        let prev_balance = self.balance_of(&payer);
+
+        if prev_balance < qty {
+            return Err(Error::InsufficientBalance);
+        }
+
+        let new_balance = prev_balance - qty;
+
+        if new_balance == 0 {
+            self.balances.remove(&payer);
+        } else {
+            self.balances.insert(payer, new_balance);
+        }
+
+        let id = self.escrow_id_counter;
+        self.escrow_id_counter += 1;
+
+        self.escrows.insert(
+            id,
+            Escrow {
+                id,
+                payer,
+                payee,
+                arbiter,
+                qty,
+                deadline,
+                event_payload,
+            },
+        );
+
+        Ok(id)
+    }
+
+    pub fn release_escrow(&mut self, caller: Principal, id: EscrowId) -> Result<Escrow, Error> {
+        let escrow = self.escrows.get(&id).ok_or(Error::EscrowNotFound)?;
+
+        if caller != escrow.payer && caller != escrow.arbiter {
+            return Err(Error::AccessDenied);
+        }
+
+        let escrow = self.escrows.remove(&id).unwrap();
+        self.escrow_refund_tasks.remove(&id);
+
+        let new_balance = self.balance_of(&escrow.payee) + escrow.qty;
+        self.balances.insert(escrow.payee, new_balance);
+
+        Ok(escrow)
+    }
+
+    pub fn refund_escrow(&mut self, id: EscrowId, now: u64) -> Result<Escrow, Error> {
+        let escrow = self.escrows.get(&id).ok_or(Error::EscrowNotFound)?;
+
+        if now < escrow.deadline {
+            return Err(Error::EscrowDeadlineNotReached);
+        }
+
+        let escrow = self.escrows.remove(&id).unwrap();
+        self.escrow_refund_tasks.remove(&id);
+
+        let new_balance = self.balance_of(&escrow.payer) + escrow.qty;
+        self.balances.insert(escrow.payer, new_balance);
+
+        Ok(escrow)
+    }
+
+    pub fn register_escrow_refund_task(&mut self, id: EscrowId, task_id: TaskId) {
+        self.escrow_refund_tasks.insert(id, task_id);
+    }
+
+    pub fn get_escrow_refund_task(&self, id: EscrowId) -> Option<TaskId> {
+        self.escrow_refund_tasks.get(&id).cloned()
+    }
+
+    pub fn get_escrow(&self, id: EscrowId) -> Option<Escrow> {
+        self.escrows.get(&id).cloned()
+    }
+
+    pub fn get_escrows_of(&self, participant: &Principal) -> Vec<Escrow> {
+        let mut escrows = self
+            .escrows
+            .values()
+            .filter(|it| {
+                it.payer == *participant || it.payee == *participant || it.arbiter == *participant
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        escrows.sort_by_key(|it| it.id);
+
+        escrows
+    }
+
+    /// Sums up the balances and the escrowed funds separately - together they should always
+    /// be equal to `total_supply`. The sums saturate, so an overflow shows up as a mismatch.
+    pub fn compute_supply(&self) -> (u64, u64) {
+        let balances_sum = self
+            .balances
+            .values()
+            .fold(0u64, |acc, it| acc.saturating_add(*it));
+
+        let escrowed_sum = self
+            .escrows
+            .values()
+            .fold(0u64, |acc, it| acc.saturating_add(it.qty));
+
+        (balances_sum, escrowed_sum)
+    }
+
+    pub fn is_supply_consistent(&self) -> bool {
+        let (balances_sum, escrowed_sum) = self.compute_supply();
+
+        balances_sum.checked_add(escrowed_sum) == Some(self.total_supply)
+    }
+
+    /// SHA-256 over the balances (sorted by owner), the total supply, the token info and the
+    /// controllers of every role. Only depends on the state itself, so two deployments holding
+    /// the same state always produce the same hash.
+    pub fn get_state_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+
+        hasher.update((self.balances.len() as u64).to_be_bytes());
+        for (owner, balance) in &self.balances {
+            let owner = owner.as_slice();
+
+            hasher.update([owner.len() as u8]);
+            hasher.update(owner);
+            hasher.update(balance.to_be_bytes());
+        }
+
+        hasher.update(self.total_supply.to_be_bytes());
+        hasher.update(encode_one(&self.info).expect("Unable to encode token info"));
+        hasher.update(encode_one(self.roles.get_roles()).expect("Unable to encode controllers"));
+
+        hasher.finalize().into()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::{HashMap, HashSet};
-
     use ic_cdk::export::candid::Principal;
     use union_utils::random_principal_test;
 
-    use antifragile_currency_token_client::types::{ControllerList, TokenInfo};
+    use currency_token_client::types::{
+        CreateEscrowRequest, TokenInfo, BURN_ROLE, DEFAULT_ROLES, INFO_ROLE, MINT_ROLE,
+    };
+    use token_common::roles::RoleRegistry;
 
     use crate::common::currency_token::CurrencyToken;
 
@@ -167,20 +458,32 @@ mod tests {
         vec![1u8, 3u8, 3u8, 7u8]
     }
 
+    fn escrow_request(
+        payee: Principal,
+        arbiter: Principal,
+        qty: u64,
+        deadline: u64,
+    ) -> CreateEscrowRequest {
+        CreateEscrowRequest {
+            payee,
+            arbiter,
+            qty,
+            deadline,
+            event_payload: None,
+        }
+    }
+
     fn create_currency_token() -> (CurrencyToken, Principal) {
         let controller = random_principal_test();
-        let token = CurrencyToken {
-            balances: HashMap::new(),
-            total_supply: 0,
-            info: TokenInfo {
+        let token = CurrencyToken::new(
+            TokenInfo {
                 name: String::from("test"),
                 symbol: String::from("TST"),
                 decimals: 8,
+                ..Default::default()
             },
-            controllers: ControllerList::single(Some(controller)),
-            recurrent_mint_tasks: HashSet::new(),
-            recurrent_transfer_tasks: HashMap::new(),
-        };
+            RoleRegistry::new(&DEFAULT_ROLES, &[controller]),
+        );
 
         (token, controller)
     }
@@ -191,8 +494,9 @@ mod tests {
 
         assert!(token.balances.is_empty());
         assert_eq!(token.total_supply, 0);
-        assert!(token.controllers.info_controllers.contains(&controller));
-        assert!(token.controllers.mint_controllers.contains(&controller));
+        assert!(token.roles.has_role(INFO_ROLE, &controller));
+        assert!(token.roles.has_role(MINT_ROLE, &controller));
+        assert!(token.roles.has_role(BURN_ROLE, &controller));
         assert_eq!(token.info.name, String::from("test"));
         assert_eq!(token.info.symbol, String::from("TST"));
         assert_eq!(token.info.decimals, 8);
@@ -217,6 +521,25 @@ mod tests {
         assert_eq!(token.balances.get(&controller).unwrap().clone(), 200);
     }
 
+    #[test]
+    fn mint_quotas_are_respected() {
+        let (mut token, controller) = create_currency_token();
+        let user_1 = random_principal_test();
+
+        token.mint_quotas.set_quota(controller, Some(100), None, 0);
+
+        token.mint_by(controller, user_1, 70, 0).ok().unwrap();
+        token.mint_by(controller, user_1, 40, 0).err().unwrap();
+        token.mint_by(controller, user_1, 0, 0).err().unwrap();
+
+        assert_eq!(token.total_supply, 70);
+        assert_eq!(token.balance_of(&user_1), 70);
+        assert_eq!(
+            token.mint_quotas.get_remaining(&controller, 0),
+            (Some(30), None)
+        );
+    }
+
     #[test]
     fn burning_works_fine() {
         let (mut token, controller) = create_currency_token();
@@ -270,6 +593,11 @@ mod tests {
         token.transfer(user_2, user_1, 1).err().unwrap();
 
         token.transfer(user_2, user_1, 0).err().unwrap();
+
+        token.transfer(user_1, user_1, 400).ok().unwrap();
+
+        assert_eq!(token.balance_of(&user_1), 1000);
+        assert_eq!(token.total_supply, 1000);
     }
 
     #[test]
@@ -280,8 +608,10 @@ mod tests {
             name: String::from("name 1"),
             symbol: String::from("NME1"),
             decimals: 9,
+            description: Some(String::from("description 1")),
+            ..Default::default()
         };
-        token.update_info(new_info_1);
+        token.update_info(new_info_1).ok().unwrap();
 
         assert_eq!(token.info.name, String::from("name 1"));
         assert_eq!(token.info.symbol, String::from("NME1"));
@@ -291,11 +621,210 @@ mod tests {
             name: String::from("name 2"),
             symbol: String::from("NME2"),
             decimals: 2,
+            website: Some(String::from("not a url")),
+            ..Default::default()
         };
-        token.update_info(new_info_2);
+        token.update_info(new_info_2).err().unwrap();
+
+        let new_info_3 = TokenInfo {
+            name: String::from("name 2"),
+            symbol: String::from("NME2"),
+            decimals: 2,
+            ..Default::default()
+        };
+        token.update_info(new_info_3).ok().unwrap();
 
         assert_eq!(token.info.name, String::from("name 2"));
         assert_eq!(token.info.symbol, String::from("NME2"));
         assert_eq!(token.info.decimals, 2);
+
+        let new_info_4 = TokenInfo {
+            name: String::from("name 4"),
+            symbol: String::from("NME-4"),
+            decimals: 2,
+            ..Default::default()
+        };
+        token.update_info(new_info_4).err().unwrap();
+
+        token.mint(controller, 100).ok().unwrap();
+
+        let new_info_5 = TokenInfo {
+            name: String::from("name 5"),
+            symbol: String::from("NME5"),
+            decimals: 8,
+            ..Default::default()
+        };
+        token.update_info(new_info_5).err().unwrap();
+
+        let new_info_6 = TokenInfo {
+            name: String::from("name 6"),
+            symbol: String::from("NME6"),
+            decimals: 2,
+            ..Default::default()
+        };
+        token.update_info(new_info_6).ok().unwrap();
+
+        assert_eq!(token.info.name, String::from("name 6"));
+        assert_eq!(token.info.decimals, 2);
+    }
+
+    #[test]
+    fn escrow_release_works_fine() {
+        let (mut token, _) = create_currency_token();
+        let payer = random_principal_test();
+        let payee = random_principal_test();
+        let arbiter = random_principal_test();
+
+        token.mint(payer, 100).ok().unwrap();
+
+        token
+            .create_escrow(payer, escrow_request(payee, arbiter, 200, 10), 0)
+            .err()
+            .unwrap();
+        token
+            .create_escrow(payer, escrow_request(payee, arbiter, 50, 0), 0)
+            .err()
+            .unwrap();
+
+        let id = token
+            .create_escrow(payer, escrow_request(payee, arbiter, 60, 10), 0)
+            .ok()
+            .unwrap();
+
+        assert_eq!(token.balance_of(&payer), 40);
+        assert_eq!(token.balance_of(&payee), 0);
+        assert_eq!(token.total_supply, 100);
+        assert_eq!(token.get_escrows_of(&arbiter).len(), 1);
+
+        token.release_escrow(payee, id).err().unwrap();
+        token.refund_escrow(id, 5).err().unwrap();
+
+        token.release_escrow(arbiter, id).ok().unwrap();
+
+        assert_eq!(token.balance_of(&payer), 40);
+        assert_eq!(token.balance_of(&payee), 60);
+        assert_eq!(token.total_supply, 100);
+        assert!(token.get_escrow(id).is_none());
+
+        token.release_escrow(payer, id).err().unwrap();
+    }
+
+    #[test]
+    fn escrow_refund_works_fine() {
+        let (mut token, _) = create_currency_token();
+        let payer = random_principal_test();
+        let payee = random_principal_test();
+
+        token.mint(payer, 100).ok().unwrap();
+
+        let id = token
+            .create_escrow(payer, escrow_request(payee, payee, 100, 10), 0)
+            .ok()
+            .unwrap();
+
+        assert!(token.balances.is_empty());
+
+        token.refund_escrow(id, 9).err().unwrap();
+        token.refund_escrow(id, 10).ok().unwrap();
+
+        assert_eq!(token.balance_of(&payer), 100);
+        assert_eq!(token.balance_of(&payee), 0);
+        assert_eq!(token.total_supply, 100);
+
+        token.refund_escrow(id, 11).err().unwrap();
+    }
+
+    #[test]
+    fn supply_audit_works_fine() {
+        let (mut token, _) = create_currency_token();
+        let user_1 = random_principal_test();
+        let user_2 = random_principal_test();
+
+        token.mint(user_1, 100).ok().unwrap();
+        token.transfer(user_1, user_2, 30).ok().unwrap();
+        token
+            .create_escrow(user_1, escrow_request(user_2, user_2, 50, 10), 0)
+            .ok()
+            .unwrap();
+        token.burn(user_2, 10).ok().unwrap();
+
+        assert_eq!(token.compute_supply(), (40, 50));
+        assert!(token.is_supply_consistent());
+
+        token.balances.insert(user_2, 1);
+
+        assert!(!token.is_supply_consistent());
+    }
+
+    #[test]
+    fn state_hash_is_deterministic() {
+        let (mut token_1, controller) = create_currency_token();
+        let mut token_2 = CurrencyToken::new(
+            token_1.info.clone(),
+            RoleRegistry::new(&DEFAULT_ROLES, &[controller]),
+        );
+        let user_1 = random_principal_test();
+        let user_2 = random_principal_test();
+
+        token_1.mint(user_1, 100).ok().unwrap();
+        token_1.mint(user_2, 200).ok().unwrap();
+
+        token_2.mint(user_2, 200).ok().unwrap();
+        token_2.mint(user_1, 100).ok().unwrap();
+
+        assert_eq!(token_1.get_state_hash(), token_2.get_state_hash());
+
+        token_2.transfer(user_2, user_1, 1).ok().unwrap();
+        assert_ne!(token_1.get_state_hash(), token_2.get_state_hash());
+
+        token_2.transfer(user_1, user_2, 1).ok().unwrap();
+        assert_eq!(token_1.get_state_hash(), token_2.get_state_hash());
+
+        token_2.roles.add_role_member(MINT_ROLE, user_1);
+        assert_ne!(token_1.get_state_hash(), token_2.get_state_hash());
+    }
+
+    #[test]
+    fn holders_pagination_works_fine() {
+        let (mut token, _) = create_currency_token();
+
+        for i in 1..=5 {
+            token.mint(random_principal_test(), i * 10).ok().unwrap();
+        }
+
+        assert_eq!(token.get_holders_count(), 5);
+
+        let (page_1, cursor_1) = token.get_holders(None, 2);
+        assert_eq!(page_1.len(), 2);
+        assert!(cursor_1.is_some());
+
+        let (page_2, cursor_2) = token.get_holders(cursor_1, 2);
+        assert_eq!(page_2.len(), 2);
+        assert!(cursor_2.is_some());
+
+        let (page_3, cursor_3) = token.get_holders(cursor_2, 2);
+        assert_eq!(page_3.len(), 1);
+        assert!(cursor_3.is_none());
+
+        let mut all = page_1;
+        all.extend(page_2);
+        all.extend(page_3);
+
+        let (expected, _) = token.get_holders(None, 10);
+        assert_eq!(all, expected);
+
+        let top = token.get_top_holders(2);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].1, 50);
+        assert_eq!(top[1].1, 40);
+
+        // ties are resolved by principal
+        token.mint(random_principal_test(), 40).ok().unwrap();
+
+        let (mut expected, _) = token.get_holders(None, 10);
+        expected.sort_by(|(p1, b1), (p2, b2)| b2.cmp(b1).then(p1.cmp(p2)));
+        assert_eq!(token.get_top_holders(3), expected[..3].to_vec());
+        assert_eq!(token.get_top_holders(10), expected);
+        assert!(token.get_top_holders(0).is_empty());
     }
 }
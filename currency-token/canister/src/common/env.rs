@@ -0,0 +1,421 @@
+#[cfg(any(test, feature = "mock"))]
+use std::sync::Mutex;
+
+use ic_cdk::api::call::{msg_cycles_accept, msg_cycles_available, CallResult};
+use ic_cdk::api::{canister_balance, data_certificate, set_certified_data, time};
+use ic_cdk::export::candid::{CandidType, Principal, Result as CandidResult};
+use ic_cdk::{block_on, call, caller, id, trap};
+use ic_cron::types::{ScheduledTask, SchedulingInterval, TaskId};
+use ic_event_hub::types::IEvent;
+use serde::de::DeserializeOwned;
+
+use currency_token_client::events::LoggedEvent;
+use currency_token_client::types::{AppendEventsRequest, EventRecord};
+
+use crate::common::types::CronTaskKind;
+use crate::{cron_dequeue, cron_enqueue, emit, get_cron_state};
+
+/// Everything the canister needs from the system. Endpoints only reach the system through
+/// `get_env()`, so native tests could run them against `MockEnvironment` instead of a replica.
+pub trait Environment {
+    fn caller(&self) -> Principal;
+    fn canister_id(&self) -> Principal;
+    fn time(&self) -> u64;
+    fn trap(&self, msg: &str) -> !;
+    fn log(&self, msg: &str);
+
+    fn cycles_balance(&self) -> u64;
+    /// Accepts all the cycles attached to the call, returning the accepted amount
+    fn accept_cycles(&mut self) -> u64;
+
+    fn set_certified_data(&mut self, data: &[u8]);
+    fn data_certificate(&self) -> Option<Vec<u8>>;
+
+    /// Delivers the event to the event listeners
+    fn emit<T: LoggedEvent + IEvent>(&mut self, event: T);
+
+    fn schedule<T: CandidType>(
+        &mut self,
+        kind: CronTaskKind,
+        payload: T,
+        interval: SchedulingInterval,
+    ) -> CandidResult<TaskId>;
+    fn dequeue(&mut self, task_id: TaskId);
+    fn has_task(&self, task_id: TaskId) -> bool;
+    /// Returns the task if it is still scheduled and its payload is of the requested type
+    fn get_task<T: CandidType + DeserializeOwned>(&self, task_id: TaskId) -> Option<CronTask<T>>;
+    /// Returns ids and kinds of all the scheduled tasks
+    fn get_scheduled_tasks(&self) -> Vec<(TaskId, u8)>;
+
+    /// Hands the events over to the archive canister, reporting whether they were accepted
+    fn append_to_archive<F: FnOnce(bool) + 'static>(
+        &mut self,
+        archive: Principal,
+        events: Vec<EventRecord>,
+        on_result: F,
+    );
+}
+
+/// A scheduled task with its payload decoded
+pub struct CronTask<T> {
+    pub id: TaskId,
+    pub kind: u8,
+    pub payload: T,
+    pub scheduled_at: u64,
+    pub rescheduled_at: Option<u64>,
+    pub scheduling_interval: SchedulingInterval,
+}
+
+/// Anything the payload of a fired task can be decoded from
+pub trait TaskPayload {
+    fn decode_payload<T: CandidType + DeserializeOwned>(&self) -> CandidResult<T>;
+}
+
+impl TaskPayload for ScheduledTask {
+    fn decode_payload<T: CandidType + DeserializeOwned>(&self) -> CandidResult<T> {
+        self.get_payload::<T>()
+    }
+}
+
+#[cfg(not(any(test, feature = "mock")))]
+pub type Env = CanisterEnvironment;
+
+#[cfg(any(test, feature = "mock"))]
+pub type Env = MockEnvironment;
+
+static mut ENV: Option<Env> = None;
+
+pub fn get_env() -> &'static mut Env {
+    unsafe { ENV.get_or_insert_with(Env::default) }
+}
+
+/// The environment and the state are process-wide, so anything installing them has to hold it
+#[cfg(any(test, feature = "mock"))]
+pub static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+#[cfg(any(test, feature = "mock"))]
+pub fn set_env(env: Env) {
+    unsafe {
+        ENV = Some(env);
+    }
+}
+
+/// The environment of a deployed canister
+#[cfg_attr(any(test, feature = "mock"), allow(dead_code))]
+#[derive(Default)]
+pub struct CanisterEnvironment;
+
+impl Environment for CanisterEnvironment {
+    fn caller(&self) -> Principal {
+        caller()
+    }
+
+    fn canister_id(&self) -> Principal {
+        id()
+    }
+
+    fn time(&self) -> u64 {
+        time()
+    }
+
+    fn trap(&self, msg: &str) -> ! {
+        trap(msg)
+    }
+
+    fn log(&self, msg: &str) {
+        union_utils::log(msg);
+    }
+
+    fn cycles_balance(&self) -> u64 {
+        canister_balance()
+    }
+
+    fn accept_cycles(&mut self) -> u64 {
+        msg_cycles_accept(msg_cycles_available())
+    }
+
+    fn set_certified_data(&mut self, data: &[u8]) {
+        set_certified_data(data);
+    }
+
+    fn data_certificate(&self) -> Option<Vec<u8>> {
+        data_certificate()
+    }
+
+    fn emit<T: LoggedEvent + IEvent>(&mut self, event: T) {
+        emit(event);
+    }
+
+    fn schedule<T: CandidType>(
+        &mut self,
+        kind: CronTaskKind,
+        payload: T,
+        interval: SchedulingInterval,
+    ) -> CandidResult<TaskId> {
+        cron_enqueue(kind as u8, payload, interval)
+    }
+
+    fn dequeue(&mut self, task_id: TaskId) {
+        cron_dequeue(task_id);
+    }
+
+    fn has_task(&self, task_id: TaskId) -> bool {
+        get_cron_state().get_task_by_id(&task_id).is_some()
+    }
+
+    fn get_task<T: CandidType + DeserializeOwned>(&self, task_id: TaskId) -> Option<CronTask<T>> {
+        let task = get_cron_state().get_task_by_id(&task_id)?;
+        let payload = task.get_payload::<T>().ok()?;
+
+        Some(CronTask {
+            id: task.id,
+            kind: task.get_kind(),
+            payload,
+            scheduled_at: task.scheduled_at,
+            rescheduled_at: task.rescheduled_at,
+            scheduling_interval: task.scheduling_interval.clone(),
+        })
+    }
+
+    fn get_scheduled_tasks(&self) -> Vec<(TaskId, u8)> {
+        get_cron_state()
+            .tasks
+            .values()
+            .map(|task| (task.id, task.get_kind()))
+            .collect()
+    }
+
+    fn append_to_archive<F: FnOnce(bool) + 'static>(
+        &mut self,
+        archive: Principal,
+        events: Vec<EventRecord>,
+        on_result: F,
+    ) {
+        block_on(async move {
+            let result: CallResult<()> =
+                call(archive, "append_events", (AppendEventsRequest { events },)).await;
+
+            on_result(result.is_ok());
+        });
+    }
+}
+
+#[cfg(any(test, feature = "mock"))]
+pub use mock::MockEnvironment;
+
+#[cfg(any(test, feature = "mock"))]
+mod mock {
+    use std::collections::BTreeMap;
+
+    use ic_cdk::export::candid::Result as CandidResult;
+    use ic_cdk::export::candid::{decode_one, encode_one, CandidType, Principal};
+    use ic_cron::types::{Iterations, SchedulingInterval, TaskId};
+    use ic_event_hub::types::IEvent;
+    use serde::de::DeserializeOwned;
+
+    use currency_token_client::events::LoggedEvent;
+    use currency_token_client::types::EventRecord;
+
+    use crate::common::env::{CronTask, Environment, TaskPayload};
+    use crate::common::types::CronTaskKind;
+
+    #[derive(Clone)]
+    pub struct MockTask {
+        pub kind: u8,
+        pub payload: Vec<u8>,
+        pub scheduled_at: u64,
+        pub rescheduled_at: Option<u64>,
+        pub scheduling_interval: SchedulingInterval,
+    }
+
+    impl TaskPayload for MockTask {
+        fn decode_payload<T: CandidType + DeserializeOwned>(&self) -> CandidResult<T> {
+            decode_one(&self.payload)
+        }
+    }
+
+    /// In-memory environment: traps turn into panics, emitted events, scheduled tasks and
+    /// archive calls are recorded, so tests could inspect them
+    #[derive(Clone)]
+    pub struct MockEnvironment {
+        pub caller: Principal,
+        pub canister_id: Principal,
+        pub time: u64,
+        pub cycles_balance: u64,
+        pub cycles_available: u64,
+        pub certified_data: Vec<u8>,
+        pub emitted_events: Vec<(String, Vec<u8>)>,
+        pub tasks: BTreeMap<TaskId, MockTask>,
+        pub task_id_counter: TaskId,
+        pub archive_accepts: bool,
+        pub archived_events: Vec<(Principal, Vec<EventRecord>)>,
+    }
+
+    impl Default for MockEnvironment {
+        fn default() -> Self {
+            MockEnvironment {
+                caller: Principal::anonymous(),
+                canister_id: Principal::management_canister(),
+                time: 0,
+                cycles_balance: u64::MAX,
+                cycles_available: 0,
+                certified_data: Vec::new(),
+                emitted_events: Vec::new(),
+                tasks: BTreeMap::new(),
+                task_id_counter: 0,
+                archive_accepts: true,
+                archived_events: Vec::new(),
+            }
+        }
+    }
+
+    impl MockEnvironment {
+        /// Fires the task the same way the scheduler does: a task is dropped right before
+        /// its last iteration gets executed
+        pub fn fire_task(&mut self, task_id: TaskId) -> Option<MockTask> {
+            let now = self.time;
+            let task = self.tasks.get_mut(&task_id)?;
+
+            let last_iteration = match &mut task.scheduling_interval.iterations {
+                Iterations::Exact(n) if *n <= 1 => true,
+                Iterations::Exact(n) => {
+                    *n -= 1;
+                    false
+                }
+                _ => false,
+            };
+
+            if last_iteration {
+                return self.tasks.remove(&task_id);
+            }
+
+            task.rescheduled_at = Some(now);
+
+            Some(task.clone())
+        }
+
+        #[cfg(test)]
+        pub fn get_emitted_event_names(&self) -> Vec<&str> {
+            self.emitted_events
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect()
+        }
+    }
+
+    impl Environment for MockEnvironment {
+        fn caller(&self) -> Principal {
+            self.caller
+        }
+
+        fn canister_id(&self) -> Principal {
+            self.canister_id
+        }
+
+        fn time(&self) -> u64 {
+            self.time
+        }
+
+        fn trap(&self, msg: &str) -> ! {
+            panic!("{}", msg)
+        }
+
+        // the system log reads the caller and the time straight from the replica
+        fn log(&self, _msg: &str) {}
+
+        fn cycles_balance(&self) -> u64 {
+            self.cycles_balance
+        }
+
+        fn accept_cycles(&mut self) -> u64 {
+            let accepted = self.cycles_available;
+
+            self.cycles_available = 0;
+            self.cycles_balance = self.cycles_balance.saturating_add(accepted);
+
+            accepted
+        }
+
+        fn set_certified_data(&mut self, data: &[u8]) {
+            self.certified_data = data.to_vec();
+        }
+
+        fn data_certificate(&self) -> Option<Vec<u8>> {
+            None
+        }
+
+        fn emit<T: LoggedEvent + IEvent>(&mut self, event: T) {
+            let payload = encode_one(&event).unwrap();
+
+            self.emitted_events
+                .push((String::from(LoggedEvent::get_name(&event)), payload));
+        }
+
+        fn schedule<T: CandidType>(
+            &mut self,
+            kind: CronTaskKind,
+            payload: T,
+            interval: SchedulingInterval,
+        ) -> CandidResult<TaskId> {
+            let id = self.task_id_counter;
+
+            let task = MockTask {
+                kind: kind as u8,
+                payload: encode_one(payload)?,
+                scheduled_at: self.time,
+                rescheduled_at: None,
+                scheduling_interval: interval,
+            };
+
+            self.tasks.insert(id, task);
+            self.task_id_counter += 1;
+
+            Ok(id)
+        }
+
+        fn dequeue(&mut self, task_id: TaskId) {
+            self.tasks.remove(&task_id);
+        }
+
+        fn has_task(&self, task_id: TaskId) -> bool {
+            self.tasks.contains_key(&task_id)
+        }
+
+        fn get_task<T: CandidType + DeserializeOwned>(
+            &self,
+            task_id: TaskId,
+        ) -> Option<CronTask<T>> {
+            let task = self.tasks.get(&task_id)?;
+            let payload = task.decode_payload::<T>().ok()?;
+
+            Some(CronTask {
+                id: task_id,
+                kind: task.kind,
+                payload,
+                scheduled_at: task.scheduled_at,
+                rescheduled_at: task.rescheduled_at,
+                scheduling_interval: task.scheduling_interval.clone(),
+            })
+        }
+
+        fn get_scheduled_tasks(&self) -> Vec<(TaskId, u8)> {
+            self.tasks
+                .iter()
+                .map(|(task_id, task)| (*task_id, task.kind))
+                .collect()
+        }
+
+        fn append_to_archive<F: FnOnce(bool) + 'static>(
+            &mut self,
+            archive: Principal,
+            events: Vec<EventRecord>,
+            on_result: F,
+        ) {
+            if self.archive_accepts {
+                self.archived_events.push((archive, events));
+            }
+
+            on_result(self.archive_accepts);
+        }
+    }
+}
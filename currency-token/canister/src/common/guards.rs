@@ -1,28 +1,33 @@
-use ic_cdk::{caller, id};
+use currency_token_client::types::{
+    ADMIN_ROLE, BURN_ROLE, EVENT_LISTENERS_ROLE, INFO_ROLE, MINT_ROLE,
+};
 
+use crate::common::env::{get_env, Environment};
 use crate::get_token;
 
-#[inline(always)]
-pub fn mint_guard() -> Result<(), String> {
-    if get_token().controllers.mint_controllers.contains(&caller()) {
-        Ok(())
-    } else {
-        Err(String::from("The caller is not the mint controller"))
-    }
+/// Generates a guard function that only lets through members of the given role
+macro_rules! role_guard {
+    ($name:ident, $role:expr) => {
+        #[inline(always)]
+        pub fn $name() -> Result<(), String> {
+            if get_token().roles.has_role($role, &get_env().caller()) {
+                Ok(())
+            } else {
+                Err(format!("The caller is not the {} controller", $role))
+            }
+        }
+    };
 }
 
-#[inline(always)]
-pub fn info_guard() -> Result<(), String> {
-    if get_token().controllers.info_controllers.contains(&caller()) {
-        Ok(())
-    } else {
-        Err(String::from("The caller is not the info controller"))
-    }
-}
+role_guard!(mint_guard, MINT_ROLE);
+role_guard!(info_guard, INFO_ROLE);
+role_guard!(burn_guard, BURN_ROLE);
+role_guard!(admin_guard, ADMIN_ROLE);
+role_guard!(event_listeners_guard, EVENT_LISTENERS_ROLE);
 
 #[inline(always)]
 pub fn self_guard() -> Result<(), String> {
-    if caller() == id() {
+    if get_env().caller() == get_env().canister_id() {
         Ok(())
     } else {
         Err(String::from("The caller is not the Currency Token itself"))
@@ -0,0 +1,211 @@
+use std::collections::BTreeSet;
+
+use currency_token_client::types::{MetadataValue, TokenInfo, TokenLogo};
+
+use crate::common::types::Error;
+
+pub const MAX_NAME_LEN: usize = 64;
+pub const MAX_SYMBOL_LEN: usize = 12;
+pub const MAX_DECIMALS: u8 = 18;
+pub const MAX_DESCRIPTION_LEN: usize = 1024;
+pub const MAX_WEBSITE_LEN: usize = 256;
+pub const MAX_LOGO_SIZE: usize = 32 * 1024;
+pub const MAX_METADATA_ENTRIES: usize = 64;
+pub const MAX_METADATA_KEY_LEN: usize = 64;
+pub const MAX_METADATA_VALUE_SIZE: usize = 1024;
+
+/// Checks the whole token info - name, symbol, decimals and the optional metadata.
+/// Names are any printable text without surrounding whitespace, symbols are ASCII alphanumeric.
+pub fn validate_info(info: &TokenInfo) -> Result<(), Error> {
+    let name_len = info.name.chars().count();
+
+    if name_len == 0
+        || name_len > MAX_NAME_LEN
+        || info.name.trim() != info.name
+        || info.name.chars().any(char::is_control)
+    {
+        return Err(Error::InvalidName);
+    }
+
+    if info.symbol.is_empty()
+        || info.symbol.len() > MAX_SYMBOL_LEN
+        || !info.symbol.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        return Err(Error::InvalidSymbol);
+    }
+
+    if info.decimals > MAX_DECIMALS {
+        return Err(Error::InvalidDecimals);
+    }
+
+    validate_metadata(info)
+}
+
+/// Checks the optional token metadata - description, website, logo and custom entries.
+/// Metadata keys follow the ICRC-1 `namespace:key` format.
+pub fn validate_metadata(info: &TokenInfo) -> Result<(), Error> {
+    if let Some(description) = &info.description {
+        if description.chars().count() > MAX_DESCRIPTION_LEN {
+            return Err(Error::DescriptionTooLong);
+        }
+    }
+
+    if let Some(website) = &info.website {
+        let has_valid_scheme = website.starts_with("https://") || website.starts_with("http://");
+
+        if !has_valid_scheme
+            || website.len() > MAX_WEBSITE_LEN
+            || website.contains(char::is_whitespace)
+        {
+            return Err(Error::InvalidWebsite);
+        }
+    }
+
+    if let Some(logo) = &info.logo {
+        validate_logo(logo)?;
+    }
+
+    if info.metadata.len() > MAX_METADATA_ENTRIES {
+        return Err(Error::TooManyMetadataEntries);
+    }
+
+    let mut keys = BTreeSet::new();
+
+    for (key, value) in &info.metadata {
+        if !is_valid_metadata_key(key) {
+            return Err(Error::InvalidMetadataKey);
+        }
+
+        if !keys.insert(key) {
+            return Err(Error::DuplicateMetadataKey);
+        }
+
+        let value_size = match value {
+            MetadataValue::Text(text) => text.len(),
+            MetadataValue::Blob(blob) => blob.len(),
+            MetadataValue::Nat(_) | MetadataValue::Int(_) => 0,
+        };
+
+        if value_size > MAX_METADATA_VALUE_SIZE {
+            return Err(Error::MetadataValueTooLarge);
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_logo(logo: &TokenLogo) -> Result<(), Error> {
+    let (is_image, size) = match logo {
+        TokenLogo::DataUrl(url) => (url.starts_with("data:image/"), url.len()),
+        TokenLogo::Blob { content_type, data } => (content_type.starts_with("image/"), data.len()),
+    };
+
+    if !is_image {
+        return Err(Error::InvalidLogo);
+    }
+
+    if size > MAX_LOGO_SIZE {
+        return Err(Error::LogoTooLarge);
+    }
+
+    Ok(())
+}
+
+fn is_valid_metadata_key(key: &str) -> bool {
+    let mut parts = key.splitn(2, ':');
+
+    let (namespace, name) = match (parts.next(), parts.next()) {
+        (Some(namespace), Some(name)) => (namespace, name),
+        _ => return false,
+    };
+
+    key.len() <= MAX_METADATA_KEY_LEN
+        && !namespace.is_empty()
+        && !name.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == ':')
+}
+
+#[cfg(test)]
+mod tests {
+    use currency_token_client::types::{MetadataValue, TokenInfo, TokenLogo};
+
+    use crate::common::info::{validate_info, validate_metadata, MAX_LOGO_SIZE};
+
+    #[test]
+    fn metadata_validation_works_fine() {
+        let mut info = TokenInfo {
+            name: String::from("test"),
+            symbol: String::from("TST"),
+            decimals: 8,
+            logo: Some(TokenLogo::DataUrl(String::from(
+                "data:image/png;base64,AAAA",
+            ))),
+            description: Some(String::from("Test token")),
+            website: Some(String::from("https://example.com")),
+            metadata: vec![
+                (String::from("icrc1:fee"), MetadataValue::Nat(0)),
+                (
+                    String::from("app:category"),
+                    MetadataValue::Text(String::from("test")),
+                ),
+            ],
+        };
+        validate_metadata(&info).ok().unwrap();
+
+        info.website = Some(String::from("ftp://example.com"));
+        validate_metadata(&info).err().unwrap();
+        info.website = None;
+
+        info.logo = Some(TokenLogo::Blob {
+            content_type: String::from("image/png"),
+            data: vec![0u8; MAX_LOGO_SIZE + 1],
+        });
+        validate_metadata(&info).err().unwrap();
+        info.logo = Some(TokenLogo::DataUrl(String::from(
+            "https://example.com/logo.png",
+        )));
+        validate_metadata(&info).err().unwrap();
+        info.logo = None;
+
+        info.metadata
+            .push((String::from("icrc1:fee"), MetadataValue::Nat(1)));
+        validate_metadata(&info).err().unwrap();
+        info.metadata.pop();
+
+        info.metadata
+            .push((String::from("no_namespace"), MetadataValue::Int(-1)));
+        validate_metadata(&info).err().unwrap();
+    }
+
+    #[test]
+    fn info_validation_works_fine() {
+        let mut info = TokenInfo {
+            name: String::from("Test Token"),
+            symbol: String::from("TST"),
+            decimals: 8,
+            ..Default::default()
+        };
+        validate_info(&info).ok().unwrap();
+
+        info.name = String::from("");
+        validate_info(&info).err().unwrap();
+        info.name = String::from(" Test Token");
+        validate_info(&info).err().unwrap();
+        info.name = String::from("Test\nToken");
+        validate_info(&info).err().unwrap();
+        info.name = String::from("Test Token");
+
+        info.symbol = String::from("");
+        validate_info(&info).err().unwrap();
+        info.symbol = String::from("T$T");
+        validate_info(&info).err().unwrap();
+        info.symbol = String::from("TOOLONGSYMBOL1");
+        validate_info(&info).err().unwrap();
+        info.symbol = String::from("TST");
+
+        info.decimals = 19;
+        validate_info(&info).err().unwrap();
+    }
+}
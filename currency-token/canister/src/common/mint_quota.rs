@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+
+use currency_token_client::types::{MintQuota, MintRateLimit};
+
+use crate::common::types::Error;
+
+/// Per-minter allowances. Minters without a configured quota are unlimited, so the
+/// registry only restricts those principals an admin explicitly constrained.
+#[derive(Default, CandidType, Deserialize)]
+pub struct MintQuotaRegistry {
+    pub quotas: BTreeMap<Principal, MintQuota>,
+}
+
+impl MintQuotaRegistry {
+    /// Replaces the limits of the minter, keeping what was already minted under them.
+    /// Passing no limits at all removes the quota.
+    pub fn set_quota(
+        &mut self,
+        minter: Principal,
+        total_quota: Option<u64>,
+        rate_limit: Option<MintRateLimit>,
+        now: u64,
+    ) -> Option<MintQuota> {
+        if total_quota.is_none() && rate_limit.is_none() {
+            return self.quotas.remove(&minter);
+        }
+
+        let prev_quota = self.quotas.get(&minter).cloned();
+
+        let quota = self.quotas.entry(minter).or_insert(MintQuota {
+            total_quota: None,
+            rate_limit: None,
+            minted_total: 0,
+            minted_in_period: 0,
+            period_start: now,
+        });
+
+        quota.total_quota = total_quota;
+        quota.rate_limit = rate_limit;
+
+        prev_quota
+    }
+
+    /// Counts the quantity against the minter's quota, failing if either the total quota or
+    /// the current period's rate limit would be exceeded
+    pub fn consume(&mut self, minter: Principal, qty: u64, now: u64) -> Result<(), Error> {
+        let quota = match self.quotas.get_mut(&minter) {
+            Some(q) => q,
+            None => return Ok(()),
+        };
+
+        Self::actualize(quota, now);
+
+        if let Some(total_quota) = quota.total_quota {
+            if quota.minted_total + qty > total_quota {
+                return Err(Error::MintQuotaExceeded);
+            }
+        }
+
+        if let Some(rate_limit) = &quota.rate_limit {
+            if quota.minted_in_period + qty > rate_limit.qty {
+                return Err(Error::MintRateLimitExceeded);
+            }
+        }
+
+        quota.minted_total += qty;
+        quota.minted_in_period += qty;
+
+        Ok(())
+    }
+
+    pub fn get_quota(&self, minter: &Principal, now: u64) -> Option<MintQuota> {
+        self.quotas.get(minter).cloned().map(|mut it| {
+            Self::actualize(&mut it, now);
+            it
+        })
+    }
+
+    /// Returns how much the minter is still allowed to mint in total and within the current
+    /// period, `None` meaning there is no such limit
+    pub fn get_remaining(&self, minter: &Principal, now: u64) -> (Option<u64>, Option<u64>) {
+        match self.get_quota(minter, now) {
+            None => (None, None),
+            Some(quota) => (
+                quota
+                    .total_quota
+                    .map(|it| it.saturating_sub(quota.minted_total)),
+                quota
+                    .rate_limit
+                    .as_ref()
+                    .map(|it| it.qty.saturating_sub(quota.minted_in_period)),
+            ),
+        }
+    }
+
+    /// Moves the quota to the period containing `now`, resetting the period's counter
+    fn actualize(quota: &mut MintQuota, now: u64) {
+        let period_nano = match &quota.rate_limit {
+            Some(rate_limit) if rate_limit.period_nano > 0 => rate_limit.period_nano,
+            _ => return,
+        };
+
+        if now >= quota.period_start + period_nano {
+            quota.period_start = now - (now - quota.period_start) % period_nano;
+            quota.minted_in_period = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use currency_token_client::types::MintRateLimit;
+    use union_utils::random_principal_test;
+
+    use crate::common::mint_quota::MintQuotaRegistry;
+
+    #[test]
+    fn quotas_work_fine() {
+        let mut quotas = MintQuotaRegistry::default();
+        let minter = random_principal_test();
+        let other_minter = random_principal_test();
+
+        quotas.set_quota(
+            minter,
+            Some(150),
+            Some(MintRateLimit {
+                qty: 100,
+                period_nano: 10,
+            }),
+            0,
+        );
+
+        quotas.consume(minter, 60, 1).ok().unwrap();
+        quotas.consume(minter, 60, 2).err().unwrap();
+        quotas.consume(other_minter, 1000, 2).ok().unwrap();
+
+        assert_eq!(quotas.get_remaining(&minter, 3), (Some(90), Some(40)));
+        assert_eq!(quotas.get_remaining(&minter, 12), (Some(90), Some(100)));
+        assert_eq!(quotas.get_remaining(&other_minter, 12), (None, None));
+
+        quotas.consume(minter, 60, 12).ok().unwrap();
+        quotas.consume(minter, 40, 25).err().unwrap();
+        quotas.consume(minter, 30, 25).ok().unwrap();
+
+        assert_eq!(quotas.get_remaining(&minter, 25), (Some(0), Some(70)));
+
+        quotas.set_quota(minter, Some(200), None, 26);
+        quotas.consume(minter, 50, 26).ok().unwrap();
+        quotas.consume(minter, 1, 26).err().unwrap();
+
+        quotas.set_quota(minter, None, None, 27);
+        assert!(quotas.get_quota(&minter, 27).is_none());
+        quotas.consume(minter, 1000, 27).ok().unwrap();
+    }
+}
@@ -1,3 +1,9 @@
+pub mod certification;
 pub mod currency_token;
+pub mod env;
 pub mod guards;
+pub mod info;
+pub mod mint_quota;
+#[cfg(test)]
+mod state_machine_tests;
 pub mod types;
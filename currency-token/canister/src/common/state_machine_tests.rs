@@ -0,0 +1,349 @@
+//! Randomized state-machine tests: generated sequences of operations are applied both to
+//! `CurrencyToken` and to a trivial reference model, which are compared after every step.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ic_cdk::export::candid::Principal;
+use ic_cron::types::TaskId;
+use proptest::prelude::*;
+
+use currency_token_client::types::{CreateEscrowRequest, EscrowId, TokenInfo, DEFAULT_ROLES};
+use token_common::roles::RoleRegistry;
+
+use crate::common::currency_token::CurrencyToken;
+
+/// Accounts are picked from a small pool, so that operations often hit the same accounts
+const ACCOUNTS: u8 = 5;
+const MAX_QTY: u64 = 1000;
+const MAX_ESCROW_ID: EscrowId = 8;
+const MAX_TASK_ID: TaskId = 4;
+const ESCROW_DEADLINE: u64 = 10;
+
+fn account(idx: u8) -> Principal {
+    Principal::from_slice(&[idx, 1])
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Mint {
+        to: u8,
+        qty: u64,
+    },
+    Transfer {
+        from: u8,
+        to: u8,
+        qty: u64,
+    },
+    Burn {
+        from: u8,
+        qty: u64,
+    },
+    CreateEscrow {
+        payer: u8,
+        payee: u8,
+        arbiter: u8,
+        qty: u64,
+    },
+    ReleaseEscrow {
+        caller: u8,
+        id: EscrowId,
+    },
+    RefundEscrow {
+        id: EscrowId,
+        now: u64,
+    },
+    RegisterRecurrentTransfer {
+        from: u8,
+        task_id: TaskId,
+    },
+    UnregisterRecurrentTransfer {
+        from: u8,
+        task_id: TaskId,
+    },
+    RegisterRecurrentMint {
+        task_id: TaskId,
+    },
+    UnregisterRecurrentMint {
+        task_id: TaskId,
+    },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let acc = || 0..ACCOUNTS;
+    let qty = || 0..=MAX_QTY;
+    let escrow_id = || 0..MAX_ESCROW_ID;
+    let task_id = || 0..MAX_TASK_ID;
+
+    prop_oneof![
+        (acc(), qty()).prop_map(|(to, qty)| Op::Mint { to, qty }),
+        (acc(), acc(), qty()).prop_map(|(from, to, qty)| Op::Transfer { from, to, qty }),
+        (acc(), qty()).prop_map(|(from, qty)| Op::Burn { from, qty }),
+        (acc(), acc(), acc(), qty()).prop_map(|(payer, payee, arbiter, qty)| Op::CreateEscrow {
+            payer,
+            payee,
+            arbiter,
+            qty
+        }),
+        (acc(), escrow_id()).prop_map(|(caller, id)| Op::ReleaseEscrow { caller, id }),
+        (escrow_id(), 0..ESCROW_DEADLINE * 2).prop_map(|(id, now)| Op::RefundEscrow { id, now }),
+        (acc(), task_id())
+            .prop_map(|(from, task_id)| Op::RegisterRecurrentTransfer { from, task_id }),
+        (acc(), task_id())
+            .prop_map(|(from, task_id)| Op::UnregisterRecurrentTransfer { from, task_id }),
+        task_id().prop_map(|task_id| Op::RegisterRecurrentMint { task_id }),
+        task_id().prop_map(|task_id| Op::UnregisterRecurrentMint { task_id }),
+    ]
+}
+
+struct ModelEscrow {
+    payer: u8,
+    payee: u8,
+    arbiter: u8,
+    qty: u64,
+}
+
+#[derive(Default)]
+struct Model {
+    balances: BTreeMap<u8, u64>,
+    total_supply: u64,
+    escrows: BTreeMap<EscrowId, ModelEscrow>,
+    escrow_id_counter: EscrowId,
+    recurrent_transfer_tasks: BTreeMap<u8, BTreeSet<TaskId>>,
+    recurrent_mint_tasks: BTreeSet<TaskId>,
+}
+
+impl Model {
+    fn balance(&self, acc: u8) -> u64 {
+        self.balances.get(&acc).cloned().unwrap_or_default()
+    }
+
+    fn set_balance(&mut self, acc: u8, balance: u64) {
+        if balance == 0 {
+            self.balances.remove(&acc);
+        } else {
+            self.balances.insert(acc, balance);
+        }
+    }
+
+    /// Returns whether the operation should succeed
+    fn apply(&mut self, op: &Op) -> bool {
+        match *op {
+            Op::Mint { to, qty } => {
+                if qty == 0 {
+                    return false;
+                }
+
+                self.set_balance(to, self.balance(to) + qty);
+                self.total_supply += qty;
+
+                true
+            }
+            Op::Transfer { from, to, qty } => {
+                if qty == 0 || self.balance(from) < qty {
+                    return false;
+                }
+
+                self.set_balance(from, self.balance(from) - qty);
+                self.set_balance(to, self.balance(to) + qty);
+
+                true
+            }
+            Op::Burn { from, qty } => {
+                if qty == 0 || self.balance(from) < qty {
+                    return false;
+                }
+
+                self.set_balance(from, self.balance(from) - qty);
+                self.total_supply -= qty;
+
+                true
+            }
+            Op::CreateEscrow {
+                payer,
+                payee,
+                arbiter,
+                qty,
+            } => {
+                if qty == 0 || self.balance(payer) < qty {
+                    return false;
+                }
+
+                self.set_balance(payer, self.balance(payer) - qty);
+                self.escrows.insert(
+                    self.escrow_id_counter,
+                    ModelEscrow {
+                        payer,
+                        payee,
+                        arbiter,
+                        qty,
+                    },
+                );
+                self.escrow_id_counter += 1;
+
+                true
+            }
+            Op::ReleaseEscrow { caller, id } => match self.escrows.get(&id) {
+                Some(escrow) if caller == escrow.payer || caller == escrow.arbiter => {
+                    let escrow = self.escrows.remove(&id).unwrap();
+                    self.set_balance(escrow.payee, self.balance(escrow.payee) + escrow.qty);
+
+                    true
+                }
+                _ => false,
+            },
+            Op::RefundEscrow { id, now } => match self.escrows.get(&id) {
+                Some(_) if now >= ESCROW_DEADLINE => {
+                    let escrow = self.escrows.remove(&id).unwrap();
+                    self.set_balance(escrow.payer, self.balance(escrow.payer) + escrow.qty);
+
+                    true
+                }
+                _ => false,
+            },
+            Op::RegisterRecurrentTransfer { from, task_id } => {
+                self.recurrent_transfer_tasks
+                    .entry(from)
+                    .or_default()
+                    .insert(task_id);
+
+                true
+            }
+            Op::UnregisterRecurrentTransfer { from, task_id } => self
+                .recurrent_transfer_tasks
+                .get_mut(&from)
+                .map(|tasks| tasks.remove(&task_id))
+                .unwrap_or(false),
+            Op::RegisterRecurrentMint { task_id } => {
+                self.recurrent_mint_tasks.insert(task_id);
+
+                true
+            }
+            Op::UnregisterRecurrentMint { task_id } => self.recurrent_mint_tasks.remove(&task_id),
+        }
+    }
+}
+
+fn create_token() -> CurrencyToken {
+    CurrencyToken::new(
+        TokenInfo {
+            name: String::from("test"),
+            symbol: String::from("TST"),
+            decimals: 8,
+            ..Default::default()
+        },
+        RoleRegistry::new(&DEFAULT_ROLES, &[account(0)]),
+    )
+}
+
+/// Returns whether the operation succeeded
+fn apply(token: &mut CurrencyToken, op: &Op) -> bool {
+    match *op {
+        Op::Mint { to, qty } => token.mint(account(to), qty).is_ok(),
+        Op::Transfer { from, to, qty } => token.transfer(account(from), account(to), qty).is_ok(),
+        Op::Burn { from, qty } => token.burn(account(from), qty).is_ok(),
+        Op::CreateEscrow {
+            payer,
+            payee,
+            arbiter,
+            qty,
+        } => token
+            .create_escrow(
+                account(payer),
+                CreateEscrowRequest {
+                    payee: account(payee),
+                    arbiter: account(arbiter),
+                    qty,
+                    deadline: ESCROW_DEADLINE,
+                    event_payload: None,
+                },
+                0,
+            )
+            .is_ok(),
+        Op::ReleaseEscrow { caller, id } => token.release_escrow(account(caller), id).is_ok(),
+        Op::RefundEscrow { id, now } => token.refund_escrow(id, now).is_ok(),
+        Op::RegisterRecurrentTransfer { from, task_id } => {
+            token.register_recurrent_transfer_task(account(from), task_id);
+
+            true
+        }
+        Op::UnregisterRecurrentTransfer { from, task_id } => {
+            token.unregister_recurrent_transfer_task(account(from), task_id)
+        }
+        Op::RegisterRecurrentMint { task_id } => {
+            token.register_recurrent_mint_task(task_id);
+
+            true
+        }
+        Op::UnregisterRecurrentMint { task_id } => token.unregister_recurrent_mint_task(task_id),
+    }
+}
+
+fn check_state(token: &CurrencyToken, model: &Model) -> Result<(), TestCaseError> {
+    let expected_balances = model
+        .balances
+        .iter()
+        .map(|(acc, balance)| (account(*acc), *balance))
+        .collect::<BTreeMap<_, _>>();
+
+    prop_assert_eq!(&token.balances, &expected_balances);
+    prop_assert_eq!(token.total_supply, model.total_supply);
+
+    prop_assert_eq!(token.escrows.len(), model.escrows.len());
+    for (id, expected) in &model.escrows {
+        let escrow = token.get_escrow(*id);
+        prop_assert!(escrow.is_some());
+
+        let escrow = escrow.unwrap();
+        prop_assert_eq!(escrow.payer, account(expected.payer));
+        prop_assert_eq!(escrow.payee, account(expected.payee));
+        prop_assert_eq!(escrow.arbiter, account(expected.arbiter));
+        prop_assert_eq!(escrow.qty, expected.qty);
+    }
+
+    for acc in 0..ACCOUNTS {
+        let mut tasks = token.get_recurrent_transfer_tasks(account(acc));
+        tasks.sort_unstable();
+
+        let expected = model
+            .recurrent_transfer_tasks
+            .get(&acc)
+            .map(|it| it.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        prop_assert_eq!(tasks, expected);
+    }
+
+    let mut mint_tasks = token.get_recurrent_mint_tasks();
+    mint_tasks.sort_unstable();
+    prop_assert_eq!(
+        mint_tasks,
+        model
+            .recurrent_mint_tasks
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>()
+    );
+
+    // invariants which hold regardless of the model
+    prop_assert!(token.is_supply_consistent());
+    prop_assert!(token.balances.values().all(|it| *it > 0));
+    prop_assert_eq!(token.get_holders_count(), token.balances.len());
+
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn currency_token_behaves_like_the_model(ops in prop::collection::vec(op(), 1..100)) {
+        let mut token = create_token();
+        let mut model = Model::default();
+
+        for op in &ops {
+            let succeed = apply(&mut token, op);
+            let expected = model.apply(op);
+
+            prop_assert_eq!(succeed, expected, "unexpected outcome of {:?}", op);
+            check_state(&token, &model)?;
+        }
+    }
+}
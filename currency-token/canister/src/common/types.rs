@@ -4,13 +4,30 @@ use ic_cdk::export::candid::{CandidType, Deserialize};
 use ic_cdk::export::Principal;
 use ic_cron::u8_enum;
 
-use currency_token_client::types::Payload;
+use currency_token_client::types::{ControllersUpdateId, EscrowId, Payload};
 
 pub enum Error {
     InsufficientBalance,
     ZeroQuantity,
     AccessDenied,
     ForbiddenOperation,
+    InvalidDeadline,
+    EscrowNotFound,
+    EscrowDeadlineNotReached,
+    DescriptionTooLong,
+    InvalidWebsite,
+    InvalidLogo,
+    LogoTooLarge,
+    TooManyMetadataEntries,
+    InvalidMetadataKey,
+    DuplicateMetadataKey,
+    MetadataValueTooLarge,
+    InvalidName,
+    InvalidSymbol,
+    InvalidDecimals,
+    DecimalsImmutable,
+    MintQuotaExceeded,
+    MintRateLimitExceeded,
 }
 
 impl Display for Error {
@@ -20,6 +37,23 @@ impl Display for Error {
             Error::ZeroQuantity => "ZeroQuantity",
             Error::AccessDenied => "AccessDenied",
             Error::ForbiddenOperation => "ForbiddenOperation",
+            Error::InvalidDeadline => "InvalidDeadline",
+            Error::EscrowNotFound => "EscrowNotFound",
+            Error::EscrowDeadlineNotReached => "EscrowDeadlineNotReached",
+            Error::DescriptionTooLong => "DescriptionTooLong",
+            Error::InvalidWebsite => "InvalidWebsite",
+            Error::InvalidLogo => "InvalidLogo",
+            Error::LogoTooLarge => "LogoTooLarge",
+            Error::TooManyMetadataEntries => "TooManyMetadataEntries",
+            Error::InvalidMetadataKey => "InvalidMetadataKey",
+            Error::DuplicateMetadataKey => "DuplicateMetadataKey",
+            Error::MetadataValueTooLarge => "MetadataValueTooLarge",
+            Error::InvalidName => "InvalidName",
+            Error::InvalidSymbol => "InvalidSymbol",
+            Error::InvalidDecimals => "InvalidDecimals",
+            Error::DecimalsImmutable => "DecimalsImmutable",
+            Error::MintQuotaExceeded => "MintQuotaExceeded",
+            Error::MintRateLimitExceeded => "MintRateLimitExceeded",
         };
 
         f.write_str(str)
@@ -30,6 +64,8 @@ u8_enum! {
     pub enum CronTaskKind {
         RecurrentTransfer,
         RecurrentMint,
+        EscrowRefund,
+        ControllersUpdate,
     }
 }
 
@@ -43,7 +79,18 @@ pub struct RecurrentTransferTask {
 
 #[derive(CandidType, Deserialize)]
 pub struct RecurrentMintTask {
+    pub minter: Principal,
     pub to: Principal,
     pub qty: u64,
     pub event_payload: Payload,
 }
+
+#[derive(CandidType, Deserialize)]
+pub struct EscrowRefundTask {
+    pub escrow_id: EscrowId,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ControllersUpdateTask {
+    pub update_id: ControllersUpdateId,
+}
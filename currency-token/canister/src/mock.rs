@@ -0,0 +1,573 @@
+//! In-memory currency token for native unit tests of canisters integrating with it. The mock
+//! runs the canister's own endpoints against `MockEnvironment`, so it behaves the same way the
+//! deployed token does: guards reject, traps roll the state back, queries never persist.
+//!
+//! Never enable the `mock` feature for a deployed canister, it replaces the system calls.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use async_trait::async_trait;
+use ic_cdk::api::call::{CallResult, RejectionCode};
+use ic_cdk::export::candid::{decode_one, encode_one, Principal};
+use ic_cron::types::TaskId;
+use union_utils::random_principal_test;
+
+use currency_token_client::api::CurrencyTokenApi;
+use currency_token_client::types::{
+    ApproveProposalResponse, AuditStateResponse, BurnFromRequest, BurnRequest, ClawbackRequest,
+    ControllersHandoverRequest, ControllersUpdateDelay, ControllersUpdateIdRequest,
+    CreateEscrowRequest, CreateEscrowResponse, DequeueRecurrentTaskRequest,
+    DequeueRecurrentTaskResponse, EscrowIdRequest, GetApprovalThresholdsResponse,
+    GetBalanceOfRequest, GetBalanceOfResponse, GetBalancesOfRequest, GetBalancesOfResponse,
+    GetControllersHandoversResponse, GetControllersResponse, GetCyclesBalanceResponse,
+    GetEscrowResponse, GetEscrowsOfRequest, GetEscrowsOfResponse, GetEventsRequest,
+    GetEventsResponse, GetHoldersCountResponse, GetHoldersRequest, GetHoldersResponse,
+    GetInfoResponse, GetMetricsResponse, GetMintQuotaRequest, GetMintQuotaResponse,
+    GetProposalResponse, GetProposalsResponse, GetRecurrentMintTasksResponse,
+    GetRecurrentTransferTasksRequest, GetRecurrentTransferTasksResponse, GetRoleMembersRequest,
+    GetRoleMembersResponse, GetScheduledControllersUpdatesResponse, GetStateHashResponse,
+    GetTopHoldersRequest, GetTopHoldersResponse, GetTotalSupplyResponse, GetTransactionsRequest,
+    GetTransactionsResponse, HasRoleResponse, InitRequest, ProposalIdRequest, ProposeRequest,
+    ProposeResponse, RoleRequest, SetApprovalThresholdRequest, SetApprovalThresholdResponse,
+    SetCyclesLowWaterMarkRequest, SetEventArchiveRequest, SetMintQuotaRequest, SetRoleAdminRequest,
+    SetRoleAdminResponse, TransferRequest, UpdateControllersRequest, UpdateControllersResponse,
+    UpdateInfoRequest, UpdateInfoResponse,
+};
+
+use crate::common::currency_token::CurrencyToken;
+use crate::common::env::{get_env, set_env, MockEnvironment, ENV_LOCK};
+use crate::common::guards::{
+    admin_guard, burn_guard, event_listeners_guard, info_guard, mint_guard,
+};
+use crate::*;
+
+type Guard = fn() -> Result<(), String>;
+
+struct MockCanister {
+    state: CurrencyToken,
+    env: MockEnvironment,
+}
+
+/// A currency token living in memory. Every instance has its own state, calls are executed
+/// one at a time on behalf of the current caller.
+pub struct MockCurrencyToken {
+    canister: RefCell<MockCanister>,
+}
+
+impl MockCurrencyToken {
+    /// Deploys the token on behalf of the controller
+    pub fn new(controller: Principal, request: InitRequest) -> Self {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        set_env(MockEnvironment {
+            caller: controller,
+            canister_id: random_principal_test(),
+            ..Default::default()
+        });
+        init(request);
+
+        MockCurrencyToken {
+            canister: RefCell::new(MockCanister {
+                state: unsafe { STATE.take() }.unwrap(),
+                env: std::mem::take(get_env()),
+            }),
+        }
+    }
+
+    /// Sets the principal the following calls are made by
+    pub fn set_caller(&self, caller: Principal) {
+        self.canister.borrow_mut().env.caller = caller;
+    }
+
+    pub fn get_canister_id(&self) -> Principal {
+        self.canister.borrow().env.canister_id
+    }
+
+    pub fn set_time(&self, time: u64) {
+        self.canister.borrow_mut().env.time = time;
+    }
+
+    /// Names and candid-encoded bodies of the events emitted so far
+    pub fn get_emitted_events(&self) -> Vec<(String, Vec<u8>)> {
+        self.canister.borrow().env.emitted_events.clone()
+    }
+
+    /// Executes the scheduled task the same way the heartbeat does
+    pub fn fire_task(&self, task_id: TaskId) -> CallResult<()> {
+        self.update(None, move || {
+            let caller = get_env().caller;
+            get_env().caller = get_env().canister_id;
+
+            let task = get_env().fire_task(task_id).unwrap_or_else(|| {
+                get_env().trap(format!("Task {} is not scheduled", task_id).as_str())
+            });
+            _execute_cron_task(task_id, task.kind, &task);
+
+            get_env().caller = caller;
+        })
+    }
+
+    fn update<R, F: FnOnce() -> R>(&self, guard: Option<Guard>, endpoint: F) -> CallResult<R> {
+        self.execute(guard, endpoint, true)
+    }
+
+    fn query<R, F: FnOnce() -> R>(&self, endpoint: F) -> CallResult<R> {
+        self.execute(None, endpoint, false)
+    }
+
+    /// Installs a copy of this instance's state and environment, runs the endpoint and takes
+    /// the copy back only if the call succeeded and is allowed to persist
+    fn execute<R, F: FnOnce() -> R>(
+        &self,
+        guard: Option<Guard>,
+        endpoint: F,
+        persist: bool,
+    ) -> CallResult<R> {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut canister = self.canister.borrow_mut();
+
+        let state_copy = decode_one(&encode_one(&canister.state).unwrap()).unwrap();
+        unsafe {
+            STATE = Some(state_copy);
+        }
+        set_env(canister.env.clone());
+
+        let result = match guard.map_or(Ok(()), |guard| guard()) {
+            Ok(_) => catch_unwind(AssertUnwindSafe(endpoint))
+                .map_err(|e| (RejectionCode::CanisterError, panic_message(e))),
+            Err(msg) => Err((RejectionCode::CanisterReject, msg)),
+        };
+
+        let state = unsafe { STATE.take() }.unwrap();
+        let env = std::mem::take(get_env());
+
+        if result.is_ok() && persist {
+            canister.state = state;
+            canister.env = env;
+        }
+
+        result
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map(|msg| String::from(*msg))
+            .unwrap_or_default(),
+    }
+}
+
+#[async_trait(?Send)]
+impl CurrencyTokenApi for MockCurrencyToken {
+    async fn mint(&self, request: TransferRequest) -> CallResult<()> {
+        self.update(Some(mint_guard), move || mint(request))
+    }
+
+    async fn transfer(&self, request: TransferRequest) -> CallResult<()> {
+        self.update(None, move || transfer(request))
+    }
+
+    async fn burn(&self, request: BurnRequest) -> CallResult<()> {
+        self.update(None, move || burn(request))
+    }
+
+    async fn burn_from(&self, request: BurnFromRequest) -> CallResult<()> {
+        self.update(Some(burn_guard), move || burn_from(request))
+    }
+
+    async fn clawback(&self, request: ClawbackRequest) -> CallResult<()> {
+        self.update(Some(burn_guard), move || clawback(request))
+    }
+
+    async fn get_balance_of(
+        &self,
+        request: GetBalanceOfRequest,
+    ) -> CallResult<(GetBalanceOfResponse,)> {
+        self.query(move || get_balance_of(request)).map(|it| (it,))
+    }
+
+    async fn get_balances_of(
+        &self,
+        request: GetBalancesOfRequest,
+    ) -> CallResult<(GetBalancesOfResponse,)> {
+        self.query(move || get_balances_of(request)).map(|it| (it,))
+    }
+
+    async fn get_total_supply(&self) -> CallResult<(GetTotalSupplyResponse,)> {
+        self.query(get_total_supply).map(|it| (it,))
+    }
+
+    async fn get_holders(&self, request: GetHoldersRequest) -> CallResult<(GetHoldersResponse,)> {
+        self.query(move || get_holders(request)).map(|it| (it,))
+    }
+
+    async fn get_holders_count(&self) -> CallResult<(GetHoldersCountResponse,)> {
+        self.query(get_holders_count).map(|it| (it,))
+    }
+
+    async fn get_top_holders(
+        &self,
+        request: GetTopHoldersRequest,
+    ) -> CallResult<(GetTopHoldersResponse,)> {
+        self.query(move || get_top_holders(request)).map(|it| (it,))
+    }
+
+    async fn get_info(&self) -> CallResult<(GetInfoResponse,)> {
+        self.query(get_info).map(|it| (it,))
+    }
+
+    async fn update_info(&self, request: UpdateInfoRequest) -> CallResult<(UpdateInfoResponse,)> {
+        self.update(Some(info_guard), move || update_info(request))
+            .map(|it| (it,))
+    }
+
+    async fn get_controllers(&self) -> CallResult<(GetControllersResponse,)> {
+        self.query(get_controllers).map(|it| (it,))
+    }
+
+    async fn grant_role(&self, request: RoleRequest) -> CallResult<()> {
+        self.update(None, move || grant_role(request))
+    }
+
+    async fn revoke_role(&self, request: RoleRequest) -> CallResult<()> {
+        self.update(None, move || revoke_role(request))
+    }
+
+    async fn set_role_admin(
+        &self,
+        request: SetRoleAdminRequest,
+    ) -> CallResult<(SetRoleAdminResponse,)> {
+        self.update(None, move || set_role_admin(request))
+            .map(|it| (it,))
+    }
+
+    async fn has_role(&self, request: RoleRequest) -> CallResult<(HasRoleResponse,)> {
+        self.query(move || has_role(request)).map(|it| (it,))
+    }
+
+    async fn get_role_members(
+        &self,
+        request: GetRoleMembersRequest,
+    ) -> CallResult<(GetRoleMembersResponse,)> {
+        self.query(move || get_role_members(request))
+            .map(|it| (it,))
+    }
+
+    async fn update_info_controller(
+        &self,
+        request: UpdateControllersRequest,
+    ) -> CallResult<(UpdateControllersResponse,)> {
+        self.update(Some(info_guard), move || update_info_controller(request))
+            .map(|it| (it,))
+    }
+
+    async fn update_mint_controller(
+        &self,
+        request: UpdateControllersRequest,
+    ) -> CallResult<(UpdateControllersResponse,)> {
+        self.update(Some(mint_guard), move || update_mint_controller(request))
+            .map(|it| (it,))
+    }
+
+    async fn update_burn_controller(
+        &self,
+        request: UpdateControllersRequest,
+    ) -> CallResult<(UpdateControllersResponse,)> {
+        self.update(Some(burn_guard), move || update_burn_controller(request))
+            .map(|it| (it,))
+    }
+
+    async fn update_event_listeners_controller(
+        &self,
+        request: UpdateControllersRequest,
+    ) -> CallResult<(UpdateControllersResponse,)> {
+        self.update(Some(event_listeners_guard), move || {
+            update_event_listeners_controller(request)
+        })
+        .map(|it| (it,))
+    }
+
+    async fn accept_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()> {
+        self.update(None, move || accept_controllers_handover(request))
+    }
+
+    async fn cancel_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()> {
+        self.update(None, move || cancel_controllers_handover(request))
+    }
+
+    async fn get_controllers_handovers(&self) -> CallResult<(GetControllersHandoversResponse,)> {
+        self.query(get_controllers_handovers).map(|it| (it,))
+    }
+
+    async fn cancel_controllers_update(
+        &self,
+        request: ControllersUpdateIdRequest,
+    ) -> CallResult<()> {
+        self.update(None, move || cancel_controllers_update(request))
+    }
+
+    async fn get_scheduled_controllers_updates(
+        &self,
+    ) -> CallResult<(GetScheduledControllersUpdatesResponse,)> {
+        self.query(get_scheduled_controllers_updates)
+            .map(|it| (it,))
+    }
+
+    async fn set_controllers_update_delay(
+        &self,
+        request: ControllersUpdateDelay,
+    ) -> CallResult<()> {
+        self.update(Some(admin_guard), move || {
+            set_controllers_update_delay(request)
+        })
+    }
+
+    async fn get_controllers_update_delay(&self) -> CallResult<(ControllersUpdateDelay,)> {
+        self.query(get_controllers_update_delay).map(|it| (it,))
+    }
+
+    async fn dequeue_recurrent_transfer_tasks(
+        &self,
+        request: DequeueRecurrentTaskRequest,
+    ) -> CallResult<(DequeueRecurrentTaskResponse,)> {
+        self.update(None, move || dequeue_recurrent_transfer_tasks(request))
+            .map(|it| (it,))
+    }
+
+    async fn get_recurrent_transfer_tasks(
+        &self,
+        request: GetRecurrentTransferTasksRequest,
+    ) -> CallResult<(GetRecurrentTransferTasksResponse,)> {
+        self.query(move || get_recurrent_transfer_tasks(request))
+            .map(|it| (it,))
+    }
+
+    async fn dequeue_recurrent_mint_tasks(
+        &self,
+        request: DequeueRecurrentTaskRequest,
+    ) -> CallResult<(DequeueRecurrentTaskResponse,)> {
+        self.update(Some(mint_guard), move || {
+            dequeue_recurrent_mint_tasks(request)
+        })
+        .map(|it| (it,))
+    }
+
+    async fn get_recurrent_mint_tasks(&self) -> CallResult<(GetRecurrentMintTasksResponse,)> {
+        self.query(get_recurrent_mint_tasks).map(|it| (it,))
+    }
+
+    async fn set_mint_quota(&self, request: SetMintQuotaRequest) -> CallResult<()> {
+        self.update(Some(admin_guard), move || set_mint_quota(request))
+    }
+
+    async fn get_mint_quota(
+        &self,
+        request: GetMintQuotaRequest,
+    ) -> CallResult<(GetMintQuotaResponse,)> {
+        self.query(move || get_mint_quota(request)).map(|it| (it,))
+    }
+
+    async fn set_approval_threshold(
+        &self,
+        request: SetApprovalThresholdRequest,
+    ) -> CallResult<(SetApprovalThresholdResponse,)> {
+        self.update(None, move || set_approval_threshold(request))
+            .map(|it| (it,))
+    }
+
+    async fn get_approval_thresholds(&self) -> CallResult<(GetApprovalThresholdsResponse,)> {
+        self.query(get_approval_thresholds).map(|it| (it,))
+    }
+
+    async fn propose(&self, request: ProposeRequest) -> CallResult<(ProposeResponse,)> {
+        self.update(None, move || propose(request)).map(|it| (it,))
+    }
+
+    async fn approve_proposal(
+        &self,
+        request: ProposalIdRequest,
+    ) -> CallResult<(ApproveProposalResponse,)> {
+        self.update(None, move || approve_proposal(request))
+            .map(|it| (it,))
+    }
+
+    async fn get_proposal(&self, request: ProposalIdRequest) -> CallResult<(GetProposalResponse,)> {
+        self.query(move || get_proposal(request)).map(|it| (it,))
+    }
+
+    async fn get_pending_proposals(&self) -> CallResult<(GetProposalsResponse,)> {
+        self.query(get_pending_proposals).map(|it| (it,))
+    }
+
+    async fn create_escrow(
+        &self,
+        request: CreateEscrowRequest,
+    ) -> CallResult<(CreateEscrowResponse,)> {
+        self.update(None, move || create_escrow(request))
+            .map(|it| (it,))
+    }
+
+    async fn release_escrow(&self, request: EscrowIdRequest) -> CallResult<()> {
+        self.update(None, move || release_escrow(request))
+    }
+
+    async fn refund_escrow(&self, request: EscrowIdRequest) -> CallResult<()> {
+        self.update(None, move || refund_escrow(request))
+    }
+
+    async fn get_escrow(&self, request: EscrowIdRequest) -> CallResult<(GetEscrowResponse,)> {
+        self.query(move || get_escrow(request)).map(|it| (it,))
+    }
+
+    async fn get_escrows_of(
+        &self,
+        request: GetEscrowsOfRequest,
+    ) -> CallResult<(GetEscrowsOfResponse,)> {
+        self.query(move || get_escrows_of(request)).map(|it| (it,))
+    }
+
+    async fn get_events(&self, request: GetEventsRequest) -> CallResult<(GetEventsResponse,)> {
+        self.query(move || get_events(request)).map(|it| (it,))
+    }
+
+    async fn get_transactions(
+        &self,
+        request: GetTransactionsRequest,
+    ) -> CallResult<(GetTransactionsResponse,)> {
+        self.query(move || get_transactions(request))
+            .map(|it| (it,))
+    }
+
+    async fn set_event_archive(&self, request: SetEventArchiveRequest) -> CallResult<()> {
+        self.update(Some(admin_guard), move || set_event_archive(request))
+    }
+
+    async fn get_cycles_balance(&self) -> CallResult<(GetCyclesBalanceResponse,)> {
+        self.query(get_cycles_balance).map(|it| (it,))
+    }
+
+    async fn set_cycles_low_water_mark(
+        &self,
+        request: SetCyclesLowWaterMarkRequest,
+    ) -> CallResult<()> {
+        self.update(Some(admin_guard), move || {
+            set_cycles_low_water_mark(request)
+        })
+    }
+
+    async fn get_metrics(&self) -> CallResult<(GetMetricsResponse,)> {
+        self.query(get_metrics).map(|it| (it,))
+    }
+
+    async fn audit_state(&self) -> CallResult<(AuditStateResponse,)> {
+        self.query(audit_state).map(|it| (it,))
+    }
+
+    async fn get_state_hash(&self) -> CallResult<(GetStateHashResponse,)> {
+        self.query(get_state_hash).map(|it| (it,))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use ic_cdk::api::call::RejectionCode;
+    use ic_cdk::export::candid::Principal;
+    use union_utils::random_principal_test;
+
+    use currency_token_client::api::CurrencyTokenApi;
+    use currency_token_client::types::{
+        GetBalanceOfRequest, InitRequest, TokenInfo, TransferEntry, TransferRequest,
+    };
+
+    use crate::mock::MockCurrencyToken;
+
+    fn transfer_request(entries: &[(Principal, u64)]) -> TransferRequest {
+        TransferRequest {
+            entries: entries
+                .iter()
+                .map(|(to, qty)| TransferEntry {
+                    to: *to,
+                    qty: *qty,
+                    event_payload: None,
+                    recurrence: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn balance_of(token: &MockCurrencyToken, account_owner: Principal) -> u64 {
+        let (response,) =
+            block_on(token.get_balance_of(GetBalanceOfRequest { account_owner })).unwrap();
+
+        response.balance
+    }
+
+    #[test]
+    fn mock_token_works_fine() {
+        let controller = random_principal_test();
+        let user = random_principal_test();
+
+        let token = MockCurrencyToken::new(
+            controller,
+            InitRequest {
+                info: TokenInfo {
+                    name: String::from("test"),
+                    symbol: String::from("TST"),
+                    decimals: 8,
+                    ..Default::default()
+                },
+                default_controllers: None,
+                controllers_update_delay_nano: None,
+            },
+        );
+
+        block_on(token.mint(transfer_request(&[(user, 100)]))).unwrap();
+        assert_eq!(balance_of(&token, user), 100);
+
+        token.set_caller(user);
+
+        let (code, _) = block_on(token.mint(transfer_request(&[(user, 100)]))).unwrap_err();
+        assert!(
+            matches!(code, RejectionCode::CanisterReject),
+            "guards should reject"
+        );
+
+        let (code, _) =
+            block_on(token.transfer(transfer_request(&[(controller, 10), (controller, 1000)])))
+                .unwrap_err();
+        assert!(
+            matches!(code, RejectionCode::CanisterError),
+            "traps should error"
+        );
+        assert_eq!(
+            balance_of(&token, user),
+            100,
+            "trapped calls should be rolled back"
+        );
+
+        block_on(token.transfer(transfer_request(&[(controller, 40)]))).unwrap();
+        assert_eq!(balance_of(&token, user), 60);
+        assert_eq!(balance_of(&token, controller), 40);
+
+        assert_eq!(
+            token
+                .get_emitted_events()
+                .into_iter()
+                .filter(|(name, _)| name == "TokenMoveEvent")
+                .count(),
+            2
+        );
+    }
+}
@@ -1,13 +1,27 @@
+use async_trait::async_trait;
 use ic_cdk::api::call::CallResult;
 use ic_cdk::call;
 use ic_cdk::export::candid::Principal;
 
 use crate::types::{
-    BurnRequest, DequeueRecurrentTaskRequest, DequeueRecurrentTaskResponse, GetBalanceOfRequest,
-    GetBalanceOfResponse, GetControllersResponse, GetInfoResponse, GetRecurrentMintTasksResponse,
-    GetRecurrentTransferTasksRequest, GetRecurrentTransferTasksResponse, GetTotalSupplyResponse,
-    TransferRequest, UpdateControllersRequest, UpdateControllersResponse, UpdateInfoRequest,
-    UpdateInfoResponse,
+    ApproveProposalResponse, AuditStateResponse, BurnFromRequest, BurnRequest, ClawbackRequest,
+    ControllersHandoverRequest, ControllersUpdateDelay, ControllersUpdateIdRequest,
+    CreateEscrowRequest, CreateEscrowResponse, DequeueRecurrentTaskRequest,
+    DequeueRecurrentTaskResponse, EscrowIdRequest, GetApprovalThresholdsResponse,
+    GetBalanceOfRequest, GetBalanceOfResponse, GetBalancesOfRequest, GetBalancesOfResponse,
+    GetControllersHandoversResponse, GetControllersResponse, GetCyclesBalanceResponse,
+    GetEscrowResponse, GetEscrowsOfRequest, GetEscrowsOfResponse, GetEventsRequest,
+    GetEventsResponse, GetHoldersCountResponse, GetHoldersRequest, GetHoldersResponse,
+    GetInfoResponse, GetMetricsResponse, GetMintQuotaRequest, GetMintQuotaResponse,
+    GetProposalResponse, GetProposalsResponse, GetRecurrentMintTasksResponse,
+    GetRecurrentTransferTasksRequest, GetRecurrentTransferTasksResponse, GetRoleMembersRequest,
+    GetRoleMembersResponse, GetScheduledControllersUpdatesResponse, GetStateHashResponse,
+    GetTopHoldersRequest, GetTopHoldersResponse, GetTotalSupplyResponse, GetTransactionsRequest,
+    GetTransactionsResponse, HasRoleResponse, ProposalIdRequest, ProposeRequest, ProposeResponse,
+    RoleRequest, SetApprovalThresholdRequest, SetApprovalThresholdResponse,
+    SetCyclesLowWaterMarkRequest, SetEventArchiveRequest, SetMintQuotaRequest, SetRoleAdminRequest,
+    SetRoleAdminResponse, TransferRequest, UpdateControllersRequest, UpdateControllersResponse,
+    UpdateInfoRequest, UpdateInfoResponse,
 };
 
 /// Client struct for easier interaction from other rust canisters
@@ -15,76 +29,366 @@ pub struct CurrencyTokenClient {
     pub canister_id: Principal,
 }
 
+/// Currency token interface. Code written against it could be unit-tested natively with the
+/// in-memory mock from the `mock` feature of the `currency-token` crate.
+#[async_trait(?Send)]
+pub trait CurrencyTokenApi {
+    async fn mint(&self, request: TransferRequest) -> CallResult<()>;
+
+    async fn transfer(&self, request: TransferRequest) -> CallResult<()>;
+
+    async fn burn(&self, request: BurnRequest) -> CallResult<()>;
+
+    async fn burn_from(&self, request: BurnFromRequest) -> CallResult<()>;
+
+    async fn clawback(&self, request: ClawbackRequest) -> CallResult<()>;
+
+    async fn get_balance_of(
+        &self,
+        request: GetBalanceOfRequest,
+    ) -> CallResult<(GetBalanceOfResponse,)>;
+
+    async fn get_balances_of(
+        &self,
+        request: GetBalancesOfRequest,
+    ) -> CallResult<(GetBalancesOfResponse,)>;
+
+    async fn get_total_supply(&self) -> CallResult<(GetTotalSupplyResponse,)>;
+
+    async fn get_holders(&self, request: GetHoldersRequest) -> CallResult<(GetHoldersResponse,)>;
+
+    async fn get_holders_count(&self) -> CallResult<(GetHoldersCountResponse,)>;
+
+    async fn get_top_holders(
+        &self,
+        request: GetTopHoldersRequest,
+    ) -> CallResult<(GetTopHoldersResponse,)>;
+
+    async fn get_info(&self) -> CallResult<(GetInfoResponse,)>;
+
+    async fn update_info(&self, request: UpdateInfoRequest) -> CallResult<(UpdateInfoResponse,)>;
+
+    async fn get_controllers(&self) -> CallResult<(GetControllersResponse,)>;
+
+    async fn grant_role(&self, request: RoleRequest) -> CallResult<()>;
+
+    async fn revoke_role(&self, request: RoleRequest) -> CallResult<()>;
+
+    async fn set_role_admin(
+        &self,
+        request: SetRoleAdminRequest,
+    ) -> CallResult<(SetRoleAdminResponse,)>;
+
+    async fn has_role(&self, request: RoleRequest) -> CallResult<(HasRoleResponse,)>;
+
+    async fn get_role_members(
+        &self,
+        request: GetRoleMembersRequest,
+    ) -> CallResult<(GetRoleMembersResponse,)>;
+
+    async fn update_info_controller(
+        &self,
+        request: UpdateControllersRequest,
+    ) -> CallResult<(UpdateControllersResponse,)>;
+
+    async fn update_mint_controller(
+        &self,
+        request: UpdateControllersRequest,
+    ) -> CallResult<(UpdateControllersResponse,)>;
+
+    async fn update_burn_controller(
+        &self,
+        request: UpdateControllersRequest,
+    ) -> CallResult<(UpdateControllersResponse,)>;
+
+    async fn update_event_listeners_controller(
+        &self,
+        request: UpdateControllersRequest,
+    ) -> CallResult<(UpdateControllersResponse,)>;
+
+    async fn accept_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()>;
+
+    async fn cancel_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()>;
+
+    async fn get_controllers_handovers(&self) -> CallResult<(GetControllersHandoversResponse,)>;
+
+    async fn cancel_controllers_update(
+        &self,
+        request: ControllersUpdateIdRequest,
+    ) -> CallResult<()>;
+
+    async fn get_scheduled_controllers_updates(
+        &self,
+    ) -> CallResult<(GetScheduledControllersUpdatesResponse,)>;
+
+    async fn set_controllers_update_delay(&self, request: ControllersUpdateDelay)
+        -> CallResult<()>;
+
+    async fn get_controllers_update_delay(&self) -> CallResult<(ControllersUpdateDelay,)>;
+
+    async fn dequeue_recurrent_transfer_tasks(
+        &self,
+        request: DequeueRecurrentTaskRequest,
+    ) -> CallResult<(DequeueRecurrentTaskResponse,)>;
+
+    async fn get_recurrent_transfer_tasks(
+        &self,
+        request: GetRecurrentTransferTasksRequest,
+    ) -> CallResult<(GetRecurrentTransferTasksResponse,)>;
+
+    async fn dequeue_recurrent_mint_tasks(
+        &self,
+        request: DequeueRecurrentTaskRequest,
+    ) -> CallResult<(DequeueRecurrentTaskResponse,)>;
+
+    async fn get_recurrent_mint_tasks(&self) -> CallResult<(GetRecurrentMintTasksResponse,)>;
+
+    async fn set_mint_quota(&self, request: SetMintQuotaRequest) -> CallResult<()>;
+
+    async fn get_mint_quota(
+        &self,
+        request: GetMintQuotaRequest,
+    ) -> CallResult<(GetMintQuotaResponse,)>;
+
+    async fn set_approval_threshold(
+        &self,
+        request: SetApprovalThresholdRequest,
+    ) -> CallResult<(SetApprovalThresholdResponse,)>;
+
+    async fn get_approval_thresholds(&self) -> CallResult<(GetApprovalThresholdsResponse,)>;
+
+    async fn propose(&self, request: ProposeRequest) -> CallResult<(ProposeResponse,)>;
+
+    async fn approve_proposal(
+        &self,
+        request: ProposalIdRequest,
+    ) -> CallResult<(ApproveProposalResponse,)>;
+
+    async fn get_proposal(&self, request: ProposalIdRequest) -> CallResult<(GetProposalResponse,)>;
+
+    async fn get_pending_proposals(&self) -> CallResult<(GetProposalsResponse,)>;
+
+    async fn create_escrow(
+        &self,
+        request: CreateEscrowRequest,
+    ) -> CallResult<(CreateEscrowResponse,)>;
+
+    async fn release_escrow(&self, request: EscrowIdRequest) -> CallResult<()>;
+
+    async fn refund_escrow(&self, request: EscrowIdRequest) -> CallResult<()>;
+
+    async fn get_escrow(&self, request: EscrowIdRequest) -> CallResult<(GetEscrowResponse,)>;
+
+    async fn get_escrows_of(
+        &self,
+        request: GetEscrowsOfRequest,
+    ) -> CallResult<(GetEscrowsOfResponse,)>;
+
+    async fn get_events(&self, request: GetEventsRequest) -> CallResult<(GetEventsResponse,)>;
+
+    async fn get_transactions(
+        &self,
+        request: GetTransactionsRequest,
+    ) -> CallResult<(GetTransactionsResponse,)>;
+
+    async fn set_event_archive(&self, request: SetEventArchiveRequest) -> CallResult<()>;
+
+    async fn get_cycles_balance(&self) -> CallResult<(GetCyclesBalanceResponse,)>;
+
+    async fn set_cycles_low_water_mark(
+        &self,
+        request: SetCyclesLowWaterMarkRequest,
+    ) -> CallResult<()>;
+
+    async fn get_metrics(&self) -> CallResult<(GetMetricsResponse,)>;
+
+    async fn audit_state(&self) -> CallResult<(AuditStateResponse,)>;
+
+    async fn get_state_hash(&self) -> CallResult<(GetStateHashResponse,)>;
+}
+
 impl CurrencyTokenClient {
     #[inline(always)]
     pub fn new(canister_id: Principal) -> Self {
         CurrencyTokenClient { canister_id }
     }
+}
 
-    #[inline(always)]
-    pub async fn mint(&self, request: TransferRequest) -> CallResult<()> {
+#[async_trait(?Send)]
+impl CurrencyTokenApi for CurrencyTokenClient {
+    async fn mint(&self, request: TransferRequest) -> CallResult<()> {
         call(self.canister_id, "mint", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn transfer(&self, request: TransferRequest) -> CallResult<()> {
+    async fn transfer(&self, request: TransferRequest) -> CallResult<()> {
         call(self.canister_id, "transfer", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn burn(&self, request: BurnRequest) -> CallResult<()> {
+    async fn burn(&self, request: BurnRequest) -> CallResult<()> {
         call(self.canister_id, "burn", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn get_balance_of(
+    async fn burn_from(&self, request: BurnFromRequest) -> CallResult<()> {
+        call(self.canister_id, "burn_from", (request,)).await
+    }
+
+    async fn clawback(&self, request: ClawbackRequest) -> CallResult<()> {
+        call(self.canister_id, "clawback", (request,)).await
+    }
+
+    async fn get_balance_of(
         &self,
         request: GetBalanceOfRequest,
     ) -> CallResult<(GetBalanceOfResponse,)> {
         call(self.canister_id, "get_balance_of", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn get_total_supply(&self) -> CallResult<(GetTotalSupplyResponse,)> {
+    async fn get_balances_of(
+        &self,
+        request: GetBalancesOfRequest,
+    ) -> CallResult<(GetBalancesOfResponse,)> {
+        call(self.canister_id, "get_balances_of", (request,)).await
+    }
+
+    async fn get_total_supply(&self) -> CallResult<(GetTotalSupplyResponse,)> {
         call(self.canister_id, "get_total_supply", ()).await
     }
 
-    #[inline(always)]
-    pub async fn get_info(&self) -> CallResult<(GetInfoResponse,)> {
-        call(self.canister_id, "get_info", ()).await
+    async fn get_holders(&self, request: GetHoldersRequest) -> CallResult<(GetHoldersResponse,)> {
+        call(self.canister_id, "get_holders", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn update_info(
+    async fn get_holders_count(&self) -> CallResult<(GetHoldersCountResponse,)> {
+        call(self.canister_id, "get_holders_count", ()).await
+    }
+
+    async fn get_top_holders(
         &self,
-        request: UpdateInfoRequest,
-    ) -> CallResult<(UpdateInfoResponse,)> {
+        request: GetTopHoldersRequest,
+    ) -> CallResult<(GetTopHoldersResponse,)> {
+        call(self.canister_id, "get_top_holders", (request,)).await
+    }
+
+    async fn get_info(&self) -> CallResult<(GetInfoResponse,)> {
+        call(self.canister_id, "get_info", ()).await
+    }
+
+    async fn update_info(&self, request: UpdateInfoRequest) -> CallResult<(UpdateInfoResponse,)> {
         call(self.canister_id, "update_info", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn get_controllers(&self) -> CallResult<(GetControllersResponse,)> {
+    async fn get_controllers(&self) -> CallResult<(GetControllersResponse,)> {
         call(self.canister_id, "get_controllers", ()).await
     }
 
-    #[inline(always)]
-    pub async fn update_info_controller(
+    async fn grant_role(&self, request: RoleRequest) -> CallResult<()> {
+        call(self.canister_id, "grant_role", (request,)).await
+    }
+
+    async fn revoke_role(&self, request: RoleRequest) -> CallResult<()> {
+        call(self.canister_id, "revoke_role", (request,)).await
+    }
+
+    async fn set_role_admin(
+        &self,
+        request: SetRoleAdminRequest,
+    ) -> CallResult<(SetRoleAdminResponse,)> {
+        call(self.canister_id, "set_role_admin", (request,)).await
+    }
+
+    async fn has_role(&self, request: RoleRequest) -> CallResult<(HasRoleResponse,)> {
+        call(self.canister_id, "has_role", (request,)).await
+    }
+
+    async fn get_role_members(
+        &self,
+        request: GetRoleMembersRequest,
+    ) -> CallResult<(GetRoleMembersResponse,)> {
+        call(self.canister_id, "get_role_members", (request,)).await
+    }
+
+    async fn update_info_controller(
         &self,
         request: UpdateControllersRequest,
     ) -> CallResult<(UpdateControllersResponse,)> {
         call(self.canister_id, "update_info_controller", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn update_mint_controller(
+    async fn update_mint_controller(
         &self,
         request: UpdateControllersRequest,
     ) -> CallResult<(UpdateControllersResponse,)> {
         call(self.canister_id, "update_mint_controller", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn dequeue_recurrent_transfer_tasks(
+    async fn update_burn_controller(
+        &self,
+        request: UpdateControllersRequest,
+    ) -> CallResult<(UpdateControllersResponse,)> {
+        call(self.canister_id, "update_burn_controller", (request,)).await
+    }
+
+    async fn update_event_listeners_controller(
+        &self,
+        request: UpdateControllersRequest,
+    ) -> CallResult<(UpdateControllersResponse,)> {
+        call(
+            self.canister_id,
+            "update_event_listeners_controller",
+            (request,),
+        )
+        .await
+    }
+
+    async fn accept_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()> {
+        call(self.canister_id, "accept_controllers_handover", (request,)).await
+    }
+
+    async fn cancel_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()> {
+        call(self.canister_id, "cancel_controllers_handover", (request,)).await
+    }
+
+    async fn get_controllers_handovers(&self) -> CallResult<(GetControllersHandoversResponse,)> {
+        call(self.canister_id, "get_controllers_handovers", ()).await
+    }
+
+    async fn cancel_controllers_update(
+        &self,
+        request: ControllersUpdateIdRequest,
+    ) -> CallResult<()> {
+        call(self.canister_id, "cancel_controllers_update", (request,)).await
+    }
+
+    async fn get_scheduled_controllers_updates(
+        &self,
+    ) -> CallResult<(GetScheduledControllersUpdatesResponse,)> {
+        call(self.canister_id, "get_scheduled_controllers_updates", ()).await
+    }
+
+    async fn set_controllers_update_delay(
+        &self,
+        request: ControllersUpdateDelay,
+    ) -> CallResult<()> {
+        call(self.canister_id, "set_controllers_update_delay", (request,)).await
+    }
+
+    async fn get_controllers_update_delay(&self) -> CallResult<(ControllersUpdateDelay,)> {
+        call(self.canister_id, "get_controllers_update_delay", ()).await
+    }
+
+    async fn dequeue_recurrent_transfer_tasks(
         &self,
         request: DequeueRecurrentTaskRequest,
     ) -> CallResult<(DequeueRecurrentTaskResponse,)> {
@@ -96,24 +400,151 @@ impl CurrencyTokenClient {
         .await
     }
 
-    #[inline(always)]
-    pub async fn get_recurrent_transfer_tasks(
+    async fn get_recurrent_transfer_tasks(
         &self,
         request: GetRecurrentTransferTasksRequest,
     ) -> CallResult<(GetRecurrentTransferTasksResponse,)> {
         call(self.canister_id, "get_recurrent_transfer_tasks", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn dequeue_recurrent_mint_tasks(
+    async fn dequeue_recurrent_mint_tasks(
         &self,
         request: DequeueRecurrentTaskRequest,
     ) -> CallResult<(DequeueRecurrentTaskResponse,)> {
         call(self.canister_id, "dequeue_recurrent_mint_tasks", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn get_recurrent_mint_tasks(&self) -> CallResult<(GetRecurrentMintTasksResponse,)> {
+    async fn get_recurrent_mint_tasks(&self) -> CallResult<(GetRecurrentMintTasksResponse,)> {
         call(self.canister_id, "get_recurrent_mint_tasks", ()).await
     }
+
+    async fn set_mint_quota(&self, request: SetMintQuotaRequest) -> CallResult<()> {
+        call(self.canister_id, "set_mint_quota", (request,)).await
+    }
+
+    async fn get_mint_quota(
+        &self,
+        request: GetMintQuotaRequest,
+    ) -> CallResult<(GetMintQuotaResponse,)> {
+        call(self.canister_id, "get_mint_quota", (request,)).await
+    }
+
+    async fn set_approval_threshold(
+        &self,
+        request: SetApprovalThresholdRequest,
+    ) -> CallResult<(SetApprovalThresholdResponse,)> {
+        call(self.canister_id, "set_approval_threshold", (request,)).await
+    }
+
+    async fn get_approval_thresholds(&self) -> CallResult<(GetApprovalThresholdsResponse,)> {
+        call(self.canister_id, "get_approval_thresholds", ()).await
+    }
+
+    async fn propose(&self, request: ProposeRequest) -> CallResult<(ProposeResponse,)> {
+        call(self.canister_id, "propose", (request,)).await
+    }
+
+    async fn approve_proposal(
+        &self,
+        request: ProposalIdRequest,
+    ) -> CallResult<(ApproveProposalResponse,)> {
+        call(self.canister_id, "approve_proposal", (request,)).await
+    }
+
+    async fn get_proposal(&self, request: ProposalIdRequest) -> CallResult<(GetProposalResponse,)> {
+        call(self.canister_id, "get_proposal", (request,)).await
+    }
+
+    async fn get_pending_proposals(&self) -> CallResult<(GetProposalsResponse,)> {
+        call(self.canister_id, "get_pending_proposals", ()).await
+    }
+
+    async fn create_escrow(
+        &self,
+        request: CreateEscrowRequest,
+    ) -> CallResult<(CreateEscrowResponse,)> {
+        call(self.canister_id, "create_escrow", (request,)).await
+    }
+
+    async fn release_escrow(&self, request: EscrowIdRequest) -> CallResult<()> {
+        call(self.canister_id, "release_escrow", (request,)).await
+    }
+
+    async fn refund_escrow(&self, request: EscrowIdRequest) -> CallResult<()> {
+        call(self.canister_id, "refund_escrow", (request,)).await
+    }
+
+    async fn get_escrow(&self, request: EscrowIdRequest) -> CallResult<(GetEscrowResponse,)> {
+        call(self.canister_id, "get_escrow", (request,)).await
+    }
+
+    async fn get_escrows_of(
+        &self,
+        request: GetEscrowsOfRequest,
+    ) -> CallResult<(GetEscrowsOfResponse,)> {
+        call(self.canister_id, "get_escrows_of", (request,)).await
+    }
+
+    async fn get_events(&self, request: GetEventsRequest) -> CallResult<(GetEventsResponse,)> {
+        call(self.canister_id, "get_events", (request,)).await
+    }
+
+    async fn get_transactions(
+        &self,
+        request: GetTransactionsRequest,
+    ) -> CallResult<(GetTransactionsResponse,)> {
+        call(self.canister_id, "get_transactions", (request,)).await
+    }
+
+    async fn set_event_archive(&self, request: SetEventArchiveRequest) -> CallResult<()> {
+        call(self.canister_id, "set_event_archive", (request,)).await
+    }
+
+    async fn get_cycles_balance(&self) -> CallResult<(GetCyclesBalanceResponse,)> {
+        call(self.canister_id, "get_cycles_balance", ()).await
+    }
+
+    async fn set_cycles_low_water_mark(
+        &self,
+        request: SetCyclesLowWaterMarkRequest,
+    ) -> CallResult<()> {
+        call(self.canister_id, "set_cycles_low_water_mark", (request,)).await
+    }
+
+    async fn get_metrics(&self) -> CallResult<(GetMetricsResponse,)> {
+        call(self.canister_id, "get_metrics", ()).await
+    }
+
+    async fn audit_state(&self) -> CallResult<(AuditStateResponse,)> {
+        call(self.canister_id, "audit_state", ()).await
+    }
+
+    async fn get_state_hash(&self) -> CallResult<(GetStateHashResponse,)> {
+        call(self.canister_id, "get_state_hash", ()).await
+    }
+}
+
+/// Client struct for reading events and transactions moved to an archive canister
+pub struct CurrencyTokenArchiveClient {
+    pub canister_id: Principal,
+}
+
+impl CurrencyTokenArchiveClient {
+    #[inline(always)]
+    pub fn new(canister_id: Principal) -> Self {
+        CurrencyTokenArchiveClient { canister_id }
+    }
+
+    #[inline(always)]
+    pub async fn get_events(&self, request: GetEventsRequest) -> CallResult<(GetEventsResponse,)> {
+        call(self.canister_id, "get_events", (request,)).await
+    }
+
+    #[inline(always)]
+    pub async fn get_transactions(
+        &self,
+        request: GetTransactionsRequest,
+    ) -> CallResult<(GetTransactionsResponse,)> {
+        call(self.canister_id, "get_transactions", (request,)).await
+    }
 }
@@ -1,27 +1,51 @@
 use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+use ic_cron::types::TaskId;
 use ic_event_hub_macros::Event;
 
-use crate::types::{Controllers, Payload, TokenInfo};
+use crate::types::{
+    Controllers, ControllersChange, ControllersUpdateId, EscrowId, EventSeq, ForcedMoveReason,
+    Payload, ProposalAction, ProposalId, Role, TokenInfo, ADMIN_ROLE, BURN_ROLE,
+    EVENT_LISTENERS_ROLE, INFO_ROLE, MINT_ROLE,
+};
 
 #[derive(Event, CandidType, Deserialize)]
 pub struct TokenMoveEvent {
+    pub seq: EventSeq,
     #[topic]
     pub from: Option<Principal>,
     #[topic]
     pub to: Option<Principal>,
     pub qty: u64,
     pub event_payload: Payload,
+    pub forced: Option<ForcedMoveReason>,
 }
 
 #[derive(Debug, CandidType, Deserialize)]
 pub enum ControllerType {
+    Admin,
     Mint,
     Info,
+    Burn,
     EventListeners,
+    Custom(Role),
+}
+
+impl From<&str> for ControllerType {
+    fn from(role: &str) -> Self {
+        match role {
+            ADMIN_ROLE => ControllerType::Admin,
+            MINT_ROLE => ControllerType::Mint,
+            INFO_ROLE => ControllerType::Info,
+            BURN_ROLE => ControllerType::Burn,
+            EVENT_LISTENERS_ROLE => ControllerType::EventListeners,
+            _ => ControllerType::Custom(String::from(role)),
+        }
+    }
 }
 
 #[derive(Event, CandidType, Deserialize)]
 pub struct ControllersUpdateEvent {
+    pub seq: EventSeq,
     #[topic]
     pub kind: ControllerType,
     pub new_controllers: Controllers,
@@ -29,5 +53,225 @@ pub struct ControllersUpdateEvent {
 
 #[derive(Event, CandidType, Deserialize)]
 pub struct InfoUpdateEvent {
+    pub seq: EventSeq,
     pub new_info: TokenInfo,
 }
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct EscrowCreatedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub escrow_id: EscrowId,
+    #[topic]
+    pub payer: Principal,
+    #[topic]
+    pub payee: Principal,
+    #[topic]
+    pub arbiter: Principal,
+    pub qty: u64,
+    pub deadline: u64,
+    pub event_payload: Payload,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct EscrowReleasedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub escrow_id: EscrowId,
+    #[topic]
+    pub payee: Principal,
+    pub released_by: Principal,
+    pub qty: u64,
+    pub event_payload: Payload,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct EscrowRefundedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub escrow_id: EscrowId,
+    #[topic]
+    pub payer: Principal,
+    pub qty: u64,
+    pub event_payload: Payload,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ProposalCreatedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub proposal_id: ProposalId,
+    #[topic]
+    pub proposer: Principal,
+    pub role: Role,
+    pub action: ProposalAction,
+    pub expires_at: u64,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ProposalApprovedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub proposal_id: ProposalId,
+    #[topic]
+    pub approver: Principal,
+    pub approvals: u32,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ProposalExecutedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub proposal_id: ProposalId,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ControllersUpdateScheduledEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub update_id: ControllersUpdateId,
+    #[topic]
+    pub kind: ControllerType,
+    pub change: ControllersChange,
+    pub scheduled_by: Principal,
+    pub execute_at: u64,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ControllersUpdateCancelledEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub update_id: ControllersUpdateId,
+    #[topic]
+    pub kind: ControllerType,
+    pub cancelled_by: Principal,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ControllersHandoverProposedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub kind: ControllerType,
+    pub new_controllers: Controllers,
+    pub proposed_by: Principal,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ControllersHandoverAcceptedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub kind: ControllerType,
+    #[topic]
+    pub accepted_by: Principal,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ControllersHandoverCancelledEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub kind: ControllerType,
+    pub cancelled_by: Principal,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct RecurrentTaskCreatedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub task_id: TaskId,
+    #[topic]
+    pub from: Option<Principal>,
+    #[topic]
+    pub to: Principal,
+    pub qty: u64,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct RecurrentTaskCancelledEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub task_id: TaskId,
+    #[topic]
+    pub from: Option<Principal>,
+    #[topic]
+    pub to: Principal,
+    pub cancelled_by: Principal,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct RecurrentTaskCompletedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub task_id: TaskId,
+    #[topic]
+    pub from: Option<Principal>,
+    #[topic]
+    pub to: Principal,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct LowCyclesEvent {
+    pub seq: EventSeq,
+    pub balance: u64,
+    pub low_water_mark: u64,
+}
+
+/// Events which are stored in the token's event log before being delivered to listeners
+pub trait LoggedEvent: CandidType {
+    fn get_name(&self) -> &'static str;
+
+    /// Returns principals this event refers to, so the log could be filtered by them
+    fn get_principals(&self) -> Vec<Principal>;
+}
+
+trait AsPrincipals {
+    fn as_principals(&self) -> Vec<Principal>;
+}
+
+impl AsPrincipals for Principal {
+    fn as_principals(&self) -> Vec<Principal> {
+        vec![*self]
+    }
+}
+
+impl AsPrincipals for Option<Principal> {
+    fn as_principals(&self) -> Vec<Principal> {
+        self.iter().cloned().collect()
+    }
+}
+
+macro_rules! logged_event {
+    ($event:ident $(, $field:ident)*) => {
+        impl LoggedEvent for $event {
+            fn get_name(&self) -> &'static str {
+                stringify!($event)
+            }
+
+            #[allow(unused_mut)]
+            fn get_principals(&self) -> Vec<Principal> {
+                let mut principals = Vec::new();
+                $(principals.extend(self.$field.as_principals());)*
+
+                principals
+            }
+        }
+    };
+}
+
+logged_event!(TokenMoveEvent, from, to);
+logged_event!(ControllersUpdateEvent);
+logged_event!(InfoUpdateEvent);
+logged_event!(EscrowCreatedEvent, payer, payee, arbiter);
+logged_event!(EscrowReleasedEvent, payee, released_by);
+logged_event!(EscrowRefundedEvent, payer);
+logged_event!(ProposalCreatedEvent, proposer);
+logged_event!(ProposalApprovedEvent, approver);
+logged_event!(ProposalExecutedEvent);
+logged_event!(ControllersUpdateScheduledEvent, scheduled_by);
+logged_event!(ControllersUpdateCancelledEvent, cancelled_by);
+logged_event!(ControllersHandoverProposedEvent, proposed_by);
+logged_event!(ControllersHandoverAcceptedEvent, accepted_by);
+logged_event!(ControllersHandoverCancelledEvent, cancelled_by);
+logged_event!(RecurrentTaskCreatedEvent, from, to);
+logged_event!(RecurrentTaskCancelledEvent, from, to, cancelled_by);
+logged_event!(RecurrentTaskCompletedEvent, from, to);
+logged_event!(LowCyclesEvent);
@@ -1,10 +1,17 @@
 //! Currency token on-chain client and API types
 
+/// Off-chain client calling the token through ic-agent
+#[cfg(feature = "agent")]
+pub mod agent;
+
 /// Client struct to use
 pub mod api;
 
 /// Events emitted by the currency token
 pub mod events;
 
+/// Typed handling of the events delivered by the currency token
+pub mod listener;
+
 /// API related types used
 pub mod types;
@@ -0,0 +1,72 @@
+use ic_cdk::export::candid::Principal;
+pub use ic_event_hub::types::Event;
+use ic_event_hub::types::IEvent;
+
+use crate::events::{ControllersUpdateEvent, InfoUpdateEvent, TokenMoveEvent};
+
+/// Typed handlers of the currency token's events. Every handler does nothing by default, so
+/// only the interesting ones have to be implemented.
+pub trait CurrencyTokenEventHandler {
+    fn on_token_move(&self, _event: TokenMoveEvent) {}
+
+    fn on_controllers_update(&self, _event: ControllersUpdateEvent) {}
+
+    fn on_info_update(&self, _event: InfoUpdateEvent) {}
+
+    /// Receives the rest of the token's events (escrows, proposals, recurrent tasks, etc.)
+    fn on_other_event(&self, _event: Event) {}
+}
+
+/// Events could only be trusted if the token itself has sent them
+pub fn verify_event_source(caller: Principal, token: Principal) -> Result<(), String> {
+    if caller == token {
+        Ok(())
+    } else {
+        Err(format!(
+            "The caller {} is not the currency token {}",
+            caller, token
+        ))
+    }
+}
+
+/// Decodes the events and hands them over to the handler, in the order they were emitted
+pub fn dispatch_events<H: CurrencyTokenEventHandler>(handler: &H, events: Vec<Event>) {
+    for event in events {
+        match event.get_name().as_str() {
+            "TokenMoveEvent" => handler.on_token_move(TokenMoveEvent::from_event(event)),
+            "ControllersUpdateEvent" => {
+                handler.on_controllers_update(ControllersUpdateEvent::from_event(event))
+            }
+            "InfoUpdateEvent" => handler.on_info_update(InfoUpdateEvent::from_event(event)),
+            _ => handler.on_other_event(event),
+        }
+    }
+}
+
+/// Implements the update endpoint the token delivers events to, which traps unless it is called
+/// by the token. The callback name defaults to `events_callback`, handler is a value of a type
+/// implementing `CurrencyTokenEventHandler`.
+///
+/// ```ignore
+/// implement_currency_token_listener!(token = get_state().token_id, handler = MyHandler);
+/// ```
+#[macro_export]
+macro_rules! implement_currency_token_listener {
+    (token = $token:expr, handler = $handler:expr) => {
+        $crate::implement_currency_token_listener!(
+            callback = "events_callback",
+            token = $token,
+            handler = $handler
+        );
+    };
+    (callback = $callback:tt, token = $token:expr, handler = $handler:expr) => {
+        #[ic_cdk_macros::update(name = $callback)]
+        fn _currency_token_events_callback(events: Vec<$crate::listener::Event>) {
+            if let Err(e) = $crate::listener::verify_event_source(ic_cdk::caller(), $token) {
+                ic_cdk::trap(e.as_str());
+            }
+
+            $crate::listener::dispatch_events(&$handler, events);
+        }
+    };
+}
@@ -1,31 +1,54 @@
-use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+use ic_cdk::export::candid::{decode_one, CandidType, Deserialize, Principal};
 use ic_cron::types::{SchedulingInterval, TaskId};
 
-pub type Controllers = Vec<Principal>;
+pub use token_common::types::{
+    Controllers, ControllersChange, ControllersHandover, ControllersUpdateId, EventRecord,
+    EventSeq, EventTopicFilter, HeaderField, HttpRequest, HttpResponse, ProposalId, ProposalStatus,
+    Role, RoleInfo, RoleRequest, ScheduledControllersUpdate, SetRoleAdminRequest,
+    UpdateRoleControllersRequest, ADMIN_ROLE,
+};
+
+use crate::events::TokenMoveEvent;
+
 pub type Payload = Option<Vec<u8>>;
+pub type EscrowId = u64;
 
-#[derive(Clone, CandidType, Deserialize)]
-pub struct ControllerList {
-    pub mint_controllers: Controllers,
-    pub info_controllers: Controllers,
-}
-
-impl ControllerList {
-    pub fn single(controller_opt: Option<Principal>) -> ControllerList {
-        let controllers = if let Some(controller) = controller_opt {
-            vec![controller]
-        } else {
-            Vec::new()
-        };
-
-        ControllerList {
-            mint_controllers: controllers.clone(),
-            info_controllers: controllers,
-        }
-    }
+pub const MINT_ROLE: &str = "mint";
+pub const INFO_ROLE: &str = "info";
+pub const BURN_ROLE: &str = "burn";
+pub const EVENT_LISTENERS_ROLE: &str = "event_listeners";
+
+/// Roles every currency token is initialized with, each of them is administered by itself
+pub const DEFAULT_ROLES: [&str; 5] = [
+    ADMIN_ROLE,
+    MINT_ROLE,
+    INFO_ROLE,
+    BURN_ROLE,
+    EVENT_LISTENERS_ROLE,
+];
+
+#[derive(CandidType, Deserialize)]
+pub struct HasRoleResponse {
+    pub has_role: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetRoleMembersRequest {
+    pub role: Role,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetRoleMembersResponse {
+    pub members: Controllers,
 }
 
 #[derive(CandidType, Deserialize)]
+pub struct SetRoleAdminResponse {
+    pub old_admin_role: Role,
+    pub scheduled_update_id: Option<ControllersUpdateId>,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
 pub struct TransferEntry {
     pub to: Principal,
     pub qty: u64,
@@ -49,16 +72,38 @@ pub struct GetRecurrentTransferTasksRequest {
 }
 
 #[derive(Clone, CandidType, Deserialize)]
+pub enum TokenLogo {
+    DataUrl(String),
+    Blob { content_type: String, data: Vec<u8> },
+}
+
+/// Value of a custom metadata entry, mirrors ICRC-1 metadata values
+#[derive(Clone, CandidType, Deserialize)]
+pub enum MetadataValue {
+    Nat(u64),
+    Int(i64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+#[derive(Clone, Default, CandidType, Deserialize)]
 pub struct TokenInfo {
     pub name: String,
     pub symbol: String,
     pub decimals: u8,
+    pub logo: Option<TokenLogo>,
+    pub description: Option<String>,
+    pub website: Option<String>,
+    pub metadata: Vec<(String, MetadataValue)>,
 }
 
 #[derive(CandidType, Deserialize)]
 pub struct InitRequest {
     pub info: TokenInfo,
     pub default_controllers: Option<Controllers>,
+    /// How long scheduled controllers changes wait before they take effect. Defaults to 0,
+    /// which disables the timelock - changes are then applied immediately.
+    pub controllers_update_delay_nano: Option<u64>,
 }
 
 #[derive(CandidType, Deserialize)]
@@ -71,6 +116,16 @@ pub struct GetBalanceOfResponse {
     pub balance: u64,
 }
 
+#[derive(CandidType, Deserialize)]
+pub struct GetBalancesOfRequest {
+    pub account_owners: Vec<Principal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetBalancesOfResponse {
+    pub balances: Vec<HolderEntry>,
+}
+
 #[derive(CandidType, Deserialize)]
 pub struct GetTotalSupplyResponse {
     pub total_supply: u64,
@@ -81,7 +136,7 @@ pub struct GetInfoResponse {
     pub info: TokenInfo,
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(Clone, CandidType, Deserialize)]
 pub struct UpdateInfoRequest {
     pub new_info: TokenInfo,
 }
@@ -93,20 +148,22 @@ pub struct UpdateInfoResponse {
 
 #[derive(CandidType, Deserialize)]
 pub struct GetControllersResponse {
-    pub controllers: ControllerList,
+    pub controllers: Vec<RoleInfo>,
 }
 
 #[derive(CandidType, Deserialize)]
 pub struct UpdateControllersRequest {
     pub new_controllers: Controllers,
+    pub renounce: Option<bool>,
 }
 
 #[derive(CandidType, Deserialize)]
 pub struct UpdateControllersResponse {
     pub old_controllers: Controllers,
+    pub scheduled_update_id: Option<ControllersUpdateId>,
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(Clone, CandidType, Deserialize)]
 pub struct TransferRequest {
     pub entries: Vec<TransferEntry>,
 }
@@ -132,6 +189,7 @@ pub struct RecurrentTransferTaskExt {
 #[derive(CandidType, Deserialize)]
 pub struct RecurrentMintTaskExt {
     pub task_id: TaskId,
+    pub minter: Principal,
     pub to: Principal,
     pub qty: u64,
     pub event_payload: Payload,
@@ -149,3 +207,377 @@ pub struct GetRecurrentTransferTasksResponse {
 pub struct GetRecurrentMintTasksResponse {
     pub tasks: Vec<RecurrentMintTaskExt>,
 }
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct MintRateLimit {
+    pub qty: u64,
+    pub period_nano: u64,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct MintQuota {
+    pub total_quota: Option<u64>,
+    pub rate_limit: Option<MintRateLimit>,
+    pub minted_total: u64,
+    pub minted_in_period: u64,
+    pub period_start: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SetMintQuotaRequest {
+    pub minter: Principal,
+    pub total_quota: Option<u64>,
+    pub rate_limit: Option<MintRateLimit>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetMintQuotaRequest {
+    pub minter: Principal,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetMintQuotaResponse {
+    pub quota: Option<MintQuota>,
+    pub remaining_total: Option<u64>,
+    pub remaining_in_period: Option<u64>,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct Escrow {
+    pub id: EscrowId,
+    pub payer: Principal,
+    pub payee: Principal,
+    pub arbiter: Principal,
+    pub qty: u64,
+    pub deadline: u64,
+    pub event_payload: Payload,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct CreateEscrowRequest {
+    pub payee: Principal,
+    pub arbiter: Principal,
+    pub qty: u64,
+    pub deadline: u64,
+    pub event_payload: Payload,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct CreateEscrowResponse {
+    pub escrow_id: EscrowId,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct EscrowIdRequest {
+    pub escrow_id: EscrowId,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetEscrowResponse {
+    pub escrow: Option<Escrow>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetEscrowsOfRequest {
+    pub participant: Principal,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetEscrowsOfResponse {
+    pub escrows: Vec<Escrow>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct HolderEntry {
+    pub account_owner: Principal,
+    pub balance: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetHoldersRequest {
+    pub cursor: Option<Principal>,
+    pub limit: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetHoldersResponse {
+    pub holders: Vec<HolderEntry>,
+    pub next_cursor: Option<Principal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetHoldersCountResponse {
+    pub holders_count: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetTopHoldersRequest {
+    pub n: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetTopHoldersResponse {
+    pub holders: Vec<HolderEntry>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct BurnFromRequest {
+    pub account: Principal,
+    pub qty: u64,
+    pub reason: String,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ClawbackRequest {
+    pub from: Principal,
+    pub to: Principal,
+    pub qty: u64,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub enum ForcedMoveKind {
+    BurnFrom,
+    Clawback,
+}
+
+/// Set as `forced` of every `TokenMoveEvent` emitted by `burn_from` or `clawback`, so listeners
+/// could tell forced moves apart from regular ones
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ForcedMoveReason {
+    pub kind: ForcedMoveKind,
+    pub executed_by: Principal,
+    pub reason: String,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct SetApprovalThresholdRequest {
+    pub role: Role,
+    pub threshold: u32,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SetApprovalThresholdResponse {
+    pub old_threshold: u32,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ApprovalThreshold {
+    pub role: Role,
+    pub threshold: u32,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetApprovalThresholdsResponse {
+    pub thresholds: Vec<ApprovalThreshold>,
+}
+
+/// Privileged operation which can be executed only after enough members of the
+/// corresponding role approve it
+#[derive(Clone, CandidType, Deserialize)]
+pub enum ProposalAction {
+    Mint(TransferRequest),
+    UpdateInfo(UpdateInfoRequest),
+    UpdateControllers(UpdateRoleControllersRequest),
+    GrantRole(RoleRequest),
+    RevokeRole(RoleRequest),
+    SetApprovalThreshold(SetApprovalThresholdRequest),
+}
+
+pub type Proposal = token_common::types::Proposal<ProposalAction>;
+
+#[derive(CandidType, Deserialize)]
+pub struct ProposeRequest {
+    pub action: ProposalAction,
+    pub ttl_nano: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ProposeResponse {
+    pub proposal_id: ProposalId,
+    pub executed: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ProposalIdRequest {
+    pub proposal_id: ProposalId,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ApproveProposalResponse {
+    pub executed: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetProposalResponse {
+    pub proposal: Option<Proposal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetProposalsResponse {
+    pub proposals: Vec<Proposal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ControllersUpdateIdRequest {
+    pub update_id: ControllersUpdateId,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetScheduledControllersUpdatesResponse {
+    pub updates: Vec<ScheduledControllersUpdate>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ControllersUpdateDelay {
+    pub delay_nano: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ControllersHandoverRequest {
+    pub role: Role,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetControllersHandoversResponse {
+    pub handovers: Vec<ControllersHandover>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetEventsRequest {
+    pub from_seq: EventSeq,
+    pub limit: u64,
+    pub topic_filter: Option<EventTopicFilter>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetEventsResponse {
+    pub events: Vec<EventRecord>,
+    pub oldest_seq: EventSeq,
+    pub next_seq: EventSeq,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SetEventArchiveRequest {
+    pub archive: Option<Principal>,
+    pub local_capacity: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct AppendEventsRequest {
+    pub events: Vec<EventRecord>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ArchiveInitRequest {
+    pub token: Principal,
+}
+
+/// A token move restored from its `TokenMoveEvent`, identified by the event's sequence number
+#[derive(Clone, CandidType, Deserialize)]
+pub struct Transaction {
+    pub seq: EventSeq,
+    pub timestamp: u64,
+    pub from: Option<Principal>,
+    pub to: Option<Principal>,
+    pub qty: u64,
+    pub event_payload: Payload,
+    pub forced: Option<ForcedMoveReason>,
+}
+
+impl Transaction {
+    /// Returns `None` for records of any other events
+    pub fn from_record(record: &EventRecord) -> Option<Transaction> {
+        if record.name != "TokenMoveEvent" {
+            return None;
+        }
+
+        let event = decode_one::<TokenMoveEvent>(&record.payload).ok()?;
+
+        Some(Transaction {
+            seq: record.seq,
+            timestamp: record.timestamp,
+            from: event.from,
+            to: event.to,
+            qty: event.qty,
+            event_payload: event.event_payload,
+            forced: event.forced,
+        })
+    }
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetTransactionsRequest {
+    pub start: EventSeq,
+    pub length: u64,
+}
+
+/// Canister method to call with the same `GetTransactionsRequest` arguments
+#[derive(Clone, CandidType, Deserialize)]
+pub struct ArchiveCallback {
+    pub canister_id: Principal,
+    pub method: String,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ArchivedTransactionsRange {
+    pub start: EventSeq,
+    pub length: u64,
+    pub callback: ArchiveCallback,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetTransactionsResponse {
+    pub transactions: Vec<Transaction>,
+    pub first_seq: EventSeq,
+    pub archived_ranges: Vec<ArchivedTransactionsRange>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct WalletReceiveResponse {
+    pub accepted: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetCyclesBalanceResponse {
+    pub balance: u64,
+    pub low_water_mark: Option<u64>,
+    pub suspended: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SetCyclesLowWaterMarkRequest {
+    pub low_water_mark: Option<u64>,
+    pub suspend_on_low_cycles: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetMetricsResponse {
+    pub heap_memory_bytes: u64,
+    pub stable_memory_bytes: u64,
+    pub cycles_balance: u64,
+    pub accounts_count: u64,
+    pub total_supply: u64,
+    pub recurrent_transfer_tasks_count: u64,
+    pub recurrent_mint_tasks_count: u64,
+    pub event_listeners_count: u64,
+    pub call_counters: Vec<(String, u64)>,
+    pub failed_recurrent_executions: u64,
+    pub last_heartbeat_at: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct AuditStateResponse {
+    pub total_supply: u64,
+    pub balances_sum: u64,
+    pub escrowed_sum: u64,
+    pub supply_consistent: bool,
+    pub dangling_task_ids: Vec<TaskId>,
+    pub orphaned_task_ids: Vec<TaskId>,
+    pub consistent: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetStateHashResponse {
+    pub hash: Vec<u8>,
+}
@@ -10,7 +10,7 @@ export async function deployMembershipToken(controller: Principal) {
 }
 
 export async function deployCurrencyToken(controller: Principal) {
-    const command = `dfx deploy currency-token --argument '(record { info = record { name = "Test Currency"; symbol = "TST"; decimals = 2 : nat8; }; default_controllers = opt vec { principal "${controller}" }; })'`;
+    const command = `dfx deploy currency-token --argument '(record { info = record { name = "Test Currency"; symbol = "TST"; decimals = 2 : nat8; metadata = vec {}; }; default_controllers = opt vec { principal "${controller}" }; })'`;
 
     console.log('Deploying currency token...');
     console.log(command)
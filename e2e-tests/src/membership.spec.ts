@@ -73,7 +73,14 @@ describe('membership token', () => {
             "Should be impossible to issue membership not being the controller"
         );
 
-        await tokenController.membershipTokenClient.update_issue_controller({new_controllers: [tokenHolder2Id.getPrincipal()]});
+        await tokenController.membershipTokenClient.update_issue_controller({new_controllers: [tokenHolder2Id.getPrincipal()], renounce: []});
+
+        await expectThrowsAsync(
+            tokenHolder2.membershipTokenClient.issue_memberships({principals: [tokenHolder2Id.getPrincipal()]}),
+            "Should not be able to issue membership before accepting the handover"
+        );
+
+        await tokenHolder2.membershipTokenClient.accept_controllers_handover({role: "issue"});
 
         await expectThrowsAsync(
             tokenController.membershipTokenClient.issue_memberships({principals: [tokenHolder2Id.getPrincipal()]}),
@@ -81,7 +88,7 @@ describe('membership token', () => {
         );
 
         await tokenHolder2.membershipTokenClient.issue_memberships({principals: [tokenHolder2Id.getPrincipal()]});
-        await tokenHolder2.membershipTokenClient.update_issue_controller({new_controllers: []});
+        await tokenHolder2.membershipTokenClient.update_issue_controller({new_controllers: [], renounce: [true]});
 
         await expectThrowsAsync(
             tokenController.membershipTokenClient.issue_memberships({principals: [tokenControllerId.getPrincipal()]}),
@@ -1,25 +1,66 @@
-use ic_cdk::export::candid::export_service;
-use ic_cdk::{caller, trap};
-use ic_cdk_macros::{init, query, update};
+use std::convert::TryInto;
+
+use ic_cdk::export::candid::{encode_one, export_service, Principal};
+use ic_cdk_macros::{heartbeat, init, query, update};
+use ic_cron::implement_cron;
+use ic_cron::types::{Iterations, ScheduledTask, SchedulingInterval, TaskId};
+use ic_event_hub::types::IEvent;
 use ic_event_hub_macros::{
     implement_add_event_listeners, implement_event_emitter, implement_get_event_listeners,
     implement_remove_event_listeners,
 };
-use union_utils::log;
 
 use membership_token_client::events::{
-    ControllerType, ControllersUpdateEvent, MembershipStatus, MembershipStatusUpdateEvent,
+    ControllerType, ControllersHandoverAcceptedEvent, ControllersHandoverCancelledEvent,
+    ControllersHandoverProposedEvent, ControllersUpdateCancelledEvent, ControllersUpdateEvent,
+    ControllersUpdateScheduledEvent, LoggedEvent, LowCyclesEvent, MembershipStatus,
+    MembershipStatusUpdateEvent, ProposalApprovedEvent, ProposalCreatedEvent,
+    ProposalExecutedEvent,
 };
 use membership_token_client::types::{
-    ControllerList, GetControllersResponse, GetTotalMembersResponse, InitRequest, IsMemberRequest,
-    IsMemberResponse, IssueRevokeMembershipsRequest, UpdateControllerRequest,
-    UpdateControllerResponse,
+    ApprovalThreshold, ApproveProposalResponse, AreMembersRequest, AreMembersResponse,
+    ControllersChange, ControllersHandoverRequest, ControllersUpdateDelay, ControllersUpdateId,
+    ControllersUpdateIdRequest, EventSeq, GetApprovalThresholdsResponse,
+    GetControllersHandoversResponse, GetControllersResponse, GetCyclesBalanceResponse,
+    GetEventsRequest, GetEventsResponse, GetMetricsResponse, GetProposalResponse,
+    GetProposalsResponse, GetRoleMembersRequest, GetRoleMembersResponse,
+    GetScheduledControllersUpdatesResponse, GetTotalMembersResponse, HasRoleResponse, HttpRequest,
+    HttpResponse, InitRequest, IsMemberRequest, IsMemberResponse, IssueRevokeMembershipsRequest,
+    MemberStatus, MemberStatusEntry, ProposalAction, ProposalIdRequest, ProposeRequest,
+    ProposeResponse, Role, RoleRequest, SetApprovalThresholdRequest, SetApprovalThresholdResponse,
+    SetCyclesLowWaterMarkRequest, SetEventArchiveRequest, SetRoleAdminRequest,
+    SetRoleAdminResponse, UpdateControllerRequest, UpdateControllerResponse,
+    UpdateRoleControllersRequest, WalletReceiveResponse, ADMIN_ROLE, DEFAULT_ROLES,
+    EVENT_LISTENERS_ROLE, ISSUE_ROLE, REVOKE_ROLE,
 };
-
-use crate::common::guards::{event_listeners_guard, issue_guard, revoke_guard};
-use crate::common::membership_token::MembershipToken;
-
+use token_common::governance::{Governance, GovernanceEvent};
+use token_common::handover::HandoverRegistry;
+use token_common::http::{
+    error_response, get_num_param, get_principal_param, json_response, parse_principal, parse_url,
+    text_response, Json, DEFAULT_HTTP_PAGE_SIZE,
+};
+use token_common::metrics::{heap_memory_bytes, stable_memory_bytes, PrometheusWriter};
+use token_common::proposals::ProposalRegistry;
+use token_common::roles::RoleRegistry;
+use token_common::timelock::ControllersTimelock;
+
+use crate::common::env::{get_env, Environment, TaskPayload};
+use crate::common::guards::{admin_guard, event_listeners_guard, issue_guard, revoke_guard};
+use crate::common::membership_token::{MembershipToken, MAX_BATCH_QUERY_SIZE};
+use crate::common::types::{ControllersUpdateTask, CronTaskKind};
+
+#[cfg(test)]
+mod actor_tests;
 mod common;
+#[cfg(feature = "mock")]
+pub mod mock;
+
+// the mock environment fakes the caller, the time and the cycles, so a wasm built with it
+// would let anyone act as anyone
+#[cfg(all(feature = "mock", target_arch = "wasm32"))]
+compile_error!(
+    "the `mock` feature is for native tests only and must never be enabled for wasm builds"
+);
 
 // -------------------- MAIN LOGIC ------------------------
 
@@ -27,17 +68,18 @@ mod common;
 fn init(request: InitRequest) {
     log("membership_token.init()");
 
-    let controllers = if let Some(default_controllers) = request.default_controllers {
-        ControllerList {
-            issue_controllers: default_controllers.clone(),
-            revoke_controllers: default_controllers.clone(),
-            event_listeners_controllers: default_controllers,
-        }
-    } else {
-        ControllerList::single(Some(caller()))
-    };
+    let default_controllers = request
+        .default_controllers
+        .unwrap_or_else(|| vec![get_env().caller()]);
+    let roles = RoleRegistry::new(&DEFAULT_ROLES, &default_controllers);
+
+    let delay_nano = request.controllers_update_delay_nano.unwrap_or_default();
+    if let Err(e) = ControllersTimelock::check_delay(delay_nano) {
+        get_env().trap(format!("Invalid controllers update delay - {}", e).as_str());
+    }
 
-    let token = MembershipToken::new(controllers);
+    let mut token = MembershipToken::new(roles);
+    token.timelock.delay_nano = delay_nano;
 
     unsafe {
         STATE = Some(token);
@@ -47,18 +89,24 @@ fn init(request: InitRequest) {
 #[update(guard = "issue_guard")]
 fn issue_memberships(request: IssueRevokeMembershipsRequest) {
     log("membership_token.issue_memberships()");
+    count_call("issue_memberships");
 
+    approval_not_required(ISSUE_ROLE);
+    _issue_memberships(request);
+}
+
+fn _issue_memberships(request: IssueRevokeMembershipsRequest) {
     let token = get_token();
 
     for to in request.principals.into_iter() {
         match token.issue_membership(to) {
-            Ok(_) => emit(MembershipStatusUpdateEvent {
+            Ok(_) => emit_logged(|seq| MembershipStatusUpdateEvent {
+                seq,
                 member: to,
                 new_status: MembershipStatus::Issued,
             }),
-            Err(e) => {
-                trap(format!("Failed to issue membership on principal {} - {}", to, e).as_str())
-            }
+            Err(e) => get_env()
+                .trap(format!("Failed to issue membership on principal {} - {}", to, e).as_str()),
         }
     }
 }
@@ -66,18 +114,25 @@ fn issue_memberships(request: IssueRevokeMembershipsRequest) {
 #[update(guard = "revoke_guard")]
 fn revoke_memberships(request: IssueRevokeMembershipsRequest) {
     log("membership_token.revoke_memberships()");
+    count_call("revoke_memberships");
+
+    approval_not_required(REVOKE_ROLE);
+    _revoke_memberships(request);
+}
 
+fn _revoke_memberships(request: IssueRevokeMembershipsRequest) {
     let token = get_token();
 
     for from in request.principals.into_iter() {
         match token.revoke_membership(from) {
-            Ok(_) => emit(MembershipStatusUpdateEvent {
+            Ok(_) => emit_logged(|seq| MembershipStatusUpdateEvent {
+                seq,
                 member: from,
                 new_status: MembershipStatus::Revoked,
             }),
-            Err(e) => {
-                trap(format!("Failed to revoke membership on principal {} - {}", from, e).as_str())
-            }
+            Err(e) => get_env().trap(
+                format!("Failed to revoke membership on principal {} - {}", from, e).as_str(),
+            ),
         }
     }
 }
@@ -85,30 +140,38 @@ fn revoke_memberships(request: IssueRevokeMembershipsRequest) {
 #[update]
 fn accept_membership() {
     log("membership_token.accept_membership()");
+    count_call("accept_membership");
 
-    let caller = caller();
+    let caller = get_env().caller();
 
     match get_token().accept_membership(caller) {
-        Ok(_) => emit(MembershipStatusUpdateEvent {
+        Ok(_) => emit_logged(|seq| MembershipStatusUpdateEvent {
+            seq,
             member: caller,
             new_status: MembershipStatus::Accepted,
         }),
-        Err(e) => trap(format!("Failed to accept membership for caller - {}", e).as_str()),
+        Err(e) => {
+            get_env().trap(format!("Failed to accept membership for caller - {}", e).as_str())
+        }
     }
 }
 
 #[update]
 fn decline_membership() {
     log("membership_token.decline_membership()");
+    count_call("decline_membership");
 
-    let caller = caller();
+    let caller = get_env().caller();
 
     match get_token().decline_membership(caller) {
-        Ok(_) => emit(MembershipStatusUpdateEvent {
+        Ok(_) => emit_logged(|seq| MembershipStatusUpdateEvent {
+            seq,
             member: caller,
             new_status: MembershipStatus::Declined,
         }),
-        Err(e) => trap(format!("Failed to decline membership for caller - {}", e).as_str()),
+        Err(e) => {
+            get_env().trap(format!("Failed to decline membership for caller - {}", e).as_str())
+        }
     }
 }
 
@@ -132,6 +195,33 @@ fn is_pending_member(request: IsMemberRequest) -> IsMemberResponse {
     }
 }
 
+#[query]
+fn are_members(request: AreMembersRequest) -> AreMembersResponse {
+    log("membership_token.are_members()");
+
+    if request.principals.len() > MAX_BATCH_QUERY_SIZE {
+        get_env().trap(
+            format!(
+                "Too many principals in a single request, the limit is {}",
+                MAX_BATCH_QUERY_SIZE
+            )
+            .as_str(),
+        );
+    }
+
+    let token = get_token();
+    let statuses = request
+        .principals
+        .into_iter()
+        .map(|prin| MemberStatusEntry {
+            prin,
+            status: token.get_member_status(&prin),
+        })
+        .collect();
+
+    AreMembersResponse { statuses }
+}
+
 #[query]
 fn get_total_members() -> GetTotalMembersResponse {
     log("membership_token.total_members()");
@@ -141,64 +231,737 @@ fn get_total_members() -> GetTotalMembersResponse {
     GetTotalMembersResponse { total_members }
 }
 
-// ------------- GRANULAR CONTROL -----------------
+// ------------- GRANULAR CONTROL --------------------
 
-#[update(guard = "issue_guard")]
-fn update_issue_controller(request: UpdateControllerRequest) -> UpdateControllerResponse {
-    log("membership_token.update_issue_controller()");
+#[query]
+fn get_controllers() -> GetControllersResponse {
+    log("membership_token.get_controllers()");
 
-    let old_controller = get_token().update_issue_controllers(request.new_controllers.clone());
+    let controllers = get_token().roles.get_roles();
 
-    emit(ControllersUpdateEvent {
-        kind: ControllerType::Issue,
-        new_controllers: request.new_controllers,
-    });
+    GetControllersResponse { controllers }
+}
 
-    UpdateControllerResponse {
-        old_controllers: old_controller,
+#[update]
+fn grant_role(request: RoleRequest) {
+    log("membership_token.grant_role()");
+    count_call("grant_role");
+
+    TokenGovernance::grant_role(request, get_env().caller());
+}
+
+#[update]
+fn revoke_role(request: RoleRequest) {
+    log("membership_token.revoke_role()");
+    count_call("revoke_role");
+
+    TokenGovernance::revoke_role(request, get_env().caller());
+}
+
+#[update]
+fn set_role_admin(request: SetRoleAdminRequest) -> SetRoleAdminResponse {
+    log("membership_token.set_role_admin()");
+    count_call("set_role_admin");
+
+    let (old_admin_role, scheduled_update_id) =
+        TokenGovernance::set_role_admin(request, get_env().caller());
+
+    SetRoleAdminResponse {
+        old_admin_role,
+        scheduled_update_id,
     }
 }
 
+#[query]
+fn has_role(request: RoleRequest) -> HasRoleResponse {
+    log("membership_token.has_role()");
+
+    let has_role = get_token()
+        .roles
+        .has_role(&request.role, &request.principal);
+
+    HasRoleResponse { has_role }
+}
+
+#[query]
+fn get_role_members(request: GetRoleMembersRequest) -> GetRoleMembersResponse {
+    log("membership_token.get_role_members()");
+
+    let members = get_token().roles.get_role_members(&request.role);
+
+    GetRoleMembersResponse { members }
+}
+
+#[update(guard = "issue_guard")]
+fn update_issue_controller(request: UpdateControllerRequest) -> UpdateControllerResponse {
+    log("membership_token.update_issue_controller()");
+    count_call("update_issue_controller");
+
+    _update_role_controllers(ISSUE_ROLE, request)
+}
+
 #[update(guard = "revoke_guard")]
 fn update_revoke_controller(request: UpdateControllerRequest) -> UpdateControllerResponse {
     log("membership_token.update_revoke_controller()");
+    count_call("update_revoke_controller");
 
-    let old_controller = get_token().update_revoke_controllers(request.new_controllers.clone());
-
-    emit(ControllersUpdateEvent {
-        kind: ControllerType::Revoke,
-        new_controllers: request.new_controllers,
-    });
-
-    UpdateControllerResponse {
-        old_controllers: old_controller,
-    }
+    _update_role_controllers(REVOKE_ROLE, request)
 }
 
 #[update(guard = "event_listeners_guard")]
 fn update_event_listeners_controller(request: UpdateControllerRequest) -> UpdateControllerResponse {
     log("membership_token.update_event_listeners_controller()");
+    count_call("update_event_listeners_controller");
 
-    let old_controller =
-        get_token().update_event_listeners_controllers(request.new_controllers.clone());
+    _update_role_controllers(EVENT_LISTENERS_ROLE, request)
+}
 
-    emit(ControllersUpdateEvent {
-        kind: ControllerType::EventListeners,
-        new_controllers: request.new_controllers,
-    });
+fn _update_role_controllers(
+    role: &str,
+    request: UpdateControllerRequest,
+) -> UpdateControllerResponse {
+    let (old_controllers, scheduled_update_id) = TokenGovernance::update_role_controllers(
+        UpdateRoleControllersRequest {
+            role: String::from(role),
+            new_controllers: request.new_controllers,
+            renounce: request.renounce,
+        },
+        get_env().caller(),
+    );
 
     UpdateControllerResponse {
-        old_controllers: old_controller,
+        old_controllers,
+        scheduled_update_id,
+    }
+}
+
+// ------------------ HANDOVER -------------------
+
+#[update]
+fn accept_controllers_handover(request: ControllersHandoverRequest) {
+    log("membership_token.accept_controllers_handover()");
+    count_call("accept_controllers_handover");
+
+    TokenGovernance::accept_controllers_handover(&request.role, get_env().caller());
+}
+
+#[update]
+fn cancel_controllers_handover(request: ControllersHandoverRequest) {
+    log("membership_token.cancel_controllers_handover()");
+    count_call("cancel_controllers_handover");
+
+    TokenGovernance::cancel_controllers_handover(&request.role, get_env().caller());
+}
+
+#[query]
+fn get_controllers_handovers() -> GetControllersHandoversResponse {
+    log("membership_token.get_controllers_handovers()");
+
+    let handovers = get_token().handovers.get_handovers();
+
+    GetControllersHandoversResponse { handovers }
+}
+
+// ------------------ TIMELOCK -------------------
+
+#[update]
+fn cancel_controllers_update(request: ControllersUpdateIdRequest) {
+    log("membership_token.cancel_controllers_update()");
+    count_call("cancel_controllers_update");
+
+    TokenGovernance::cancel_controllers_update(request.update_id, get_env().caller());
+}
+
+#[query]
+fn get_scheduled_controllers_updates() -> GetScheduledControllersUpdatesResponse {
+    log("membership_token.get_scheduled_controllers_updates()");
+
+    let updates = get_token().timelock.get_scheduled_updates();
+
+    GetScheduledControllersUpdatesResponse { updates }
+}
+
+#[update(guard = "admin_guard")]
+fn set_controllers_update_delay(request: ControllersUpdateDelay) {
+    log("membership_token.set_controllers_update_delay()");
+    count_call("set_controllers_update_delay");
+
+    TokenGovernance::set_controllers_update_delay(request.delay_nano, get_env().caller());
+}
+
+#[query]
+fn get_controllers_update_delay() -> ControllersUpdateDelay {
+    log("membership_token.get_controllers_update_delay()");
+
+    ControllersUpdateDelay {
+        delay_nano: get_token().timelock.delay_nano,
+    }
+}
+
+// ------------------ MULTISIG -------------------
+
+#[update]
+fn set_approval_threshold(request: SetApprovalThresholdRequest) -> SetApprovalThresholdResponse {
+    log("membership_token.set_approval_threshold()");
+    count_call("set_approval_threshold");
+
+    let old_threshold = TokenGovernance::set_approval_threshold(
+        &request.role,
+        request.threshold,
+        get_env().caller(),
+    );
+
+    SetApprovalThresholdResponse { old_threshold }
+}
+
+#[query]
+fn get_approval_thresholds() -> GetApprovalThresholdsResponse {
+    log("membership_token.get_approval_thresholds()");
+
+    let thresholds = get_token()
+        .proposals
+        .thresholds
+        .iter()
+        .map(|(role, threshold)| ApprovalThreshold {
+            role: role.clone(),
+            threshold: *threshold,
+        })
+        .collect();
+
+    GetApprovalThresholdsResponse { thresholds }
+}
+
+#[update]
+fn propose(request: ProposeRequest) -> ProposeResponse {
+    log("membership_token.propose()");
+    count_call("propose");
+
+    let (proposal_id, executed) =
+        TokenGovernance::propose(request.action, request.ttl_nano, get_env().caller());
+
+    ProposeResponse {
+        proposal_id,
+        executed,
     }
 }
 
+#[update]
+fn approve_proposal(request: ProposalIdRequest) -> ApproveProposalResponse {
+    log("membership_token.approve_proposal()");
+    count_call("approve_proposal");
+
+    let executed = TokenGovernance::approve_proposal(request.proposal_id, get_env().caller());
+
+    ApproveProposalResponse { executed }
+}
+
 #[query]
-fn get_controllers() -> GetControllersResponse {
-    log("membership_token.get_controllers()");
+fn get_proposal(request: ProposalIdRequest) -> GetProposalResponse {
+    log("membership_token.get_proposal()");
 
-    let controllers = get_token().controllers.clone();
+    let proposal = get_token()
+        .proposals
+        .get_proposal(request.proposal_id, get_env().time());
 
-    GetControllersResponse { controllers }
+    GetProposalResponse { proposal }
+}
+
+#[query]
+fn get_pending_proposals() -> GetProposalsResponse {
+    log("membership_token.get_pending_proposals()");
+
+    let proposals = get_token()
+        .proposals
+        .get_pending_proposals(get_env().time());
+
+    GetProposalsResponse { proposals }
+}
+
+/// Traps if the role is configured to execute privileged operations only through proposals
+fn approval_not_required(role: &str) {
+    TokenGovernance::approval_not_required(role);
+}
+
+/// Binds the shared governance logic to the membership token's state, environment and events
+struct TokenGovernance;
+
+impl Governance for TokenGovernance {
+    type Action = ProposalAction;
+
+    fn roles() -> &'static mut RoleRegistry {
+        &mut get_token().roles
+    }
+
+    fn proposals() -> &'static mut ProposalRegistry<ProposalAction> {
+        &mut get_token().proposals
+    }
+
+    fn timelock() -> &'static mut ControllersTimelock {
+        &mut get_token().timelock
+    }
+
+    fn handovers() -> &'static mut HandoverRegistry {
+        &mut get_token().handovers
+    }
+
+    fn time() -> u64 {
+        get_env().time()
+    }
+
+    fn trap(msg: &str) -> ! {
+        get_env().trap(msg)
+    }
+
+    fn log(msg: &str) {
+        log(msg);
+    }
+
+    fn schedule_update_task(update_id: ControllersUpdateId, delay_nano: u64) -> Option<TaskId> {
+        let enqueue_result = get_env().schedule(
+            CronTaskKind::ControllersUpdate,
+            ControllersUpdateTask { update_id },
+            SchedulingInterval {
+                duration_nano: delay_nano,
+                iterations: Iterations::Exact(1),
+            },
+        );
+
+        match enqueue_result {
+            Ok(task_id) => Some(task_id),
+            Err(_) => {
+                log("Candid serialization error met during controllers update enqueue");
+                None
+            }
+        }
+    }
+
+    fn dequeue_task(task_id: TaskId) {
+        get_env().dequeue(task_id);
+    }
+
+    fn emit(event: GovernanceEvent<ProposalAction>) {
+        match event {
+            GovernanceEvent::ControllersUpdated {
+                role,
+                new_controllers,
+            } => emit_logged(|seq| ControllersUpdateEvent {
+                seq,
+                kind: ControllerType::from(role.as_str()),
+                new_controllers,
+            }),
+            GovernanceEvent::ControllersUpdateScheduled(update) => {
+                emit_logged(|seq| ControllersUpdateScheduledEvent {
+                    seq,
+                    update_id: update.id,
+                    kind: ControllerType::from(update.change.get_role().as_str()),
+                    change: update.change,
+                    scheduled_by: update.scheduled_by,
+                    execute_at: update.execute_at,
+                })
+            }
+            GovernanceEvent::ControllersUpdateCancelled {
+                update_id,
+                role,
+                cancelled_by,
+            } => emit_logged(|seq| ControllersUpdateCancelledEvent {
+                seq,
+                update_id,
+                kind: ControllerType::from(role.as_str()),
+                cancelled_by,
+            }),
+            GovernanceEvent::HandoverProposed {
+                role,
+                new_controllers,
+                proposed_by,
+            } => emit_logged(|seq| ControllersHandoverProposedEvent {
+                seq,
+                kind: ControllerType::from(role.as_str()),
+                new_controllers,
+                proposed_by,
+            }),
+            GovernanceEvent::HandoverAccepted { role, accepted_by } => {
+                emit_logged(|seq| ControllersHandoverAcceptedEvent {
+                    seq,
+                    kind: ControllerType::from(role.as_str()),
+                    accepted_by,
+                })
+            }
+            GovernanceEvent::HandoverCancelled { role, cancelled_by } => {
+                emit_logged(|seq| ControllersHandoverCancelledEvent {
+                    seq,
+                    kind: ControllerType::from(role.as_str()),
+                    cancelled_by,
+                })
+            }
+            GovernanceEvent::ProposalCreated {
+                proposal_id,
+                proposer,
+                role,
+                action,
+                expires_at,
+            } => emit_logged(|seq| ProposalCreatedEvent {
+                seq,
+                proposal_id,
+                proposer,
+                role,
+                action,
+                expires_at,
+            }),
+            GovernanceEvent::ProposalApproved {
+                proposal_id,
+                approver,
+                approvals,
+            } => emit_logged(|seq| ProposalApprovedEvent {
+                seq,
+                proposal_id,
+                approver,
+                approvals,
+            }),
+            GovernanceEvent::ProposalExecuted { proposal_id } => {
+                emit_logged(|seq| ProposalExecutedEvent { seq, proposal_id })
+            }
+        }
+    }
+
+    fn required_role(action: &ProposalAction) -> Role {
+        let roles = &get_token().roles;
+
+        match action {
+            ProposalAction::IssueMemberships(_) => String::from(ISSUE_ROLE),
+            ProposalAction::RevokeMemberships(_) => String::from(REVOKE_ROLE),
+            ProposalAction::UpdateControllers(r) => roles.get_admin_role(&r.role),
+            ProposalAction::GrantRole(r) => roles.get_admin_role(&r.role),
+            ProposalAction::RevokeRole(r) => roles.get_admin_role(&r.role),
+            ProposalAction::SetApprovalThreshold(r) => roles.get_admin_role(&r.role),
+        }
+    }
+
+    fn execute_action(action: ProposalAction, proposer: Principal) {
+        match action {
+            ProposalAction::IssueMemberships(request) => _issue_memberships(request),
+            ProposalAction::RevokeMemberships(request) => _revoke_memberships(request),
+            ProposalAction::UpdateControllers(request) => {
+                Self::propose_role_controllers(request, proposer);
+            }
+            ProposalAction::GrantRole(request) => {
+                Self::schedule_controllers_change(ControllersChange::GrantRole(request), proposer);
+            }
+            ProposalAction::RevokeRole(request) => {
+                Self::schedule_role_revocation(request, proposer);
+            }
+            ProposalAction::SetApprovalThreshold(request) => {
+                Self::apply_approval_threshold(&request.role, request.threshold);
+            }
+        };
+    }
+}
+
+// --------------- RECURRENCE ------------------
+
+implement_cron!();
+
+/// Records the heartbeat before dispatching the due tasks, so the heartbeat is observed even
+/// when there is nothing to execute
+#[heartbeat]
+fn heartbeat() {
+    let now = get_env().time();
+    get_token().metrics.record_heartbeat(now);
+
+    for task in get_cron_state().iterate(now) {
+        _cron_task_handler(task);
+    }
+}
+
+fn _cron_task_handler(task: ScheduledTask) {
+    _execute_cron_task(task.get_kind(), &task);
+}
+
+fn _execute_cron_task<P: TaskPayload>(kind: u8, task: &P) {
+    check_cycles();
+
+    match kind.try_into() {
+        Ok(CronTaskKind::ControllersUpdate) => _controllers_update(task.decode_payload().unwrap()),
+        Err(_) => log("Invalid cron task handler"),
+    }
+}
+
+fn _controllers_update(task: ControllersUpdateTask) {
+    log("membership_token._controllers_update()");
+
+    TokenGovernance::apply_due_controllers_update(task.update_id);
+}
+
+// ------------------ CYCLES --------------------
+
+#[update]
+fn wallet_receive() -> WalletReceiveResponse {
+    log("membership_token.wallet_receive()");
+    count_call("wallet_receive");
+
+    let accepted = get_env().accept_cycles();
+    check_cycles();
+
+    WalletReceiveResponse { accepted }
+}
+
+#[query]
+fn get_cycles_balance() -> GetCyclesBalanceResponse {
+    log("membership_token.get_cycles_balance()");
+
+    let cycles = &get_token().cycles;
+
+    GetCyclesBalanceResponse {
+        balance: get_env().cycles_balance(),
+        low_water_mark: cycles.low_water_mark,
+        suspended: cycles.is_suspended(),
+    }
+}
+
+#[update(guard = "admin_guard")]
+fn set_cycles_low_water_mark(request: SetCyclesLowWaterMarkRequest) {
+    log("membership_token.set_cycles_low_water_mark()");
+    count_call("set_cycles_low_water_mark");
+
+    approval_not_required(ADMIN_ROLE);
+
+    let cycles = &mut get_token().cycles;
+    cycles.low_water_mark = request.low_water_mark;
+    cycles.suspend_on_low_cycles = request.suspend_on_low_cycles;
+
+    check_cycles();
+}
+
+/// Raises a `LowCyclesEvent` once the balance drops below the low-water mark. The alert
+/// itself is delivered to listeners even if the rest of event delivery is suspended.
+fn check_cycles() {
+    let balance = get_env().cycles_balance();
+
+    if let Some(low_water_mark) = get_token().cycles.check(balance) {
+        log(format!(
+            "Cycles balance {} dropped below the low-water mark {}",
+            balance, low_water_mark
+        )
+        .as_str());
+
+        let event = log_event(|seq| LowCyclesEvent {
+            seq,
+            balance,
+            low_water_mark,
+        });
+
+        get_env().emit(event);
+    }
+}
+
+// ------------------ METRICS --------------------
+
+#[query]
+fn get_metrics() -> GetMetricsResponse {
+    log("membership_token.get_metrics()");
+
+    _get_metrics()
+}
+
+fn _get_metrics() -> GetMetricsResponse {
+    let token = get_token();
+
+    GetMetricsResponse {
+        heap_memory_bytes: heap_memory_bytes(),
+        stable_memory_bytes: stable_memory_bytes(),
+        cycles_balance: get_env().cycles_balance(),
+        members_count: token.members.len() as u64,
+        pending_members_count: token.pending_members.len() as u64,
+        scheduled_controllers_updates_count: token.timelock.scheduled_updates.len() as u64,
+        event_listeners_count: token.roles.get_role_members(EVENT_LISTENERS_ROLE).len() as u64,
+        call_counters: token.metrics.get_call_counters(),
+        last_heartbeat_at: token.metrics.last_heartbeat_at,
+    }
+}
+
+fn encode_prometheus_metrics(metrics: &GetMetricsResponse) -> String {
+    PrometheusWriter::new("membership_token")
+        .gauge(
+            "heap_memory_bytes",
+            "Heap memory size",
+            metrics.heap_memory_bytes,
+        )
+        .gauge(
+            "stable_memory_bytes",
+            "Stable memory size",
+            metrics.stable_memory_bytes,
+        )
+        .gauge("cycles_balance", "Cycles balance", metrics.cycles_balance)
+        .gauge("members", "Number of members", metrics.members_count)
+        .gauge(
+            "pending_members",
+            "Number of pending members",
+            metrics.pending_members_count,
+        )
+        .gauge(
+            "scheduled_controllers_updates",
+            "Number of timelocked controllers updates",
+            metrics.scheduled_controllers_updates_count,
+        )
+        .gauge(
+            "event_listeners",
+            "Number of event listeners",
+            metrics.event_listeners_count,
+        )
+        .labeled_counter(
+            "calls_total",
+            "Update calls per method",
+            "method",
+            &metrics.call_counters,
+        )
+        .gauge(
+            "last_heartbeat_timestamp_nanos",
+            "Last time the canister heartbeat ran",
+            metrics.last_heartbeat_at.unwrap_or_default(),
+        )
+        .finish()
+}
+
+fn log(msg: &str) {
+    get_env().log(msg);
+}
+
+fn count_call(method: &str) {
+    get_token().metrics.record_call(method);
+}
+
+// -------------------- HTTP ----------------------
+
+/// Serves membership data as JSON for browsers and explorers, through the raw (uncertified)
+/// domain only
+#[query]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    log("membership_token.http_request()");
+
+    if request.method != "GET" {
+        return error_response(405, "Method not allowed");
+    }
+
+    let (segments, params) = parse_url(&request.url);
+
+    let result = match segments.as_slice() {
+        ["metrics"] => Ok(text_response(
+            200,
+            "text/plain; version=0.0.4",
+            encode_prometheus_metrics(&_get_metrics()),
+        )),
+        ["members"] => http_members(&params),
+        ["is_member", prin] => http_is_member(prin),
+        _ => Err(error_response(404, "Not found")),
+    };
+
+    result.unwrap_or_else(|e| e)
+}
+
+fn http_members(params: &[(&str, &str)]) -> Result<HttpResponse, HttpResponse> {
+    let cursor = get_principal_param(params, "cursor")?;
+    let limit = get_num_param(params, "limit")?.unwrap_or(DEFAULT_HTTP_PAGE_SIZE as u64);
+
+    let (members, next_cursor) = get_token().get_members(cursor, limit as usize);
+
+    let body = Json::object(vec![
+        (
+            "members",
+            Json::Array(members.into_iter().map(Json::from).collect()),
+        ),
+        ("next_cursor", next_cursor.into()),
+    ]);
+
+    Ok(json_response(200, body.encode().into_bytes()))
+}
+
+fn http_is_member(prin: &str) -> Result<HttpResponse, HttpResponse> {
+    let prin = parse_principal(prin)?;
+
+    let status = match get_token().get_member_status(&prin) {
+        MemberStatus::Member => "member",
+        MemberStatus::Pending => "pending",
+        MemberStatus::NotMember => "none",
+    };
+
+    let body = Json::object(vec![("principal", prin.into()), ("status", status.into())]);
+
+    Ok(json_response(200, body.encode().into_bytes()))
+}
+
+// ------------------ EVENT LOG --------------------
+
+#[query]
+fn get_events(request: GetEventsRequest) -> GetEventsResponse {
+    log("membership_token.get_events()");
+
+    let event_log = &get_token().event_log;
+    let events = event_log.get_events(
+        request.from_seq,
+        request.limit as usize,
+        &request.topic_filter,
+    );
+
+    GetEventsResponse {
+        events,
+        oldest_seq: event_log.get_oldest_seq(),
+        next_seq: event_log.get_next_seq(),
+    }
+}
+
+#[update(guard = "admin_guard")]
+fn set_event_archive(request: SetEventArchiveRequest) {
+    log("membership_token.set_event_archive()");
+    count_call("set_event_archive");
+
+    get_token().event_log.archive = request.archive;
+}
+
+/// Stores the event in the event log under the next sequence number and then delivers
+/// it to the listeners, unless event delivery is suspended because of low cycles
+fn emit_logged<T: LoggedEvent + IEvent, F: FnOnce(EventSeq) -> T>(make_event: F) {
+    check_cycles();
+
+    let event = log_event(make_event);
+
+    if !get_token().cycles.is_suspended() {
+        get_env().emit(event);
+    }
+}
+
+/// Stores the event in the event log under the next sequence number, flushing evicted
+/// events to the archive if there is one
+fn log_event<T: LoggedEvent, F: FnOnce(EventSeq) -> T>(make_event: F) -> T {
+    let seq = get_token().event_log.get_next_seq();
+    let event = make_event(seq);
+
+    match encode_one(&event) {
+        Ok(payload) => {
+            get_token().event_log.push(
+                event.get_name(),
+                event.get_principals(),
+                payload,
+                get_env().time(),
+            );
+        }
+        Err(_) => get_env().trap("Candid serialization error met during event logging"),
+    };
+
+    if let Some((archive, batch)) = get_token().event_log.take_archive_batch() {
+        get_env().append_to_archive(archive, batch, move |accepted| {
+            if accepted {
+                get_token().event_log.confirm_archive_batch(archive);
+            } else {
+                log("Failed to archive evicted events, they will be retried with the next event");
+                get_token().event_log.return_archive_batch();
+
+                if get_token().event_log.is_archive_queue_full() {
+                    log("The archive queue is full, newly evicted events are dropped until the archive accepts them");
+                }
+            }
+        });
+    }
+
+    event
 }
 
 // ------------------ EVENT HUB --------------------
@@ -0,0 +1,639 @@
+//! Endpoint tests, running the actor against `MockEnvironment` instead of a replica. Guards
+//! are not applied when endpoints are called directly, so they are tested separately.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::MutexGuard;
+
+use ic_cdk::export::candid::Principal;
+use ic_cron::types::TaskId;
+
+use membership_token_client::types::{
+    AreMembersRequest, ControllersHandoverRequest, ControllersUpdateDelay,
+    ControllersUpdateIdRequest, EventTopicFilter, GetEventsRequest, GetRoleMembersRequest,
+    HttpRequest, InitRequest, IsMemberRequest, IssueRevokeMembershipsRequest, MemberStatus,
+    ProposalAction, ProposalIdRequest, ProposeRequest, RoleRequest, SetApprovalThresholdRequest,
+    SetCyclesLowWaterMarkRequest, SetEventArchiveRequest, SetRoleAdminRequest,
+    UpdateControllerRequest, ADMIN_ROLE, EVENT_LISTENERS_ROLE, ISSUE_ROLE, REVOKE_ROLE,
+};
+use token_common::event_log::ARCHIVE_BATCH_SIZE;
+use token_common::timelock::MAX_CONTROLLERS_UPDATE_DELAY_NANO;
+
+use crate::common::env::{get_env, set_env, MockEnvironment, ENV_LOCK};
+use crate::common::guards::{admin_guard, event_listeners_guard, issue_guard, revoke_guard};
+use crate::common::membership_token::MAX_BATCH_QUERY_SIZE;
+use crate::*;
+
+/// Initializes the canister on behalf of a fresh controller
+fn setup() -> (MutexGuard<'static, ()>, Principal) {
+    let lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let controller = random_principal();
+
+    set_env(MockEnvironment {
+        caller: controller,
+        time: 1000,
+        ..Default::default()
+    });
+
+    init(InitRequest {
+        default_controllers: None,
+        controllers_update_delay_nano: None,
+    });
+
+    (lock, controller)
+}
+
+fn set_caller(caller: Principal) {
+    get_env().caller = caller;
+}
+
+/// Unlike `union_utils::random_principal_test`, which is derived from the current time, never
+/// repeats itself and never produces an invalid principal
+fn random_principal() -> Principal {
+    static COUNTER: AtomicU64 = AtomicU64::new(1);
+
+    let mut bytes = COUNTER
+        .fetch_add(1, Ordering::Relaxed)
+        .to_be_bytes()
+        .to_vec();
+    // the opaque id class
+    bytes.push(1);
+
+    Principal::from_slice(&bytes)
+}
+
+fn traps<F: FnOnce()>(f: F) -> bool {
+    catch_unwind(AssertUnwindSafe(f)).is_err()
+}
+
+fn principals(principals: &[Principal]) -> IssueRevokeMembershipsRequest {
+    IssueRevokeMembershipsRequest {
+        principals: principals.to_vec(),
+    }
+}
+
+fn status_of(prin: Principal) -> MemberStatus {
+    get_token().get_member_status(&prin)
+}
+
+fn role_request(role: &str, principal: Principal) -> RoleRequest {
+    RoleRequest {
+        role: String::from(role),
+        principal,
+    }
+}
+
+fn fire_task(task_id: TaskId) {
+    let task = get_env().fire_task(task_id).unwrap();
+
+    _execute_cron_task(task.kind, &task);
+}
+
+fn last_event_name() -> String {
+    let names = get_env().get_emitted_event_names();
+
+    String::from(*names.last().unwrap())
+}
+
+fn get(url: &str) -> (u16, String) {
+    let response = http_request(HttpRequest {
+        method: String::from("GET"),
+        url: String::from(url),
+        headers: vec![],
+        body: vec![],
+    });
+
+    (
+        response.status_code,
+        String::from_utf8(response.body).unwrap(),
+    )
+}
+
+#[test]
+fn init_works_fine() {
+    let (_lock, controller) = setup();
+
+    for role in &[ADMIN_ROLE, ISSUE_ROLE, REVOKE_ROLE, EVENT_LISTENERS_ROLE] {
+        assert!(has_role(role_request(role, controller)).has_role);
+    }
+
+    assert_eq!(get_total_members().total_members, 0);
+    assert_eq!(get_controllers().controllers.len(), 4);
+
+    let default_controller = random_principal();
+    init(InitRequest {
+        default_controllers: Some(vec![default_controller]),
+        controllers_update_delay_nano: Some(100),
+    });
+
+    assert!(has_role(role_request(ISSUE_ROLE, default_controller)).has_role);
+    assert!(!has_role(role_request(ISSUE_ROLE, controller)).has_role);
+    assert_eq!(get_controllers_update_delay().delay_nano, 100);
+
+    assert!(traps(|| init(InitRequest {
+        default_controllers: None,
+        controllers_update_delay_nano: Some(MAX_CONTROLLERS_UPDATE_DELAY_NANO + 1),
+    })));
+}
+
+#[test]
+fn membership_lifecycle_works_fine() {
+    let (_lock, controller) = setup();
+    let (user_1, user_2) = (random_principal(), random_principal());
+
+    issue_memberships(principals(&[user_1, user_2]));
+
+    assert_eq!(status_of(user_1), MemberStatus::Pending);
+    assert!(is_pending_member(IsMemberRequest { prin: user_1 }).is_member);
+    assert!(!is_member(IsMemberRequest { prin: user_1 }).is_member);
+    assert_eq!(get_env().emitted_events.len(), 2);
+
+    assert!(traps(|| issue_memberships(principals(&[user_1]))));
+
+    set_caller(user_1);
+    accept_membership();
+
+    assert!(is_member(IsMemberRequest { prin: user_1 }).is_member);
+    assert_eq!(get_total_members().total_members, 1);
+    assert!(traps(accept_membership));
+
+    set_caller(user_2);
+    decline_membership();
+
+    assert_eq!(status_of(user_2), MemberStatus::NotMember);
+    assert!(traps(decline_membership));
+
+    set_caller(controller);
+    assert!(traps(|| revoke_memberships(principals(&[user_2]))));
+
+    revoke_memberships(principals(&[user_1]));
+
+    assert_eq!(status_of(user_1), MemberStatus::NotMember);
+    assert_eq!(get_total_members().total_members, 0);
+    assert_eq!(last_event_name(), "MembershipStatusUpdateEvent");
+}
+
+#[test]
+fn are_members_works_fine() {
+    let (_lock, _) = setup();
+    let (user_1, user_2, user_3) = (random_principal(), random_principal(), random_principal());
+
+    issue_memberships(principals(&[user_1, user_2]));
+
+    set_caller(user_1);
+    accept_membership();
+
+    let statuses = are_members(AreMembersRequest {
+        principals: vec![user_1, user_2, user_3],
+    })
+    .statuses;
+
+    assert_eq!(statuses[0].status, MemberStatus::Member);
+    assert_eq!(statuses[1].status, MemberStatus::Pending);
+    assert_eq!(statuses[2].status, MemberStatus::NotMember);
+
+    assert!(traps(|| {
+        are_members(AreMembersRequest {
+            principals: vec![user_1; MAX_BATCH_QUERY_SIZE + 1],
+        });
+    }));
+}
+
+#[test]
+fn roles_work_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    grant_role(role_request(ISSUE_ROLE, user));
+
+    assert!(has_role(role_request(ISSUE_ROLE, user)).has_role);
+    assert_eq!(
+        get_role_members(GetRoleMembersRequest {
+            role: String::from(ISSUE_ROLE),
+        })
+        .members
+        .len(),
+        2
+    );
+
+    revoke_role(role_request(ISSUE_ROLE, controller));
+    assert!(!has_role(role_request(ISSUE_ROLE, controller)).has_role);
+
+    // the last member can't be revoked, the role can only be renounced
+    set_caller(user);
+    assert!(traps(|| revoke_role(role_request(ISSUE_ROLE, user))));
+    assert!(traps(|| revoke_role(role_request(ISSUE_ROLE, controller))));
+    assert!(traps(|| grant_role(role_request(REVOKE_ROLE, user))));
+
+    // a role without members can't administer other roles
+    assert!(traps(|| {
+        set_role_admin(SetRoleAdminRequest {
+            role: String::from(ISSUE_ROLE),
+            admin_role: String::from("nobody"),
+        });
+    }));
+
+    let old_admin_role = set_role_admin(SetRoleAdminRequest {
+        role: String::from(ISSUE_ROLE),
+        admin_role: String::from(ADMIN_ROLE),
+    })
+    .old_admin_role;
+    assert_eq!(old_admin_role, ISSUE_ROLE);
+
+    // now the admins manage the issue role
+    set_caller(controller);
+    grant_role(role_request(ISSUE_ROLE, controller));
+    assert!(has_role(role_request(ISSUE_ROLE, controller)).has_role);
+
+    set_caller(user);
+    assert!(traps(|| {
+        set_role_admin(SetRoleAdminRequest {
+            role: String::from(REVOKE_ROLE),
+            admin_role: String::from(ISSUE_ROLE),
+        });
+    }));
+}
+
+#[test]
+fn controllers_handover_works_fine() {
+    let (_lock, controller) = setup();
+    let (user_1, user_2) = (random_principal(), random_principal());
+
+    let response = update_issue_controller(UpdateControllerRequest {
+        new_controllers: vec![user_1, user_2],
+        renounce: None,
+    });
+
+    assert_eq!(response.old_controllers, vec![controller]);
+    assert!(response.scheduled_update_id.is_none());
+    assert_eq!(get_controllers_handovers().handovers.len(), 1);
+
+    let issue_handover_request = || ControllersHandoverRequest {
+        role: String::from(ISSUE_ROLE),
+    };
+
+    set_caller(user_1);
+    accept_controllers_handover(issue_handover_request());
+    assert!(traps(|| accept_controllers_handover(
+        issue_handover_request()
+    )));
+    assert!(has_role(role_request(ISSUE_ROLE, controller)).has_role);
+
+    set_caller(user_2);
+    accept_controllers_handover(issue_handover_request());
+
+    assert!(!has_role(role_request(ISSUE_ROLE, controller)).has_role);
+    assert!(has_role(role_request(ISSUE_ROLE, user_1)).has_role);
+    assert!(has_role(role_request(ISSUE_ROLE, user_2)).has_role);
+    assert!(get_controllers_handovers().handovers.is_empty());
+    assert_eq!(last_event_name(), "ControllersUpdateEvent");
+
+    // the proposer accepts their own handover right away
+    set_caller(controller);
+    update_revoke_controller(UpdateControllerRequest {
+        new_controllers: vec![controller, user_1],
+        renounce: None,
+    });
+
+    let revoke_handover_request = || ControllersHandoverRequest {
+        role: String::from(REVOKE_ROLE),
+    };
+
+    set_caller(random_principal());
+    assert!(traps(|| cancel_controllers_handover(
+        revoke_handover_request()
+    )));
+
+    // the controller has handed the issue role over already
+    set_caller(controller);
+    assert!(traps(|| cancel_controllers_handover(
+        issue_handover_request()
+    )));
+
+    cancel_controllers_handover(revoke_handover_request());
+    assert!(get_controllers_handovers().handovers.is_empty());
+    assert_eq!(last_event_name(), "ControllersHandoverCancelledEvent");
+}
+
+#[test]
+fn roles_can_be_renounced() {
+    let (_lock, controller) = setup();
+
+    assert!(traps(|| {
+        update_revoke_controller(UpdateControllerRequest {
+            new_controllers: vec![],
+            renounce: None,
+        });
+    }));
+
+    update_revoke_controller(UpdateControllerRequest {
+        new_controllers: vec![],
+        renounce: Some(true),
+    });
+    assert!(!has_role(role_request(REVOKE_ROLE, controller)).has_role);
+
+    update_event_listeners_controller(UpdateControllerRequest {
+        new_controllers: vec![],
+        renounce: Some(true),
+    });
+    assert_eq!(get_metrics().event_listeners_count, 0);
+}
+
+#[test]
+fn timelock_works_fine() {
+    let (_lock, controller) = setup();
+    let user = random_principal();
+
+    set_controllers_update_delay(ControllersUpdateDelay { delay_nano: 100 });
+    assert_eq!(get_controllers_update_delay().delay_nano, 100);
+
+    grant_role(role_request(ISSUE_ROLE, user));
+    assert!(!has_role(role_request(ISSUE_ROLE, user)).has_role);
+
+    let updates = get_scheduled_controllers_updates().updates;
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].execute_at, 1100);
+    assert_eq!(last_event_name(), "ControllersUpdateScheduledEvent");
+
+    let task_id = get_token().timelock.get_update_task(updates[0].id).unwrap();
+
+    get_env().time = 1100;
+    fire_task(task_id);
+
+    assert!(has_role(role_request(ISSUE_ROLE, user)).has_role);
+    assert!(get_scheduled_controllers_updates().updates.is_empty());
+
+    // shortening the delay is a subject to the delay itself
+    set_controllers_update_delay(ControllersUpdateDelay { delay_nano: 10 });
+    assert_eq!(get_controllers_update_delay().delay_nano, 100);
+    assert_eq!(get_metrics().scheduled_controllers_updates_count, 1);
+
+    let update_id = get_scheduled_controllers_updates().updates[0].id;
+
+    set_caller(user);
+    assert!(traps(|| cancel_controllers_update(
+        ControllersUpdateIdRequest { update_id }
+    )));
+
+    set_caller(controller);
+    cancel_controllers_update(ControllersUpdateIdRequest { update_id });
+
+    assert!(get_scheduled_controllers_updates().updates.is_empty());
+    assert!(get_env().tasks.is_empty());
+    assert!(traps(|| cancel_controllers_update(
+        ControllersUpdateIdRequest { update_id }
+    )));
+
+    // so is lengthening it, while the delay itself is bounded
+    set_controllers_update_delay(ControllersUpdateDelay { delay_nano: 200 });
+    assert_eq!(get_controllers_update_delay().delay_nano, 100);
+    assert_eq!(get_scheduled_controllers_updates().updates.len(), 1);
+
+    assert!(traps(|| set_controllers_update_delay(
+        ControllersUpdateDelay {
+            delay_nano: MAX_CONTROLLERS_UPDATE_DELAY_NANO + 1,
+        }
+    )));
+}
+
+#[test]
+fn multisig_works_fine() {
+    let (_lock, controller) = setup();
+    let (issuer, user) = (random_principal(), random_principal());
+
+    grant_role(role_request(ISSUE_ROLE, issuer));
+
+    assert!(traps(|| {
+        set_approval_threshold(SetApprovalThresholdRequest {
+            role: String::from(ISSUE_ROLE),
+            threshold: 3,
+        });
+    }));
+
+    let old_threshold = set_approval_threshold(SetApprovalThresholdRequest {
+        role: String::from(ISSUE_ROLE),
+        threshold: 2,
+    })
+    .old_threshold;
+
+    assert_eq!(old_threshold, 1);
+    assert_eq!(get_approval_thresholds().thresholds.len(), 1);
+    assert!(traps(|| issue_memberships(principals(&[user]))));
+
+    let response = propose(ProposeRequest {
+        action: ProposalAction::IssueMemberships(principals(&[user])),
+        ttl_nano: None,
+    });
+
+    assert!(!response.executed);
+    assert_eq!(get_pending_proposals().proposals.len(), 1);
+    assert_eq!(status_of(user), MemberStatus::NotMember);
+
+    let proposal_id = response.proposal_id;
+    assert!(traps(|| {
+        approve_proposal(ProposalIdRequest { proposal_id });
+    }));
+
+    set_caller(user);
+    assert!(traps(|| {
+        approve_proposal(ProposalIdRequest { proposal_id });
+    }));
+
+    set_caller(issuer);
+    let response = approve_proposal(ProposalIdRequest { proposal_id });
+
+    assert!(response.executed);
+    assert_eq!(status_of(user), MemberStatus::Pending);
+    assert!(get_pending_proposals().proposals.is_empty());
+    assert_eq!(last_event_name(), "ProposalExecutedEvent");
+
+    let proposal = get_proposal(ProposalIdRequest { proposal_id })
+        .proposal
+        .unwrap();
+    assert_eq!(proposal.proposer, controller);
+    assert_eq!(proposal.approvals, vec![controller, issuer]);
+
+    set_caller(user);
+    assert!(traps(|| {
+        propose(ProposeRequest {
+            action: ProposalAction::IssueMemberships(principals(&[user])),
+            ttl_nano: None,
+        });
+    }));
+}
+
+#[test]
+fn cycles_work_fine() {
+    let (_lock, _) = setup();
+
+    get_env().cycles_balance = 100;
+    set_cycles_low_water_mark(SetCyclesLowWaterMarkRequest {
+        low_water_mark: Some(1000),
+        suspend_on_low_cycles: true,
+    });
+
+    let response = get_cycles_balance();
+    assert_eq!(response.balance, 100);
+    assert_eq!(response.low_water_mark, Some(1000));
+    assert!(response.suspended);
+    assert_eq!(get_env().get_emitted_event_names(), vec!["LowCyclesEvent"]);
+
+    // events are still logged, but not delivered
+    issue_memberships(principals(&[random_principal()]));
+    assert_eq!(get_env().emitted_events.len(), 1);
+    assert_eq!(get_token().event_log.get_next_seq(), 2);
+
+    get_env().cycles_available = 10_000;
+    assert_eq!(wallet_receive().accepted, 10_000);
+
+    let response = get_cycles_balance();
+    assert_eq!(response.balance, 10_100);
+    assert!(!response.suspended);
+}
+
+#[test]
+fn metrics_work_fine() {
+    let (_lock, _) = setup();
+    let user = random_principal();
+
+    issue_memberships(principals(&[user, random_principal()]));
+
+    set_caller(user);
+    accept_membership();
+
+    let metrics = get_metrics();
+    assert_eq!(metrics.members_count, 1);
+    assert_eq!(metrics.pending_members_count, 1);
+    assert_eq!(metrics.cycles_balance, u64::MAX);
+    assert!(metrics
+        .call_counters
+        .iter()
+        .any(|(method, count)| method == "accept_membership" && *count == 1));
+
+    assert!(metrics.last_heartbeat_at.is_none());
+
+    get_env().time = 100;
+    heartbeat();
+    assert_eq!(get_metrics().last_heartbeat_at, Some(100));
+
+    let (status, body) = get("/metrics");
+    assert_eq!(status, 200);
+    assert!(body.contains("membership_token_members 1"));
+}
+
+#[test]
+fn http_request_works_fine() {
+    let (_lock, _) = setup();
+    let user = random_principal();
+
+    issue_memberships(principals(&[user]));
+
+    let (status, body) = get(format!("/is_member/{}", user.to_text()).as_str());
+    assert_eq!(status, 200);
+    assert!(body.contains("\"status\":\"pending\""));
+
+    set_caller(user);
+    accept_membership();
+
+    let (status, body) = get("/members?limit=1");
+    assert_eq!(status, 200);
+    assert!(body.contains(user.to_text().as_str()));
+
+    assert_eq!(get("/is_member/invalid").0, 400);
+    assert_eq!(get("/unknown").0, 404);
+
+    let response = http_request(HttpRequest {
+        method: String::from("POST"),
+        url: String::from("/members"),
+        headers: vec![],
+        body: vec![],
+    });
+    assert_eq!(response.status_code, 405);
+}
+
+#[test]
+fn event_log_works_fine() {
+    let (_lock, _) = setup();
+    let (user_1, user_2) = (random_principal(), random_principal());
+
+    issue_memberships(principals(&[user_1, user_2]));
+
+    set_caller(user_1);
+    accept_membership();
+
+    let response = get_events(GetEventsRequest {
+        from_seq: 0,
+        limit: 10,
+        topic_filter: None,
+    });
+    assert_eq!(response.events.len(), 3);
+    assert_eq!(response.oldest_seq, 0);
+    assert_eq!(response.next_seq, 3);
+
+    let response = get_events(GetEventsRequest {
+        from_seq: 0,
+        limit: 10,
+        topic_filter: Some(EventTopicFilter {
+            name: None,
+            principal: Some(user_1),
+        }),
+    });
+    assert_eq!(response.events.len(), 2);
+}
+
+#[test]
+fn event_archive_works_fine() {
+    let (_lock, _) = setup();
+    let archive = random_principal();
+
+    set_event_archive(SetEventArchiveRequest {
+        archive: Some(archive),
+    });
+    get_token().event_log.capacity = 1;
+
+    let issue = || issue_memberships(principals(&[random_principal()]));
+
+    // the archive is unavailable, so the evicted events stay in the queue
+    get_env().archive_accepts = false;
+    for _ in 0..=ARCHIVE_BATCH_SIZE {
+        issue();
+    }
+
+    assert!(get_env().archived_events.is_empty());
+    assert_eq!(
+        get_token().event_log.archive_queue.len(),
+        ARCHIVE_BATCH_SIZE
+    );
+
+    get_env().archive_accepts = true;
+    issue();
+
+    let (archived_to, batch) = &get_env().archived_events[0];
+    assert_eq!(*archived_to, archive);
+    assert_eq!(batch.len(), ARCHIVE_BATCH_SIZE);
+    assert_eq!(get_token().event_log.archive_queue.len(), 1);
+}
+
+#[test]
+fn guards_work_fine() {
+    let (_lock, controller) = setup();
+
+    assert!(issue_guard().is_ok());
+    assert!(revoke_guard().is_ok());
+    assert!(admin_guard().is_ok());
+    assert!(event_listeners_guard().is_ok());
+
+    set_caller(random_principal());
+
+    assert!(issue_guard().is_err());
+    assert!(revoke_guard().is_err());
+    assert!(admin_guard().is_err());
+    assert!(event_listeners_guard().is_err());
+
+    set_caller(controller);
+    update_issue_controller(UpdateControllerRequest {
+        new_controllers: vec![],
+        renounce: Some(true),
+    });
+    assert!(issue_guard().is_err());
+}
@@ -1,41 +1,22 @@
-use ic_cdk::caller;
+use membership_token_client::types::{ADMIN_ROLE, EVENT_LISTENERS_ROLE, ISSUE_ROLE, REVOKE_ROLE};
 
+use crate::common::env::{get_env, Environment};
 use crate::get_token;
 
-pub fn issue_guard() -> Result<(), String> {
-    if get_token()
-        .controllers
-        .issue_controllers
-        .contains(&caller())
-    {
-        Ok(())
-    } else {
-        Err(String::from("The caller is not the issue controller"))
-    }
+/// Generates a guard function that only lets through members of the given role
+macro_rules! role_guard {
+    ($name:ident, $role:expr) => {
+        pub fn $name() -> Result<(), String> {
+            if get_token().roles.has_role($role, &get_env().caller()) {
+                Ok(())
+            } else {
+                Err(format!("The caller is not the {} controller", $role))
+            }
+        }
+    };
 }
 
-pub fn revoke_guard() -> Result<(), String> {
-    if get_token()
-        .controllers
-        .revoke_controllers
-        .contains(&caller())
-    {
-        Ok(())
-    } else {
-        Err(String::from("The caller is not the revoke controller"))
-    }
-}
-
-pub fn event_listeners_guard() -> Result<(), String> {
-    if get_token()
-        .controllers
-        .event_listeners_controllers
-        .contains(&caller())
-    {
-        Ok(())
-    } else {
-        Err(String::from(
-            "The caller is not the event listeners controller",
-        ))
-    }
-}
+role_guard!(issue_guard, ISSUE_ROLE);
+role_guard!(revoke_guard, REVOKE_ROLE);
+role_guard!(event_listeners_guard, EVENT_LISTENERS_ROLE);
+role_guard!(admin_guard, ADMIN_ROLE);
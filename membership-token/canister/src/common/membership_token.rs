@@ -1,22 +1,46 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+use std::ops::Bound::{Excluded, Unbounded};
 
 use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
 
-use membership_token_client::types::{ControllerList, Controllers, Error};
+use membership_token_client::types::{Error, MemberStatus, ProposalAction};
+use token_common::cycles::CyclesMonitor;
+use token_common::event_log::EventLog;
+use token_common::handover::HandoverRegistry;
+use token_common::metrics::Metrics;
+use token_common::proposals::ProposalRegistry;
+use token_common::roles::RoleRegistry;
+use token_common::timelock::ControllersTimelock;
+
+pub const MAX_MEMBERS_PAGE_SIZE: usize = 1000;
+pub const MAX_BATCH_QUERY_SIZE: usize = 1000;
 
 #[derive(CandidType, Deserialize)]
 pub struct MembershipToken {
     pub pending_members: HashSet<Principal>,
-    pub members: HashSet<Principal>,
-    pub controllers: ControllerList,
+    /// Ordered, so members could be paginated without sorting them
+    pub members: BTreeSet<Principal>,
+    pub roles: RoleRegistry,
+    pub proposals: ProposalRegistry<ProposalAction>,
+    pub timelock: ControllersTimelock,
+    pub handovers: HandoverRegistry,
+    pub event_log: EventLog,
+    pub cycles: CyclesMonitor,
+    pub metrics: Metrics,
 }
 
 impl MembershipToken {
-    pub fn new(controllers: ControllerList) -> MembershipToken {
+    pub fn new(roles: RoleRegistry) -> MembershipToken {
         MembershipToken {
             pending_members: HashSet::new(),
-            members: HashSet::new(),
-            controllers,
+            members: BTreeSet::new(),
+            roles,
+            proposals: ProposalRegistry::default(),
+            timelock: ControllersTimelock::default(),
+            handovers: HandoverRegistry::default(),
+            event_log: EventLog::default(),
+            cycles: CyclesMonitor::default(),
+            metrics: Metrics::default(),
         }
     }
 
@@ -71,38 +95,45 @@ impl MembershipToken {
         self.members.len()
     }
 
-    pub fn update_issue_controllers(&mut self, new_issue_controllers: Controllers) -> Controllers {
-        let old_controllers = self.controllers.issue_controllers.clone();
-        self.controllers.issue_controllers = new_issue_controllers;
+    pub fn is_member(&self, holder: &Principal) -> bool {
+        self.members.contains(holder)
+    }
+    pub fn is_pending_member(&self, holder: &Principal) -> bool {
+        self.pending_members.contains(holder)
+    }
 
-        old_controllers
+    pub fn get_member_status(&self, holder: &Principal) -> MemberStatus {
+        if self.is_member(holder) {
+            MemberStatus::Member
+        } else if self.is_pending_member(holder) {
+            MemberStatus::Pending
+        } else {
+            MemberStatus::NotMember
+        }
     }
 
-    pub fn update_revoke_controllers(
-        &mut self,
-        new_revoke_controllers: Controllers,
-    ) -> Controllers {
-        let old_controllers = self.controllers.revoke_controllers.clone();
-        self.controllers.revoke_controllers = new_revoke_controllers;
+    /// Returns at most `limit` members ordered by principal, starting right after `cursor`,
+    /// together with the cursor of the next page (if there is one).
+    pub fn get_members(
+        &self,
+        cursor: Option<Principal>,
+        limit: usize,
+    ) -> (Vec<Principal>, Option<Principal>) {
+        let limit = limit.min(MAX_MEMBERS_PAGE_SIZE);
 
-        old_controllers
-    }
+        let mut iter = match cursor {
+            Some(c) => self.members.range((Excluded(c), Unbounded)),
+            None => self.members.range(..),
+        };
 
-    pub fn update_event_listeners_controllers(
-        &mut self,
-        new_event_listeners_controllers: Controllers,
-    ) -> Controllers {
-        let old_controller = self.controllers.event_listeners_controllers.clone();
-        self.controllers.event_listeners_controllers = new_event_listeners_controllers;
+        let members = iter.by_ref().take(limit).cloned().collect::<Vec<_>>();
 
-        old_controller
-    }
+        let next_cursor = match iter.next() {
+            Some(_) => members.last().cloned(),
+            None => None,
+        };
 
-    pub fn is_member(&self, holder: &Principal) -> bool {
-        self.members.contains(holder)
-    }
-    pub fn is_pending_member(&self, holder: &Principal) -> bool {
-        self.pending_members.contains(holder)
+        (members, next_cursor)
     }
 }
 
@@ -111,14 +142,16 @@ mod tests {
     use ic_cdk::export::candid::Principal;
     use union_utils::random_principal_test;
 
-    use antifragile_membership_token_client::events::MembershipStatus;
-    use antifragile_membership_token_client::types::ControllerList;
+    use membership_token_client::types::{
+        DEFAULT_ROLES, EVENT_LISTENERS_ROLE, ISSUE_ROLE, REVOKE_ROLE,
+    };
+    use token_common::roles::RoleRegistry;
 
     use crate::common::membership_token::MembershipToken;
 
     fn create_test_token() -> (MembershipToken, Principal) {
         let controller = random_principal_test();
-        let token = MembershipToken::new(ControllerList::single(Some(controller)));
+        let token = MembershipToken::new(RoleRegistry::new(&DEFAULT_ROLES, &[controller]));
 
         (token, controller)
     }
@@ -130,12 +163,9 @@ mod tests {
         assert!(token.members.is_empty());
         assert!(token.pending_members.is_empty());
 
-        assert!(token.controllers.issue_controllers.contains(&controller));
-        assert!(token.controllers.revoke_controllers.contains(&controller));
-        assert!(token
-            .controllers
-            .event_listeners_controllers
-            .contains(&controller));
+        assert!(token.roles.has_role(ISSUE_ROLE, &controller));
+        assert!(token.roles.has_role(REVOKE_ROLE, &controller));
+        assert!(token.roles.has_role(EVENT_LISTENERS_ROLE, &controller));
     }
 
     #[test]
@@ -143,49 +173,44 @@ mod tests {
         let (mut token, _) = create_test_token();
         let user_1 = random_principal_test();
 
-        let event_1 = token.issue_membership(user_1).ok().unwrap();
+        token.issue_membership(user_1).ok().unwrap();
 
         assert!(!token.is_member(&user_1));
         assert!(token.is_pending_member(&user_1));
         assert!(token.members.is_empty());
         assert_eq!(token.pending_members.len(), 1);
 
-        assert_eq!(event_1.member, user_1);
-        assert_eq!(event_1.new_status as u8, MembershipStatus::Issued as u8);
+        token.issue_membership(user_1).err().unwrap();
 
-        let events_2 = token.accept_membership(user_1).ok().unwrap();
+        token.accept_membership(user_1).ok().unwrap();
 
         assert!(token.is_member(&user_1));
         assert!(!token.is_pending_member(&user_1));
         assert!(token.pending_members.is_empty());
-        assert_eq!(token.members.len(), 1);
-
-        let (event_2_m, event_2_t, event_2_v) = events_2;
-
-        assert_eq!(event_2_m.member, user_1);
-        assert_eq!(event_2_m.new_status as u8, MembershipStatus::Accepted as u8);
-
-        assert_eq!(event_2_t.new_total_voting_power, 1);
+        assert_eq!(token.get_total_members(), 1);
 
-        assert_eq!(event_2_v.voter, user_1);
-        assert_eq!(event_2_v.new_voting_power, 1);
+        token.accept_membership(user_1).err().unwrap();
 
-        let events_3 = token.revoke_membership(user_1).ok().unwrap();
+        token.revoke_membership(user_1).ok().unwrap();
 
         assert!(!token.is_member(&user_1));
         assert!(!token.is_pending_member(&user_1));
         assert!(token.members.is_empty());
         assert!(token.pending_members.is_empty());
+    }
 
-        let (event_3_m, event_3_t, event_3_v) = events_3;
+    #[test]
+    fn decline_works_fine() {
+        let (mut token, _) = create_test_token();
+        let user_1 = random_principal_test();
 
-        assert_eq!(event_3_m.member, user_1);
-        assert_eq!(event_3_m.new_status as u8, MembershipStatus::Revoked as u8);
+        token.decline_membership(user_1).err().unwrap();
 
-        assert_eq!(event_3_t.new_total_voting_power, 0);
+        token.issue_membership(user_1).ok().unwrap();
+        token.decline_membership(user_1).ok().unwrap();
 
-        assert_eq!(event_3_v.voter, user_1);
-        assert_eq!(event_3_v.new_voting_power, 0);
+        assert!(!token.is_pending_member(&user_1));
+        token.accept_membership(user_1).err().unwrap();
     }
 
     #[test]
@@ -194,16 +219,16 @@ mod tests {
         let user_1 = random_principal_test();
         let user_2 = random_principal_test();
 
-        token.issue_membership(user_1);
-        token.accept_membership(user_1);
+        token.issue_membership(user_1).ok().unwrap();
+        token.accept_membership(user_1).ok().unwrap();
 
         token.revoke_membership(user_2).err().unwrap();
 
-        token.issue_membership(user_2);
+        token.issue_membership(user_2).ok().unwrap();
 
         token.revoke_membership(user_2).err().unwrap();
 
-        token.accept_membership(user_2);
+        token.accept_membership(user_2).ok().unwrap();
 
         token.revoke_membership(user_2).ok().unwrap();
     }
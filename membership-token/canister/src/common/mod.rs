@@ -1,2 +1,6 @@
+pub mod env;
 pub mod guards;
 pub mod membership_token;
+#[cfg(test)]
+mod state_machine_tests;
+pub mod types;
@@ -0,0 +1,135 @@
+//! Randomized state-machine tests: generated sequences of operations are applied both to
+//! `MembershipToken` and to a trivial reference model, which are compared after every step.
+
+use std::collections::BTreeMap;
+
+use ic_cdk::export::candid::Principal;
+use proptest::prelude::*;
+
+use membership_token_client::types::{MemberStatus, DEFAULT_ROLES};
+use token_common::roles::RoleRegistry;
+
+use crate::common::membership_token::MembershipToken;
+
+/// Principals are picked from a small pool, so that operations often hit the same principals
+const PRINCIPALS: u8 = 6;
+const MEMBERS_PAGE_SIZE: usize = 2;
+
+fn principal(idx: u8) -> Principal {
+    Principal::from_slice(&[idx, 1])
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Issue(u8),
+    Accept(u8),
+    Decline(u8),
+    Revoke(u8),
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (0..PRINCIPALS).prop_map(Op::Issue),
+        (0..PRINCIPALS).prop_map(Op::Accept),
+        (0..PRINCIPALS).prop_map(Op::Decline),
+        (0..PRINCIPALS).prop_map(Op::Revoke),
+    ]
+}
+
+/// Principals absent from the model are not members
+#[derive(Default)]
+struct Model {
+    statuses: BTreeMap<u8, MemberStatus>,
+}
+
+impl Model {
+    fn status(&self, idx: u8) -> MemberStatus {
+        self.statuses
+            .get(&idx)
+            .cloned()
+            .unwrap_or(MemberStatus::NotMember)
+    }
+
+    /// Returns whether the operation should succeed
+    fn apply(&mut self, op: &Op) -> bool {
+        let (idx, from, to) = match *op {
+            Op::Issue(idx) => (idx, MemberStatus::NotMember, Some(MemberStatus::Pending)),
+            Op::Accept(idx) => (idx, MemberStatus::Pending, Some(MemberStatus::Member)),
+            Op::Decline(idx) => (idx, MemberStatus::Pending, None),
+            Op::Revoke(idx) => (idx, MemberStatus::Member, None),
+        };
+
+        if self.status(idx) != from {
+            return false;
+        }
+
+        match to {
+            Some(status) => self.statuses.insert(idx, status),
+            None => self.statuses.remove(&idx),
+        };
+
+        true
+    }
+}
+
+/// Returns whether the operation succeeded
+fn apply(token: &mut MembershipToken, op: &Op) -> bool {
+    match *op {
+        Op::Issue(idx) => token.issue_membership(principal(idx)).is_ok(),
+        Op::Accept(idx) => token.accept_membership(principal(idx)).is_ok(),
+        Op::Decline(idx) => token.decline_membership(principal(idx)).is_ok(),
+        Op::Revoke(idx) => token.revoke_membership(principal(idx)).is_ok(),
+    }
+}
+
+fn check_state(token: &MembershipToken, model: &Model) -> Result<(), TestCaseError> {
+    for idx in 0..PRINCIPALS {
+        let status = token.get_member_status(&principal(idx));
+
+        prop_assert_eq!(status, model.status(idx), "status of principal #{}", idx);
+    }
+
+    // invariants which hold regardless of the model
+    prop_assert!(token
+        .members
+        .iter()
+        .all(|it| !token.pending_members.contains(it)));
+    prop_assert_eq!(token.get_total_members(), token.members.len());
+
+    let mut paginated = vec![];
+    let mut cursor = None;
+
+    loop {
+        let (page, next_cursor) = token.get_members(cursor, MEMBERS_PAGE_SIZE);
+        prop_assert!(page.len() <= MEMBERS_PAGE_SIZE);
+
+        paginated.extend(page);
+
+        if next_cursor.is_none() {
+            break;
+        }
+        cursor = next_cursor;
+    }
+
+    let members = token.members.iter().cloned().collect::<Vec<_>>();
+
+    prop_assert_eq!(paginated, members);
+
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn membership_token_behaves_like_the_model(ops in prop::collection::vec(op(), 1..100)) {
+        let mut token = MembershipToken::new(RoleRegistry::new(&DEFAULT_ROLES, &[principal(0)]));
+        let mut model = Model::default();
+
+        for op in &ops {
+            let succeed = apply(&mut token, op);
+            let expected = model.apply(op);
+
+            prop_assert_eq!(succeed, expected, "unexpected outcome of {:?}", op);
+            check_state(&token, &model)?;
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use ic_cdk::export::candid::{CandidType, Deserialize};
+use ic_cron::u8_enum;
+
+use membership_token_client::types::ControllersUpdateId;
+
+u8_enum! {
+    pub enum CronTaskKind {
+        ControllersUpdate,
+    }
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ControllersUpdateTask {
+    pub update_id: ControllersUpdateId,
+}
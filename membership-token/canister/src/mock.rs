@@ -0,0 +1,421 @@
+//! In-memory membership token for native unit tests of canisters integrating with it. The mock
+//! runs the canister's own endpoints against `MockEnvironment`, so it behaves the same way the
+//! deployed token does: guards reject, traps roll the state back, queries never persist.
+//!
+//! Never enable the `mock` feature for a deployed canister, it replaces the system calls.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use async_trait::async_trait;
+use ic_cdk::api::call::{CallResult, RejectionCode};
+use ic_cdk::export::candid::{decode_one, encode_one, Principal};
+use ic_cron::types::TaskId;
+use union_utils::random_principal_test;
+
+use membership_token_client::api::MembershipTokenApi;
+use membership_token_client::types::{
+    ApproveProposalResponse, AreMembersRequest, AreMembersResponse, ControllersHandoverRequest,
+    ControllersUpdateDelay, ControllersUpdateIdRequest, GetApprovalThresholdsResponse,
+    GetControllersHandoversResponse, GetControllersResponse, GetCyclesBalanceResponse,
+    GetEventsRequest, GetEventsResponse, GetMetricsResponse, GetProposalResponse,
+    GetProposalsResponse, GetRoleMembersRequest, GetRoleMembersResponse,
+    GetScheduledControllersUpdatesResponse, GetTotalMembersResponse, HasRoleResponse, InitRequest,
+    IsMemberRequest, IsMemberResponse, IssueRevokeMembershipsRequest, ProposalIdRequest,
+    ProposeRequest, ProposeResponse, RoleRequest, SetApprovalThresholdRequest,
+    SetApprovalThresholdResponse, SetCyclesLowWaterMarkRequest, SetEventArchiveRequest,
+    SetRoleAdminRequest, SetRoleAdminResponse, UpdateControllerRequest, UpdateControllerResponse,
+};
+
+use crate::common::env::{get_env, set_env, MockEnvironment, ENV_LOCK};
+use crate::common::guards::{admin_guard, event_listeners_guard, issue_guard, revoke_guard};
+use crate::common::membership_token::MembershipToken;
+use crate::*;
+
+type Guard = fn() -> Result<(), String>;
+
+struct MockCanister {
+    state: MembershipToken,
+    env: MockEnvironment,
+}
+
+/// A membership token living in memory. Every instance has its own state, calls are executed
+/// one at a time on behalf of the current caller.
+pub struct MockMembershipToken {
+    canister: RefCell<MockCanister>,
+}
+
+impl MockMembershipToken {
+    /// Deploys the token on behalf of the controller
+    pub fn new(controller: Principal, request: InitRequest) -> Self {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        set_env(MockEnvironment {
+            caller: controller,
+            canister_id: random_principal_test(),
+            ..Default::default()
+        });
+        init(request);
+
+        MockMembershipToken {
+            canister: RefCell::new(MockCanister {
+                state: unsafe { STATE.take() }.unwrap(),
+                env: std::mem::take(get_env()),
+            }),
+        }
+    }
+
+    /// Sets the principal the following calls are made by
+    pub fn set_caller(&self, caller: Principal) {
+        self.canister.borrow_mut().env.caller = caller;
+    }
+
+    pub fn get_canister_id(&self) -> Principal {
+        self.canister.borrow().env.canister_id
+    }
+
+    pub fn set_time(&self, time: u64) {
+        self.canister.borrow_mut().env.time = time;
+    }
+
+    /// Names and candid-encoded bodies of the events emitted so far
+    pub fn get_emitted_events(&self) -> Vec<(String, Vec<u8>)> {
+        self.canister.borrow().env.emitted_events.clone()
+    }
+
+    /// Executes the scheduled task the same way the heartbeat does
+    pub fn fire_task(&self, task_id: TaskId) -> CallResult<()> {
+        self.update(None, move || {
+            let caller = get_env().caller;
+            get_env().caller = get_env().canister_id;
+
+            let task = get_env().fire_task(task_id).unwrap_or_else(|| {
+                get_env().trap(format!("Task {} is not scheduled", task_id).as_str())
+            });
+            _execute_cron_task(task.kind, &task);
+
+            get_env().caller = caller;
+        })
+    }
+
+    fn update<R, F: FnOnce() -> R>(&self, guard: Option<Guard>, endpoint: F) -> CallResult<R> {
+        self.execute(guard, endpoint, true)
+    }
+
+    fn query<R, F: FnOnce() -> R>(&self, endpoint: F) -> CallResult<R> {
+        self.execute(None, endpoint, false)
+    }
+
+    /// Installs a copy of this instance's state and environment, runs the endpoint and takes
+    /// the copy back only if the call succeeded and is allowed to persist
+    fn execute<R, F: FnOnce() -> R>(
+        &self,
+        guard: Option<Guard>,
+        endpoint: F,
+        persist: bool,
+    ) -> CallResult<R> {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut canister = self.canister.borrow_mut();
+
+        let state_copy = decode_one(&encode_one(&canister.state).unwrap()).unwrap();
+        unsafe {
+            STATE = Some(state_copy);
+        }
+        set_env(canister.env.clone());
+
+        let result = match guard.map_or(Ok(()), |guard| guard()) {
+            Ok(_) => catch_unwind(AssertUnwindSafe(endpoint))
+                .map_err(|e| (RejectionCode::CanisterError, panic_message(e))),
+            Err(msg) => Err((RejectionCode::CanisterReject, msg)),
+        };
+
+        let state = unsafe { STATE.take() }.unwrap();
+        let env = std::mem::take(get_env());
+
+        if result.is_ok() && persist {
+            canister.state = state;
+            canister.env = env;
+        }
+
+        result
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map(|msg| String::from(*msg))
+            .unwrap_or_default(),
+    }
+}
+
+#[async_trait(?Send)]
+impl MembershipTokenApi for MockMembershipToken {
+    async fn issue_memberships(&self, request: IssueRevokeMembershipsRequest) -> CallResult<()> {
+        self.update(Some(issue_guard), move || issue_memberships(request))
+    }
+
+    async fn revoke_memberships(&self, request: IssueRevokeMembershipsRequest) -> CallResult<()> {
+        self.update(Some(revoke_guard), move || revoke_memberships(request))
+    }
+
+    async fn accept_membership(&self) -> CallResult<()> {
+        self.update(None, accept_membership)
+    }
+
+    async fn decline_membership(&self) -> CallResult<()> {
+        self.update(None, decline_membership)
+    }
+
+    async fn is_member(&self, request: IsMemberRequest) -> CallResult<(IsMemberResponse,)> {
+        self.query(move || is_member(request)).map(|it| (it,))
+    }
+
+    async fn is_pending_member(&self, request: IsMemberRequest) -> CallResult<(IsMemberResponse,)> {
+        self.query(move || is_pending_member(request))
+            .map(|it| (it,))
+    }
+
+    async fn are_members(&self, request: AreMembersRequest) -> CallResult<(AreMembersResponse,)> {
+        self.query(move || are_members(request)).map(|it| (it,))
+    }
+
+    async fn get_total_members(&self) -> CallResult<(GetTotalMembersResponse,)> {
+        self.query(get_total_members).map(|it| (it,))
+    }
+
+    async fn update_issue_controller(
+        &self,
+        request: UpdateControllerRequest,
+    ) -> CallResult<(UpdateControllerResponse,)> {
+        self.update(Some(issue_guard), move || update_issue_controller(request))
+            .map(|it| (it,))
+    }
+
+    async fn update_revoke_controller(
+        &self,
+        request: UpdateControllerRequest,
+    ) -> CallResult<(UpdateControllerResponse,)> {
+        self.update(Some(revoke_guard), move || {
+            update_revoke_controller(request)
+        })
+        .map(|it| (it,))
+    }
+
+    async fn update_event_listeners_controller(
+        &self,
+        request: UpdateControllerRequest,
+    ) -> CallResult<(UpdateControllerResponse,)> {
+        self.update(Some(event_listeners_guard), move || {
+            update_event_listeners_controller(request)
+        })
+        .map(|it| (it,))
+    }
+
+    async fn accept_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()> {
+        self.update(None, move || accept_controllers_handover(request))
+    }
+
+    async fn cancel_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()> {
+        self.update(None, move || cancel_controllers_handover(request))
+    }
+
+    async fn get_controllers_handovers(&self) -> CallResult<(GetControllersHandoversResponse,)> {
+        self.query(get_controllers_handovers).map(|it| (it,))
+    }
+
+    async fn cancel_controllers_update(
+        &self,
+        request: ControllersUpdateIdRequest,
+    ) -> CallResult<()> {
+        self.update(None, move || cancel_controllers_update(request))
+    }
+
+    async fn get_scheduled_controllers_updates(
+        &self,
+    ) -> CallResult<(GetScheduledControllersUpdatesResponse,)> {
+        self.query(get_scheduled_controllers_updates)
+            .map(|it| (it,))
+    }
+
+    async fn set_controllers_update_delay(
+        &self,
+        request: ControllersUpdateDelay,
+    ) -> CallResult<()> {
+        self.update(Some(admin_guard), move || {
+            set_controllers_update_delay(request)
+        })
+    }
+
+    async fn get_controllers_update_delay(&self) -> CallResult<(ControllersUpdateDelay,)> {
+        self.query(get_controllers_update_delay).map(|it| (it,))
+    }
+
+    async fn get_controllers(&self) -> CallResult<(GetControllersResponse,)> {
+        self.query(get_controllers).map(|it| (it,))
+    }
+
+    async fn grant_role(&self, request: RoleRequest) -> CallResult<()> {
+        self.update(None, move || grant_role(request))
+    }
+
+    async fn revoke_role(&self, request: RoleRequest) -> CallResult<()> {
+        self.update(None, move || revoke_role(request))
+    }
+
+    async fn set_role_admin(
+        &self,
+        request: SetRoleAdminRequest,
+    ) -> CallResult<(SetRoleAdminResponse,)> {
+        self.update(None, move || set_role_admin(request))
+            .map(|it| (it,))
+    }
+
+    async fn has_role(&self, request: RoleRequest) -> CallResult<(HasRoleResponse,)> {
+        self.query(move || has_role(request)).map(|it| (it,))
+    }
+
+    async fn get_role_members(
+        &self,
+        request: GetRoleMembersRequest,
+    ) -> CallResult<(GetRoleMembersResponse,)> {
+        self.query(move || get_role_members(request))
+            .map(|it| (it,))
+    }
+
+    async fn set_approval_threshold(
+        &self,
+        request: SetApprovalThresholdRequest,
+    ) -> CallResult<(SetApprovalThresholdResponse,)> {
+        self.update(None, move || set_approval_threshold(request))
+            .map(|it| (it,))
+    }
+
+    async fn get_approval_thresholds(&self) -> CallResult<(GetApprovalThresholdsResponse,)> {
+        self.query(get_approval_thresholds).map(|it| (it,))
+    }
+
+    async fn propose(&self, request: ProposeRequest) -> CallResult<(ProposeResponse,)> {
+        self.update(None, move || propose(request)).map(|it| (it,))
+    }
+
+    async fn approve_proposal(
+        &self,
+        request: ProposalIdRequest,
+    ) -> CallResult<(ApproveProposalResponse,)> {
+        self.update(None, move || approve_proposal(request))
+            .map(|it| (it,))
+    }
+
+    async fn get_proposal(&self, request: ProposalIdRequest) -> CallResult<(GetProposalResponse,)> {
+        self.query(move || get_proposal(request)).map(|it| (it,))
+    }
+
+    async fn get_pending_proposals(&self) -> CallResult<(GetProposalsResponse,)> {
+        self.query(get_pending_proposals).map(|it| (it,))
+    }
+
+    async fn get_events(&self, request: GetEventsRequest) -> CallResult<(GetEventsResponse,)> {
+        self.query(move || get_events(request)).map(|it| (it,))
+    }
+
+    async fn set_event_archive(&self, request: SetEventArchiveRequest) -> CallResult<()> {
+        self.update(Some(admin_guard), move || set_event_archive(request))
+    }
+
+    async fn get_cycles_balance(&self) -> CallResult<(GetCyclesBalanceResponse,)> {
+        self.query(get_cycles_balance).map(|it| (it,))
+    }
+
+    async fn set_cycles_low_water_mark(
+        &self,
+        request: SetCyclesLowWaterMarkRequest,
+    ) -> CallResult<()> {
+        self.update(Some(admin_guard), move || {
+            set_cycles_low_water_mark(request)
+        })
+    }
+
+    async fn get_metrics(&self) -> CallResult<(GetMetricsResponse,)> {
+        self.query(get_metrics).map(|it| (it,))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use ic_cdk::api::call::RejectionCode;
+    use ic_cdk::export::candid::Principal;
+    use union_utils::random_principal_test;
+
+    use membership_token_client::api::MembershipTokenApi;
+    use membership_token_client::types::{
+        InitRequest, IsMemberRequest, IssueRevokeMembershipsRequest,
+    };
+
+    use crate::mock::MockMembershipToken;
+
+    fn is_member(token: &MockMembershipToken, prin: Principal) -> bool {
+        let (response,) = block_on(token.is_member(IsMemberRequest { prin })).unwrap();
+
+        response.is_member
+    }
+
+    #[test]
+    fn mock_token_works_fine() {
+        let controller = random_principal_test();
+        let user = random_principal_test();
+
+        let token = MockMembershipToken::new(
+            controller,
+            InitRequest {
+                default_controllers: None,
+                controllers_update_delay_nano: None,
+            },
+        );
+
+        token.set_caller(user);
+
+        let (code, _) = block_on(token.accept_membership()).unwrap_err();
+        assert!(
+            matches!(code, RejectionCode::CanisterError),
+            "traps should error"
+        );
+
+        let (code, _) = block_on(token.issue_memberships(IssueRevokeMembershipsRequest {
+            principals: vec![user],
+        }))
+        .unwrap_err();
+        assert!(
+            matches!(code, RejectionCode::CanisterReject),
+            "guards should reject"
+        );
+
+        token.set_caller(controller);
+        block_on(token.issue_memberships(IssueRevokeMembershipsRequest {
+            principals: vec![user],
+        }))
+        .unwrap();
+        assert!(!is_member(&token, user));
+
+        token.set_caller(user);
+        block_on(token.accept_membership()).unwrap();
+        assert!(is_member(&token, user));
+
+        let names: Vec<_> = token
+            .get_emitted_events()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names.last().unwrap(), "MembershipStatusUpdateEvent");
+    }
+}
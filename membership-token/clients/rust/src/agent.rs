@@ -0,0 +1,296 @@
+//! Off-chain client, calling the membership token through `ic-agent` from backend services and
+//! scripts. Updates are verified against the IC root key by the agent itself.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use garcon::Delay;
+use ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport;
+use ic_agent::{Agent, AgentError, Identity};
+use ic_cdk::api::call::{CallResult, RejectionCode};
+use ic_cdk::export::candid::utils::{ArgumentDecoder, ArgumentEncoder};
+use ic_cdk::export::candid::{decode_args, encode_args, Principal};
+
+use crate::api::MembershipTokenApi;
+use crate::types::{
+    ApproveProposalResponse, AreMembersRequest, AreMembersResponse, ControllersHandoverRequest,
+    ControllersUpdateDelay, ControllersUpdateIdRequest, GetApprovalThresholdsResponse,
+    GetControllersHandoversResponse, GetControllersResponse, GetCyclesBalanceResponse,
+    GetEventsRequest, GetEventsResponse, GetMetricsResponse, GetProposalResponse,
+    GetProposalsResponse, GetRoleMembersRequest, GetRoleMembersResponse,
+    GetScheduledControllersUpdatesResponse, GetTotalMembersResponse, HasRoleResponse,
+    IsMemberRequest, IsMemberResponse, IssueRevokeMembershipsRequest, ProposalIdRequest,
+    ProposeRequest, ProposeResponse, RoleRequest, SetApprovalThresholdRequest,
+    SetApprovalThresholdResponse, SetCyclesLowWaterMarkRequest, SetEventArchiveRequest,
+    SetRoleAdminRequest, SetRoleAdminResponse, UpdateControllerRequest, UpdateControllerResponse,
+};
+
+const UPDATE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Creates an agent talking to the replica at `url` on behalf of `identity`. The root key should
+/// only be fetched from local replicas - doing so for the mainnet disables certificate checks.
+pub async fn create_agent<I: Identity + Send + Sync + 'static>(
+    url: &str,
+    identity: I,
+    fetch_root_key: bool,
+) -> Result<Agent, AgentError> {
+    let agent = Agent::builder()
+        .with_transport(ReqwestHttpReplicaV2Transport::create(url)?)
+        .with_identity(identity)
+        .build()?;
+
+    if fetch_root_key {
+        agent.fetch_root_key().await?;
+    }
+
+    Ok(agent)
+}
+
+/// Client struct for interacting with the token from outside of the IC
+pub struct MembershipTokenAgentClient {
+    pub agent: Agent,
+    pub canister_id: Principal,
+}
+
+impl MembershipTokenAgentClient {
+    #[inline(always)]
+    pub fn new(agent: Agent, canister_id: Principal) -> Self {
+        MembershipTokenAgentClient { agent, canister_id }
+    }
+
+    async fn update<T: ArgumentEncoder, R: for<'a> ArgumentDecoder<'a>>(
+        &self,
+        method: &str,
+        args: T,
+    ) -> CallResult<R> {
+        let waiter = Delay::builder()
+            .throttle(UPDATE_POLL_INTERVAL)
+            .timeout(UPDATE_TIMEOUT)
+            .build();
+
+        let response = self
+            .agent
+            .update(&self.canister_id, method)
+            .with_arg(encode_args(args).map_err(encoding_error)?)
+            .call_and_wait(waiter)
+            .await
+            .map_err(agent_error)?;
+
+        decode_args(&response).map_err(encoding_error)
+    }
+
+    async fn query<T: ArgumentEncoder, R: for<'a> ArgumentDecoder<'a>>(
+        &self,
+        method: &str,
+        args: T,
+    ) -> CallResult<R> {
+        let response = self
+            .agent
+            .query(&self.canister_id, method)
+            .with_arg(encode_args(args).map_err(encoding_error)?)
+            .call()
+            .await
+            .map_err(agent_error)?;
+
+        decode_args(&response).map_err(encoding_error)
+    }
+}
+
+/// Keeps the canister's reject code, so errors look the same as the ones of the on-chain client
+fn agent_error(e: AgentError) -> (RejectionCode, String) {
+    match e {
+        AgentError::ReplicaError {
+            reject_code,
+            reject_message,
+        } => (RejectionCode::from(reject_code as i32), reject_message),
+        e => (RejectionCode::Unknown, e.to_string()),
+    }
+}
+
+fn encoding_error<E: ToString>(e: E) -> (RejectionCode, String) {
+    (RejectionCode::CanisterError, e.to_string())
+}
+
+#[async_trait(?Send)]
+impl MembershipTokenApi for MembershipTokenAgentClient {
+    async fn issue_memberships(&self, request: IssueRevokeMembershipsRequest) -> CallResult<()> {
+        self.update("issue_memberships", (request,)).await
+    }
+
+    async fn revoke_memberships(&self, request: IssueRevokeMembershipsRequest) -> CallResult<()> {
+        self.update("revoke_memberships", (request,)).await
+    }
+
+    async fn accept_membership(&self) -> CallResult<()> {
+        self.update("accept_membership", ()).await
+    }
+
+    async fn decline_membership(&self) -> CallResult<()> {
+        self.update("decline_membership", ()).await
+    }
+
+    async fn is_member(&self, request: IsMemberRequest) -> CallResult<(IsMemberResponse,)> {
+        self.query("is_member", (request,)).await
+    }
+
+    async fn is_pending_member(&self, request: IsMemberRequest) -> CallResult<(IsMemberResponse,)> {
+        self.query("is_pending_member", (request,)).await
+    }
+
+    async fn are_members(&self, request: AreMembersRequest) -> CallResult<(AreMembersResponse,)> {
+        self.query("are_members", (request,)).await
+    }
+
+    async fn get_total_members(&self) -> CallResult<(GetTotalMembersResponse,)> {
+        self.query("get_total_members", ()).await
+    }
+
+    async fn update_issue_controller(
+        &self,
+        request: UpdateControllerRequest,
+    ) -> CallResult<(UpdateControllerResponse,)> {
+        self.update("update_issue_controller", (request,)).await
+    }
+
+    async fn update_revoke_controller(
+        &self,
+        request: UpdateControllerRequest,
+    ) -> CallResult<(UpdateControllerResponse,)> {
+        self.update("update_revoke_controller", (request,)).await
+    }
+
+    async fn update_event_listeners_controller(
+        &self,
+        request: UpdateControllerRequest,
+    ) -> CallResult<(UpdateControllerResponse,)> {
+        self.update("update_event_listeners_controller", (request,))
+            .await
+    }
+
+    async fn accept_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()> {
+        self.update("accept_controllers_handover", (request,)).await
+    }
+
+    async fn cancel_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()> {
+        self.update("cancel_controllers_handover", (request,)).await
+    }
+
+    async fn get_controllers_handovers(&self) -> CallResult<(GetControllersHandoversResponse,)> {
+        self.query("get_controllers_handovers", ()).await
+    }
+
+    async fn cancel_controllers_update(
+        &self,
+        request: ControllersUpdateIdRequest,
+    ) -> CallResult<()> {
+        self.update("cancel_controllers_update", (request,)).await
+    }
+
+    async fn get_scheduled_controllers_updates(
+        &self,
+    ) -> CallResult<(GetScheduledControllersUpdatesResponse,)> {
+        self.query("get_scheduled_controllers_updates", ()).await
+    }
+
+    async fn set_controllers_update_delay(
+        &self,
+        request: ControllersUpdateDelay,
+    ) -> CallResult<()> {
+        self.update("set_controllers_update_delay", (request,))
+            .await
+    }
+
+    async fn get_controllers_update_delay(&self) -> CallResult<(ControllersUpdateDelay,)> {
+        self.query("get_controllers_update_delay", ()).await
+    }
+
+    async fn get_controllers(&self) -> CallResult<(GetControllersResponse,)> {
+        self.query("get_controllers", ()).await
+    }
+
+    async fn grant_role(&self, request: RoleRequest) -> CallResult<()> {
+        self.update("grant_role", (request,)).await
+    }
+
+    async fn revoke_role(&self, request: RoleRequest) -> CallResult<()> {
+        self.update("revoke_role", (request,)).await
+    }
+
+    async fn set_role_admin(
+        &self,
+        request: SetRoleAdminRequest,
+    ) -> CallResult<(SetRoleAdminResponse,)> {
+        self.update("set_role_admin", (request,)).await
+    }
+
+    async fn has_role(&self, request: RoleRequest) -> CallResult<(HasRoleResponse,)> {
+        self.query("has_role", (request,)).await
+    }
+
+    async fn get_role_members(
+        &self,
+        request: GetRoleMembersRequest,
+    ) -> CallResult<(GetRoleMembersResponse,)> {
+        self.query("get_role_members", (request,)).await
+    }
+
+    async fn set_approval_threshold(
+        &self,
+        request: SetApprovalThresholdRequest,
+    ) -> CallResult<(SetApprovalThresholdResponse,)> {
+        self.update("set_approval_threshold", (request,)).await
+    }
+
+    async fn get_approval_thresholds(&self) -> CallResult<(GetApprovalThresholdsResponse,)> {
+        self.query("get_approval_thresholds", ()).await
+    }
+
+    async fn propose(&self, request: ProposeRequest) -> CallResult<(ProposeResponse,)> {
+        self.update("propose", (request,)).await
+    }
+
+    async fn approve_proposal(
+        &self,
+        request: ProposalIdRequest,
+    ) -> CallResult<(ApproveProposalResponse,)> {
+        self.update("approve_proposal", (request,)).await
+    }
+
+    async fn get_proposal(&self, request: ProposalIdRequest) -> CallResult<(GetProposalResponse,)> {
+        self.query("get_proposal", (request,)).await
+    }
+
+    async fn get_pending_proposals(&self) -> CallResult<(GetProposalsResponse,)> {
+        self.query("get_pending_proposals", ()).await
+    }
+
+    async fn get_events(&self, request: GetEventsRequest) -> CallResult<(GetEventsResponse,)> {
+        self.query("get_events", (request,)).await
+    }
+
+    async fn set_event_archive(&self, request: SetEventArchiveRequest) -> CallResult<()> {
+        self.update("set_event_archive", (request,)).await
+    }
+
+    async fn get_cycles_balance(&self) -> CallResult<(GetCyclesBalanceResponse,)> {
+        self.query("get_cycles_balance", ()).await
+    }
+
+    async fn set_cycles_low_water_mark(
+        &self,
+        request: SetCyclesLowWaterMarkRequest,
+    ) -> CallResult<()> {
+        self.update("set_cycles_low_water_mark", (request,)).await
+    }
+
+    async fn get_metrics(&self) -> CallResult<(GetMetricsResponse,)> {
+        self.query("get_metrics", ()).await
+    }
+}
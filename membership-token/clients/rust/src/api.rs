@@ -1,84 +1,192 @@
+use async_trait::async_trait;
 use ic_cdk::api::call::CallResult;
 use ic_cdk::call;
 use ic_cdk::export::candid::Principal;
 
 use crate::types::{
-    GetControllersResponse, GetTotalMembersResponse, IsMemberRequest, IsMemberResponse,
-    IssueRevokeMembershipsRequest, UpdateControllerRequest, UpdateControllerResponse,
+    ApproveProposalResponse, AreMembersRequest, AreMembersResponse, ControllersHandoverRequest,
+    ControllersUpdateDelay, ControllersUpdateIdRequest, GetApprovalThresholdsResponse,
+    GetControllersHandoversResponse, GetControllersResponse, GetCyclesBalanceResponse,
+    GetEventsRequest, GetEventsResponse, GetMetricsResponse, GetProposalResponse,
+    GetProposalsResponse, GetRoleMembersRequest, GetRoleMembersResponse,
+    GetScheduledControllersUpdatesResponse, GetTotalMembersResponse, HasRoleResponse,
+    IsMemberRequest, IsMemberResponse, IssueRevokeMembershipsRequest, ProposalIdRequest,
+    ProposeRequest, ProposeResponse, RoleRequest, SetApprovalThresholdRequest,
+    SetApprovalThresholdResponse, SetCyclesLowWaterMarkRequest, SetEventArchiveRequest,
+    SetRoleAdminRequest, SetRoleAdminResponse, UpdateControllerRequest, UpdateControllerResponse,
 };
 
 pub struct MembershipTokenClient {
     pub canister_id: Principal,
 }
 
+/// Membership token interface. Code written against it could be unit-tested natively with the
+/// in-memory mock from the `mock` feature of the `membership-token` crate.
+#[async_trait(?Send)]
+pub trait MembershipTokenApi {
+    async fn issue_memberships(&self, request: IssueRevokeMembershipsRequest) -> CallResult<()>;
+
+    async fn revoke_memberships(&self, request: IssueRevokeMembershipsRequest) -> CallResult<()>;
+
+    async fn accept_membership(&self) -> CallResult<()>;
+
+    async fn decline_membership(&self) -> CallResult<()>;
+
+    async fn is_member(&self, request: IsMemberRequest) -> CallResult<(IsMemberResponse,)>;
+
+    async fn is_pending_member(&self, request: IsMemberRequest) -> CallResult<(IsMemberResponse,)>;
+
+    async fn are_members(&self, request: AreMembersRequest) -> CallResult<(AreMembersResponse,)>;
+
+    async fn get_total_members(&self) -> CallResult<(GetTotalMembersResponse,)>;
+
+    async fn update_issue_controller(
+        &self,
+        request: UpdateControllerRequest,
+    ) -> CallResult<(UpdateControllerResponse,)>;
+
+    async fn update_revoke_controller(
+        &self,
+        request: UpdateControllerRequest,
+    ) -> CallResult<(UpdateControllerResponse,)>;
+
+    async fn update_event_listeners_controller(
+        &self,
+        request: UpdateControllerRequest,
+    ) -> CallResult<(UpdateControllerResponse,)>;
+
+    async fn accept_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()>;
+
+    async fn cancel_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()>;
+
+    async fn get_controllers_handovers(&self) -> CallResult<(GetControllersHandoversResponse,)>;
+
+    async fn cancel_controllers_update(
+        &self,
+        request: ControllersUpdateIdRequest,
+    ) -> CallResult<()>;
+
+    async fn get_scheduled_controllers_updates(
+        &self,
+    ) -> CallResult<(GetScheduledControllersUpdatesResponse,)>;
+
+    async fn set_controllers_update_delay(&self, request: ControllersUpdateDelay)
+        -> CallResult<()>;
+
+    async fn get_controllers_update_delay(&self) -> CallResult<(ControllersUpdateDelay,)>;
+
+    async fn get_controllers(&self) -> CallResult<(GetControllersResponse,)>;
+
+    async fn grant_role(&self, request: RoleRequest) -> CallResult<()>;
+
+    async fn revoke_role(&self, request: RoleRequest) -> CallResult<()>;
+
+    async fn set_role_admin(
+        &self,
+        request: SetRoleAdminRequest,
+    ) -> CallResult<(SetRoleAdminResponse,)>;
+
+    async fn has_role(&self, request: RoleRequest) -> CallResult<(HasRoleResponse,)>;
+
+    async fn get_role_members(
+        &self,
+        request: GetRoleMembersRequest,
+    ) -> CallResult<(GetRoleMembersResponse,)>;
+
+    async fn set_approval_threshold(
+        &self,
+        request: SetApprovalThresholdRequest,
+    ) -> CallResult<(SetApprovalThresholdResponse,)>;
+
+    async fn get_approval_thresholds(&self) -> CallResult<(GetApprovalThresholdsResponse,)>;
+
+    async fn propose(&self, request: ProposeRequest) -> CallResult<(ProposeResponse,)>;
+
+    async fn approve_proposal(
+        &self,
+        request: ProposalIdRequest,
+    ) -> CallResult<(ApproveProposalResponse,)>;
+
+    async fn get_proposal(&self, request: ProposalIdRequest) -> CallResult<(GetProposalResponse,)>;
+
+    async fn get_pending_proposals(&self) -> CallResult<(GetProposalsResponse,)>;
+
+    async fn get_events(&self, request: GetEventsRequest) -> CallResult<(GetEventsResponse,)>;
+
+    async fn set_event_archive(&self, request: SetEventArchiveRequest) -> CallResult<()>;
+
+    async fn get_cycles_balance(&self) -> CallResult<(GetCyclesBalanceResponse,)>;
+
+    async fn set_cycles_low_water_mark(
+        &self,
+        request: SetCyclesLowWaterMarkRequest,
+    ) -> CallResult<()>;
+
+    async fn get_metrics(&self) -> CallResult<(GetMetricsResponse,)>;
+}
+
 impl MembershipTokenClient {
     #[inline(always)]
     pub fn new(canister_id: Principal) -> Self {
         MembershipTokenClient { canister_id }
     }
+}
 
-    #[inline(always)]
-    pub async fn issue_memberships(
-        &self,
-        request: IssueRevokeMembershipsRequest,
-    ) -> CallResult<()> {
+#[async_trait(?Send)]
+impl MembershipTokenApi for MembershipTokenClient {
+    async fn issue_memberships(&self, request: IssueRevokeMembershipsRequest) -> CallResult<()> {
         call(self.canister_id, "issue_memberships", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn revoke_memberships(
-        &self,
-        request: IssueRevokeMembershipsRequest,
-    ) -> CallResult<()> {
+    async fn revoke_memberships(&self, request: IssueRevokeMembershipsRequest) -> CallResult<()> {
         call(self.canister_id, "revoke_memberships", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn accept_membership(&self) -> CallResult<()> {
+    async fn accept_membership(&self) -> CallResult<()> {
         call(self.canister_id, "accept_membership", ()).await
     }
 
-    #[inline(always)]
-    pub async fn decline_membership(&self) -> CallResult<()> {
+    async fn decline_membership(&self) -> CallResult<()> {
         call(self.canister_id, "decline_membership", ()).await
     }
 
-    #[inline(always)]
-    pub async fn is_member(&self, request: IsMemberRequest) -> CallResult<(IsMemberResponse,)> {
+    async fn is_member(&self, request: IsMemberRequest) -> CallResult<(IsMemberResponse,)> {
         call(self.canister_id, "is_member", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn is_pending_member(
-        &self,
-        request: IsMemberRequest,
-    ) -> CallResult<(IsMemberResponse,)> {
+    async fn is_pending_member(&self, request: IsMemberRequest) -> CallResult<(IsMemberResponse,)> {
         call(self.canister_id, "is_pending_member", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn get_total_members(&self) -> CallResult<(GetTotalMembersResponse,)> {
+    async fn are_members(&self, request: AreMembersRequest) -> CallResult<(AreMembersResponse,)> {
+        call(self.canister_id, "are_members", (request,)).await
+    }
+
+    async fn get_total_members(&self) -> CallResult<(GetTotalMembersResponse,)> {
         call(self.canister_id, "get_total_members", ()).await
     }
 
-    #[inline(always)]
-    pub async fn update_issue_controller(
+    async fn update_issue_controller(
         &self,
         request: UpdateControllerRequest,
     ) -> CallResult<(UpdateControllerResponse,)> {
         call(self.canister_id, "update_issue_controller", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn update_revoke_controller(
+    async fn update_revoke_controller(
         &self,
         request: UpdateControllerRequest,
     ) -> CallResult<(UpdateControllerResponse,)> {
         call(self.canister_id, "update_revoke_controller", (request,)).await
     }
 
-    #[inline(always)]
-    pub async fn update_event_listeners_controller(
+    async fn update_event_listeners_controller(
         &self,
         request: UpdateControllerRequest,
     ) -> CallResult<(UpdateControllerResponse,)> {
@@ -90,8 +198,128 @@ impl MembershipTokenClient {
         .await
     }
 
-    #[inline(always)]
-    pub async fn get_controllers(&self) -> CallResult<(GetControllersResponse,)> {
+    async fn accept_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()> {
+        call(self.canister_id, "accept_controllers_handover", (request,)).await
+    }
+
+    async fn cancel_controllers_handover(
+        &self,
+        request: ControllersHandoverRequest,
+    ) -> CallResult<()> {
+        call(self.canister_id, "cancel_controllers_handover", (request,)).await
+    }
+
+    async fn get_controllers_handovers(&self) -> CallResult<(GetControllersHandoversResponse,)> {
+        call(self.canister_id, "get_controllers_handovers", ()).await
+    }
+
+    async fn cancel_controllers_update(
+        &self,
+        request: ControllersUpdateIdRequest,
+    ) -> CallResult<()> {
+        call(self.canister_id, "cancel_controllers_update", (request,)).await
+    }
+
+    async fn get_scheduled_controllers_updates(
+        &self,
+    ) -> CallResult<(GetScheduledControllersUpdatesResponse,)> {
+        call(self.canister_id, "get_scheduled_controllers_updates", ()).await
+    }
+
+    async fn set_controllers_update_delay(
+        &self,
+        request: ControllersUpdateDelay,
+    ) -> CallResult<()> {
+        call(self.canister_id, "set_controllers_update_delay", (request,)).await
+    }
+
+    async fn get_controllers_update_delay(&self) -> CallResult<(ControllersUpdateDelay,)> {
+        call(self.canister_id, "get_controllers_update_delay", ()).await
+    }
+
+    async fn get_controllers(&self) -> CallResult<(GetControllersResponse,)> {
         call(self.canister_id, "get_controllers", ()).await
     }
+
+    async fn grant_role(&self, request: RoleRequest) -> CallResult<()> {
+        call(self.canister_id, "grant_role", (request,)).await
+    }
+
+    async fn revoke_role(&self, request: RoleRequest) -> CallResult<()> {
+        call(self.canister_id, "revoke_role", (request,)).await
+    }
+
+    async fn set_role_admin(
+        &self,
+        request: SetRoleAdminRequest,
+    ) -> CallResult<(SetRoleAdminResponse,)> {
+        call(self.canister_id, "set_role_admin", (request,)).await
+    }
+
+    async fn has_role(&self, request: RoleRequest) -> CallResult<(HasRoleResponse,)> {
+        call(self.canister_id, "has_role", (request,)).await
+    }
+
+    async fn get_role_members(
+        &self,
+        request: GetRoleMembersRequest,
+    ) -> CallResult<(GetRoleMembersResponse,)> {
+        call(self.canister_id, "get_role_members", (request,)).await
+    }
+
+    async fn set_approval_threshold(
+        &self,
+        request: SetApprovalThresholdRequest,
+    ) -> CallResult<(SetApprovalThresholdResponse,)> {
+        call(self.canister_id, "set_approval_threshold", (request,)).await
+    }
+
+    async fn get_approval_thresholds(&self) -> CallResult<(GetApprovalThresholdsResponse,)> {
+        call(self.canister_id, "get_approval_thresholds", ()).await
+    }
+
+    async fn propose(&self, request: ProposeRequest) -> CallResult<(ProposeResponse,)> {
+        call(self.canister_id, "propose", (request,)).await
+    }
+
+    async fn approve_proposal(
+        &self,
+        request: ProposalIdRequest,
+    ) -> CallResult<(ApproveProposalResponse,)> {
+        call(self.canister_id, "approve_proposal", (request,)).await
+    }
+
+    async fn get_proposal(&self, request: ProposalIdRequest) -> CallResult<(GetProposalResponse,)> {
+        call(self.canister_id, "get_proposal", (request,)).await
+    }
+
+    async fn get_pending_proposals(&self) -> CallResult<(GetProposalsResponse,)> {
+        call(self.canister_id, "get_pending_proposals", ()).await
+    }
+
+    async fn get_events(&self, request: GetEventsRequest) -> CallResult<(GetEventsResponse,)> {
+        call(self.canister_id, "get_events", (request,)).await
+    }
+
+    async fn set_event_archive(&self, request: SetEventArchiveRequest) -> CallResult<()> {
+        call(self.canister_id, "set_event_archive", (request,)).await
+    }
+
+    async fn get_cycles_balance(&self) -> CallResult<(GetCyclesBalanceResponse,)> {
+        call(self.canister_id, "get_cycles_balance", ()).await
+    }
+
+    async fn set_cycles_low_water_mark(
+        &self,
+        request: SetCyclesLowWaterMarkRequest,
+    ) -> CallResult<()> {
+        call(self.canister_id, "set_cycles_low_water_mark", (request,)).await
+    }
+
+    async fn get_metrics(&self) -> CallResult<(GetMetricsResponse,)> {
+        call(self.canister_id, "get_metrics", ()).await
+    }
 }
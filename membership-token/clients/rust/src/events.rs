@@ -1,7 +1,10 @@
 use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
 use ic_event_hub_macros::Event;
 
-use crate::types::Controllers;
+use crate::types::{
+    Controllers, ControllersChange, ControllersUpdateId, EventSeq, ProposalAction, ProposalId,
+    Role, ADMIN_ROLE, EVENT_LISTENERS_ROLE, ISSUE_ROLE, REVOKE_ROLE,
+};
 
 #[derive(Debug, CandidType, Deserialize)]
 pub enum MembershipStatus {
@@ -13,6 +16,7 @@ pub enum MembershipStatus {
 
 #[derive(Event, CandidType, Deserialize)]
 pub struct MembershipStatusUpdateEvent {
+    pub seq: EventSeq,
     #[topic]
     pub member: Principal,
     #[topic]
@@ -21,14 +25,167 @@ pub struct MembershipStatusUpdateEvent {
 
 #[derive(Debug, CandidType, Deserialize)]
 pub enum ControllerType {
+    Admin,
     Issue,
     Revoke,
     EventListeners,
+    Custom(Role),
+}
+
+impl From<&str> for ControllerType {
+    fn from(role: &str) -> Self {
+        match role {
+            ADMIN_ROLE => ControllerType::Admin,
+            ISSUE_ROLE => ControllerType::Issue,
+            REVOKE_ROLE => ControllerType::Revoke,
+            EVENT_LISTENERS_ROLE => ControllerType::EventListeners,
+            _ => ControllerType::Custom(String::from(role)),
+        }
+    }
 }
 
 #[derive(Event, CandidType, Deserialize)]
 pub struct ControllersUpdateEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub kind: ControllerType,
+    pub new_controllers: Controllers,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ProposalCreatedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub proposal_id: ProposalId,
+    #[topic]
+    pub proposer: Principal,
+    pub role: Role,
+    pub action: ProposalAction,
+    pub expires_at: u64,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ProposalApprovedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub proposal_id: ProposalId,
+    #[topic]
+    pub approver: Principal,
+    pub approvals: u32,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ProposalExecutedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub proposal_id: ProposalId,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ControllersUpdateScheduledEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub update_id: ControllersUpdateId,
+    #[topic]
+    pub kind: ControllerType,
+    pub change: ControllersChange,
+    pub scheduled_by: Principal,
+    pub execute_at: u64,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ControllersUpdateCancelledEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub update_id: ControllersUpdateId,
+    #[topic]
+    pub kind: ControllerType,
+    pub cancelled_by: Principal,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ControllersHandoverProposedEvent {
+    pub seq: EventSeq,
     #[topic]
     pub kind: ControllerType,
     pub new_controllers: Controllers,
+    pub proposed_by: Principal,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ControllersHandoverAcceptedEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub kind: ControllerType,
+    #[topic]
+    pub accepted_by: Principal,
+}
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct ControllersHandoverCancelledEvent {
+    pub seq: EventSeq,
+    #[topic]
+    pub kind: ControllerType,
+    pub cancelled_by: Principal,
 }
+
+#[derive(Event, CandidType, Deserialize)]
+pub struct LowCyclesEvent {
+    pub seq: EventSeq,
+    pub balance: u64,
+    pub low_water_mark: u64,
+}
+
+/// Events which are stored in the token's event log before being delivered to listeners
+pub trait LoggedEvent: CandidType {
+    fn get_name(&self) -> &'static str;
+
+    /// Returns principals this event refers to, so the log could be filtered by them
+    fn get_principals(&self) -> Vec<Principal>;
+}
+
+trait AsPrincipals {
+    fn as_principals(&self) -> Vec<Principal>;
+}
+
+impl AsPrincipals for Principal {
+    fn as_principals(&self) -> Vec<Principal> {
+        vec![*self]
+    }
+}
+
+impl AsPrincipals for Option<Principal> {
+    fn as_principals(&self) -> Vec<Principal> {
+        self.iter().cloned().collect()
+    }
+}
+
+macro_rules! logged_event {
+    ($event:ident $(, $field:ident)*) => {
+        impl LoggedEvent for $event {
+            fn get_name(&self) -> &'static str {
+                stringify!($event)
+            }
+
+            #[allow(unused_mut)]
+            fn get_principals(&self) -> Vec<Principal> {
+                let mut principals = Vec::new();
+                $(principals.extend(self.$field.as_principals());)*
+
+                principals
+            }
+        }
+    };
+}
+
+logged_event!(MembershipStatusUpdateEvent, member);
+logged_event!(ControllersUpdateEvent);
+logged_event!(ProposalCreatedEvent, proposer);
+logged_event!(ProposalApprovedEvent, approver);
+logged_event!(ProposalExecutedEvent);
+logged_event!(ControllersUpdateScheduledEvent, scheduled_by);
+logged_event!(ControllersUpdateCancelledEvent, cancelled_by);
+logged_event!(ControllersHandoverProposedEvent, proposed_by);
+logged_event!(ControllersHandoverAcceptedEvent, accepted_by);
+logged_event!(ControllersHandoverCancelledEvent, cancelled_by);
+logged_event!(LowCyclesEvent);
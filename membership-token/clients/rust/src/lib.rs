@@ -1,3 +1,6 @@
+#[cfg(feature = "agent")]
+pub mod agent;
 pub mod api;
 pub mod events;
+pub mod listener;
 pub mod types;
@@ -1,6 +1,13 @@
 use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
 use std::fmt::{Display, Formatter};
 
+pub use token_common::types::{
+    Controllers, ControllersChange, ControllersHandover, ControllersUpdateId, EventRecord,
+    EventSeq, EventTopicFilter, HeaderField, HttpRequest, HttpResponse, ProposalId, ProposalStatus,
+    Role, RoleInfo, RoleRequest, ScheduledControllersUpdate, SetRoleAdminRequest,
+    UpdateRoleControllersRequest, ADMIN_ROLE,
+};
+
 #[derive(CandidType, Deserialize)]
 pub enum Error {
     AlreadyIsAMember,
@@ -22,29 +29,32 @@ impl Display for Error {
     }
 }
 
-pub type Controllers = Vec<Principal>;
+pub const ISSUE_ROLE: &str = "issue";
+pub const REVOKE_ROLE: &str = "revoke";
+pub const EVENT_LISTENERS_ROLE: &str = "event_listeners";
 
-#[derive(Clone, CandidType, Deserialize)]
-pub struct ControllerList {
-    pub issue_controllers: Controllers,
-    pub revoke_controllers: Controllers,
-    pub event_listeners_controllers: Controllers,
-}
-
-impl ControllerList {
-    pub fn single(controller: Option<Principal>) -> ControllerList {
-        let controllers = if controller.is_some() {
-            vec![controller.unwrap()]
-        } else {
-            Vec::new()
-        };
+/// Roles every membership token is initialized with, each of them is administered by itself
+pub const DEFAULT_ROLES: [&str; 4] = [ADMIN_ROLE, ISSUE_ROLE, REVOKE_ROLE, EVENT_LISTENERS_ROLE];
 
-        ControllerList {
-            issue_controllers: controllers.clone(),
-            revoke_controllers: controllers.clone(),
-            event_listeners_controllers: controllers,
-        }
-    }
+#[derive(CandidType, Deserialize)]
+pub struct HasRoleResponse {
+    pub has_role: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetRoleMembersRequest {
+    pub role: Role,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetRoleMembersResponse {
+    pub members: Controllers,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SetRoleAdminResponse {
+    pub old_admin_role: Role,
+    pub scheduled_update_id: Option<ControllersUpdateId>,
 }
 
 #[derive(CandidType, Deserialize)]
@@ -57,32 +67,208 @@ pub struct IsMemberResponse {
     pub is_member: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, CandidType, Deserialize)]
+pub enum MemberStatus {
+    Member,
+    Pending,
+    NotMember,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct AreMembersRequest {
+    pub principals: Vec<Principal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct MemberStatusEntry {
+    pub prin: Principal,
+    pub status: MemberStatus,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct AreMembersResponse {
+    pub statuses: Vec<MemberStatusEntry>,
+}
+
 #[derive(CandidType, Deserialize)]
 pub struct GetTotalMembersResponse {
     pub total_members: u64,
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(Clone, CandidType, Deserialize)]
 pub struct IssueRevokeMembershipsRequest {
     pub principals: Vec<Principal>,
 }
 
 #[derive(CandidType, Deserialize)]
 pub struct GetControllersResponse {
-    pub controllers: ControllerList,
+    pub controllers: Vec<RoleInfo>,
 }
 
 #[derive(CandidType, Deserialize)]
 pub struct UpdateControllerRequest {
     pub new_controllers: Controllers,
+    pub renounce: Option<bool>,
 }
 
 #[derive(CandidType, Deserialize)]
 pub struct UpdateControllerResponse {
     pub old_controllers: Controllers,
+    pub scheduled_update_id: Option<ControllersUpdateId>,
 }
 
 #[derive(CandidType, Deserialize)]
 pub struct InitRequest {
     pub default_controllers: Option<Controllers>,
-}
\ No newline at end of file
+    /// How long scheduled controllers changes wait before they take effect. Defaults to 0,
+    /// which disables the timelock - changes are then applied immediately.
+    pub controllers_update_delay_nano: Option<u64>,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct SetApprovalThresholdRequest {
+    pub role: Role,
+    pub threshold: u32,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SetApprovalThresholdResponse {
+    pub old_threshold: u32,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ApprovalThreshold {
+    pub role: Role,
+    pub threshold: u32,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetApprovalThresholdsResponse {
+    pub thresholds: Vec<ApprovalThreshold>,
+}
+
+/// Privileged operation which can be executed only after enough members of the
+/// corresponding role approve it
+#[derive(Clone, CandidType, Deserialize)]
+pub enum ProposalAction {
+    IssueMemberships(IssueRevokeMembershipsRequest),
+    RevokeMemberships(IssueRevokeMembershipsRequest),
+    UpdateControllers(UpdateRoleControllersRequest),
+    GrantRole(RoleRequest),
+    RevokeRole(RoleRequest),
+    SetApprovalThreshold(SetApprovalThresholdRequest),
+}
+
+pub type Proposal = token_common::types::Proposal<ProposalAction>;
+
+#[derive(CandidType, Deserialize)]
+pub struct ProposeRequest {
+    pub action: ProposalAction,
+    pub ttl_nano: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ProposeResponse {
+    pub proposal_id: ProposalId,
+    pub executed: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ProposalIdRequest {
+    pub proposal_id: ProposalId,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ApproveProposalResponse {
+    pub executed: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetProposalResponse {
+    pub proposal: Option<Proposal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetProposalsResponse {
+    pub proposals: Vec<Proposal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ControllersUpdateIdRequest {
+    pub update_id: ControllersUpdateId,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetScheduledControllersUpdatesResponse {
+    pub updates: Vec<ScheduledControllersUpdate>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ControllersUpdateDelay {
+    pub delay_nano: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ControllersHandoverRequest {
+    pub role: Role,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetControllersHandoversResponse {
+    pub handovers: Vec<ControllersHandover>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetEventsRequest {
+    pub from_seq: EventSeq,
+    pub limit: u64,
+    pub topic_filter: Option<EventTopicFilter>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetEventsResponse {
+    pub events: Vec<EventRecord>,
+    pub oldest_seq: EventSeq,
+    pub next_seq: EventSeq,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SetEventArchiveRequest {
+    pub archive: Option<Principal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct AppendEventsRequest {
+    pub events: Vec<EventRecord>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct WalletReceiveResponse {
+    pub accepted: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetCyclesBalanceResponse {
+    pub balance: u64,
+    pub low_water_mark: Option<u64>,
+    pub suspended: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SetCyclesLowWaterMarkRequest {
+    pub low_water_mark: Option<u64>,
+    pub suspend_on_low_cycles: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetMetricsResponse {
+    pub heap_memory_bytes: u64,
+    pub stable_memory_bytes: u64,
+    pub cycles_balance: u64,
+    pub members_count: u64,
+    pub pending_members_count: u64,
+    pub scheduled_controllers_updates_count: u64,
+    pub event_listeners_count: u64,
+    pub call_counters: Vec<(String, u64)>,
+    pub last_heartbeat_at: Option<u64>,
+}
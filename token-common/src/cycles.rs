@@ -0,0 +1,64 @@
+use ic_cdk::export::candid::{CandidType, Deserialize};
+
+/// Tracks the canister's cycles balance against a configurable low-water mark
+#[derive(Default, CandidType, Deserialize)]
+pub struct CyclesMonitor {
+    pub low_water_mark: Option<u64>,
+    pub suspend_on_low_cycles: bool,
+    pub is_low: bool,
+}
+
+impl CyclesMonitor {
+    /// Returns the low-water mark only when the balance has just dropped below it, so a
+    /// single breach raises a single alert
+    pub fn check(&mut self, balance: u64) -> Option<u64> {
+        let is_low = match self.low_water_mark {
+            Some(mark) => balance < mark,
+            None => false,
+        };
+
+        let breached = is_low && !self.is_low;
+        self.is_low = is_low;
+
+        if breached {
+            self.low_water_mark
+        } else {
+            None
+        }
+    }
+
+    /// Whether non-essential work (event delivery, recurrent tasks) should be skipped
+    pub fn is_suspended(&self) -> bool {
+        self.suspend_on_low_cycles && self.is_low
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cycles::CyclesMonitor;
+
+    #[test]
+    fn low_cycles_are_detected_once_per_breach() {
+        let mut cycles = CyclesMonitor::default();
+
+        assert!(cycles.check(0).is_none());
+
+        cycles.low_water_mark = Some(1000);
+        cycles.suspend_on_low_cycles = true;
+
+        assert!(cycles.check(1500).is_none());
+        assert!(!cycles.is_suspended());
+
+        assert_eq!(cycles.check(900), Some(1000));
+        assert!(cycles.is_suspended());
+        assert!(cycles.check(800).is_none());
+        assert!(cycles.is_suspended());
+
+        assert!(cycles.check(2000).is_none());
+        assert!(!cycles.is_suspended());
+        assert_eq!(cycles.check(999), Some(1000));
+
+        cycles.suspend_on_low_cycles = false;
+        assert!(!cycles.is_suspended());
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::BTreeMap;
+
+use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+
+use crate::types::{EventRecord, EventSeq, EventTopicFilter};
+
+pub const DEFAULT_EVENT_LOG_CAPACITY: usize = 10_000;
+pub const MAX_EVENTS_PAGE_SIZE: usize = 1000;
+pub const ARCHIVE_BATCH_SIZE: usize = 100;
+pub const MAX_ARCHIVE_QUEUE_SIZE: usize = 10_000;
+
+/// Bounded log of emitted events, ordered by their global sequence numbers. Once the log is
+/// full, the oldest events are evicted - into the archive canister, if one is attached.
+/// Evicted events stay in the archive queue (and are served from it) until the archive
+/// confirms it has stored them, and at most one batch is sent to the archive at a time.
+/// While the archive keeps failing, the queue grows up to `MAX_ARCHIVE_QUEUE_SIZE` - events
+/// evicted after that are dropped and only counted.
+#[derive(CandidType, Deserialize)]
+pub struct EventLog {
+    pub events: BTreeMap<EventSeq, EventRecord>,
+    pub capacity: usize,
+    pub next_seq: EventSeq,
+    pub archive: Option<Principal>,
+    pub archive_queue: Vec<EventRecord>,
+    /// How many of the oldest queued events are being sent to the archive right now
+    pub archive_in_flight: usize,
+    pub archived_spans: Vec<ArchivedSpan>,
+    /// Evicted events which were lost, because the archive queue was full
+    pub dropped_events: u64,
+}
+
+/// Events with `from_seq <= seq < to_seq` which were stored by the archive canister
+#[derive(Clone, Debug, PartialEq, CandidType, Deserialize)]
+pub struct ArchivedSpan {
+    pub archive: Principal,
+    pub from_seq: EventSeq,
+    pub to_seq: EventSeq,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        EventLog {
+            events: BTreeMap::new(),
+            capacity: DEFAULT_EVENT_LOG_CAPACITY,
+            next_seq: 0,
+            archive: None,
+            archive_queue: Vec::new(),
+            archive_in_flight: 0,
+            archived_spans: Vec::new(),
+            dropped_events: 0,
+        }
+    }
+}
+
+impl EventLog {
+    pub fn get_next_seq(&self) -> EventSeq {
+        self.next_seq
+    }
+
+    /// Events waiting in the archive queue are still served locally, so they count as well
+    pub fn get_oldest_seq(&self) -> EventSeq {
+        self.archive_queue
+            .first()
+            .map(|it| it.seq)
+            .or_else(|| self.events.keys().next().cloned())
+            .unwrap_or(self.next_seq)
+    }
+
+    pub fn push(
+        &mut self,
+        name: &str,
+        principals: Vec<Principal>,
+        payload: Vec<u8>,
+        timestamp: u64,
+    ) -> EventSeq {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        self.events.insert(
+            seq,
+            EventRecord {
+                seq,
+                name: String::from(name),
+                principals,
+                payload,
+                timestamp,
+            },
+        );
+
+        while self.events.len() > self.capacity {
+            let oldest_seq = *self.events.keys().next().unwrap();
+            let evicted = self.events.remove(&oldest_seq).unwrap();
+
+            if self.archive.is_none() {
+                continue;
+            }
+
+            if self.is_archive_queue_full() {
+                self.dropped_events += 1;
+            } else {
+                self.archive_queue.push(evicted);
+            }
+        }
+
+        seq
+    }
+
+    pub fn is_archive_queue_full(&self) -> bool {
+        self.archive_queue.len() >= MAX_ARCHIVE_QUEUE_SIZE
+    }
+
+    /// Returns the oldest `ARCHIVE_BATCH_SIZE` queued events to send to the archive, once
+    /// enough of them are collected and no other batch is on its way. The events stay queued
+    /// until the batch is either confirmed or returned.
+    pub fn take_archive_batch(&mut self) -> Option<(Principal, Vec<EventRecord>)> {
+        let archive = self.archive?;
+
+        if self.archive_in_flight > 0 || self.archive_queue.len() < ARCHIVE_BATCH_SIZE {
+            return None;
+        }
+
+        self.archive_in_flight = ARCHIVE_BATCH_SIZE;
+
+        Some((archive, self.archive_queue[..ARCHIVE_BATCH_SIZE].to_vec()))
+    }
+
+    /// Releases the batch, if it failed to reach the archive, so it is sent again later
+    pub fn return_archive_batch(&mut self) {
+        self.archive_in_flight = 0;
+    }
+
+    /// Drops the batch from the queue and remembers where it went, once the archive has
+    /// stored it. Events dropped while the queue was full leave gaps, so a batch may cover
+    /// several spans.
+    pub fn confirm_archive_batch(&mut self, archive: Principal) {
+        let batch: Vec<_> = self.archive_queue.drain(..self.archive_in_flight).collect();
+        self.archive_in_flight = 0;
+
+        let mut spans: Vec<ArchivedSpan> = Vec::new();
+        for event in batch {
+            match spans.last_mut() {
+                Some(span) if span.to_seq == event.seq => span.to_seq += 1,
+                _ => spans.push(ArchivedSpan {
+                    archive,
+                    from_seq: event.seq,
+                    to_seq: event.seq + 1,
+                }),
+            }
+        }
+
+        for span in spans {
+            self.add_archived_span(span);
+        }
+    }
+
+    /// Keeps the spans ordered by sequence numbers, merging adjacent spans of the same archive
+    fn add_archived_span(&mut self, span: ArchivedSpan) {
+        let idx = self
+            .archived_spans
+            .iter()
+            .position(|it| it.from_seq > span.from_seq)
+            .unwrap_or(self.archived_spans.len());
+
+        self.archived_spans.insert(idx, span);
+
+        if idx + 1 < self.archived_spans.len() {
+            self.merge_archived_spans(idx);
+        }
+        if idx > 0 {
+            self.merge_archived_spans(idx - 1);
+        }
+    }
+
+    fn merge_archived_spans(&mut self, idx: usize) {
+        let (left, right) = (&self.archived_spans[idx], &self.archived_spans[idx + 1]);
+
+        if left.archive == right.archive && left.to_seq == right.from_seq {
+            self.archived_spans[idx].to_seq = self.archived_spans[idx + 1].to_seq;
+            self.archived_spans.remove(idx + 1);
+        }
+    }
+
+    /// Returns the parts of archived spans which intersect with `from_seq..to_seq`
+    pub fn get_archived_spans(&self, from_seq: EventSeq, to_seq: EventSeq) -> Vec<ArchivedSpan> {
+        self.archived_spans
+            .iter()
+            .filter(|span| span.from_seq < to_seq && from_seq < span.to_seq)
+            .map(|span| ArchivedSpan {
+                archive: span.archive,
+                from_seq: span.from_seq.max(from_seq),
+                to_seq: span.to_seq.min(to_seq),
+            })
+            .collect()
+    }
+
+    /// Returns events with `from_seq <= seq < to_seq` which are still stored locally
+    pub fn get_events_range(&self, from_seq: EventSeq, to_seq: EventSeq) -> Vec<&EventRecord> {
+        self.iter_from(from_seq)
+            .take_while(|record| record.seq < to_seq)
+            .collect()
+    }
+
+    pub fn get_events(
+        &self,
+        from_seq: EventSeq,
+        limit: usize,
+        filter: &Option<EventTopicFilter>,
+    ) -> Vec<EventRecord> {
+        self.iter_from(from_seq)
+            .filter(|record| match filter {
+                Some(f) => matches_filter(record, f),
+                None => true,
+            })
+            .take(limit.min(MAX_EVENTS_PAGE_SIZE))
+            .cloned()
+            .collect()
+    }
+
+    fn iter_from(&self, from_seq: EventSeq) -> impl Iterator<Item = &EventRecord> {
+        self.archive_queue
+            .iter()
+            .filter(move |record| record.seq >= from_seq)
+            .chain(self.events.range(from_seq..).map(|(_, record)| record))
+    }
+}
+
+/// Shared with the archive canister, so archived events are filtered the same way
+pub fn matches_filter(record: &EventRecord, filter: &EventTopicFilter) -> bool {
+    let name_matches = match &filter.name {
+        Some(name) => &record.name == name,
+        None => true,
+    };
+
+    let principal_matches = match &filter.principal {
+        Some(principal) => record.principals.contains(principal),
+        None => true,
+    };
+
+    name_matches && principal_matches
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event_log::{ArchivedSpan, EventLog, ARCHIVE_BATCH_SIZE, MAX_ARCHIVE_QUEUE_SIZE};
+    use crate::random_principal;
+    use crate::types::{EventRecord, EventTopicFilter};
+
+    #[test]
+    fn event_log_works_fine() {
+        let mut log = EventLog {
+            capacity: 3,
+            ..Default::default()
+        };
+        let user_1 = random_principal();
+        let user_2 = random_principal();
+
+        for i in 0..5 {
+            let user = if i % 2 == 0 { user_1 } else { user_2 };
+            let seq = log.push("TokenMoveEvent", vec![user], vec![], i);
+
+            assert_eq!(seq, i);
+        }
+
+        assert_eq!(log.get_oldest_seq(), 2);
+        assert_eq!(log.get_next_seq(), 5);
+        assert!(log.archive_queue.is_empty());
+
+        let events = log.get_events(0, 10, &None);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].seq, 2);
+
+        let filter = Some(EventTopicFilter {
+            name: None,
+            principal: Some(user_1),
+        });
+        let events = log.get_events(0, 10, &filter);
+        assert_eq!(events.len(), 2);
+
+        let filter = Some(EventTopicFilter {
+            name: Some(String::from("InfoUpdateEvent")),
+            principal: None,
+        });
+        assert!(log.get_events(0, 10, &filter).is_empty());
+    }
+
+    #[test]
+    fn archiving_works_fine() {
+        let mut log = EventLog {
+            capacity: 2,
+            ..Default::default()
+        };
+        let archive = random_principal();
+        log.archive = Some(archive);
+
+        for i in 0..(ARCHIVE_BATCH_SIZE as u64 + 2) {
+            log.push("TokenMoveEvent", vec![], vec![], i);
+        }
+
+        // queued events are still served locally
+        assert_eq!(log.get_oldest_seq(), 0);
+        assert_eq!(log.get_events_range(0, 3).len(), 3);
+
+        let (to, batch) = log.take_archive_batch().unwrap();
+        assert_eq!(to, archive);
+        assert_eq!(batch.len(), ARCHIVE_BATCH_SIZE);
+
+        // the batch on its way is still readable and no other batch is sent meanwhile
+        assert_eq!(log.get_oldest_seq(), 0);
+        assert!(log.take_archive_batch().is_none());
+
+        log.return_archive_batch();
+        assert_eq!(log.archive_queue.len(), ARCHIVE_BATCH_SIZE);
+
+        let (_, batch) = log.take_archive_batch().unwrap();
+        assert_eq!(batch.len(), ARCHIVE_BATCH_SIZE);
+
+        log.confirm_archive_batch(archive);
+        assert!(log.archive_queue.is_empty());
+        assert_eq!(log.get_oldest_seq(), ARCHIVE_BATCH_SIZE as u64);
+
+        assert_eq!(
+            log.get_archived_spans(10, 1000),
+            vec![ArchivedSpan {
+                archive,
+                from_seq: 10,
+                to_seq: ARCHIVE_BATCH_SIZE as u64,
+            }]
+        );
+        assert!(log
+            .get_archived_spans(ARCHIVE_BATCH_SIZE as u64, 1000)
+            .is_empty());
+
+        // spans stay sorted and merged regardless of the order they are confirmed in
+        let span = |from_seq, to_seq| ArchivedSpan {
+            archive,
+            from_seq,
+            to_seq,
+        };
+        log.archived_spans = vec![span(0, 10), span(20, 30)];
+        log.add_archived_span(span(10, 20));
+        assert_eq!(log.archived_spans, vec![span(0, 30)]);
+    }
+
+    #[test]
+    fn archive_queue_is_bounded() {
+        let mut log = EventLog {
+            capacity: 1,
+            ..Default::default()
+        };
+        let archive = random_principal();
+        log.archive = Some(archive);
+
+        // the archive never accepts anything, so the evicted events pile up in the queue
+        for i in 0..(MAX_ARCHIVE_QUEUE_SIZE as u64 + 3) {
+            log.push("TokenMoveEvent", vec![], vec![], i);
+        }
+
+        assert!(log.is_archive_queue_full());
+        assert_eq!(log.archive_queue.len(), MAX_ARCHIVE_QUEUE_SIZE);
+        assert_eq!(log.dropped_events, 2);
+        assert_eq!(log.events.len(), 1);
+
+        // once the archive is back, the batch is confirmed and the queue has room again
+        log.take_archive_batch().unwrap();
+        log.confirm_archive_batch(archive);
+        assert!(!log.is_archive_queue_full());
+
+        log.push("TokenMoveEvent", vec![], vec![], 0);
+        assert_eq!(log.dropped_events, 2);
+        assert_eq!(
+            log.archive_queue.last().unwrap().seq,
+            MAX_ARCHIVE_QUEUE_SIZE as u64 + 2
+        );
+    }
+
+    #[test]
+    fn archived_spans_skip_dropped_events() {
+        let mut log = EventLog::default();
+        let archive = random_principal();
+        log.archive = Some(archive);
+
+        let record = |seq| EventRecord {
+            seq,
+            name: String::from("TokenMoveEvent"),
+            principals: vec![],
+            payload: vec![],
+            timestamp: 0,
+        };
+        log.archive_queue = (0..50).chain(60..110).map(record).collect();
+
+        log.take_archive_batch().unwrap();
+        log.confirm_archive_batch(archive);
+
+        let span = |from_seq, to_seq| ArchivedSpan {
+            archive,
+            from_seq,
+            to_seq,
+        };
+        assert_eq!(log.archived_spans, vec![span(0, 50), span(60, 110)]);
+    }
+}
@@ -0,0 +1,606 @@
+use std::collections::BTreeSet;
+
+use ic_cdk::export::candid::Principal;
+use ic_cron::types::TaskId;
+
+use crate::handover::HandoverRegistry;
+use crate::proposals::{ProposalRegistry, DEFAULT_PROPOSAL_TTL_NANO};
+use crate::roles::RoleRegistry;
+use crate::timelock::ControllersTimelock;
+use crate::types::{
+    Controllers, ControllersChange, ControllersUpdateId, ProposalId, Role, RoleRequest,
+    ScheduledControllersUpdate, SetRoleAdminRequest, UpdateRoleControllersRequest, ADMIN_ROLE,
+};
+
+/// Something that happened to the controls of a token, for the token to emit as its own event
+pub enum GovernanceEvent<A> {
+    ControllersUpdated {
+        role: Role,
+        new_controllers: Controllers,
+    },
+    ControllersUpdateScheduled(ScheduledControllersUpdate),
+    ControllersUpdateCancelled {
+        update_id: ControllersUpdateId,
+        role: Role,
+        cancelled_by: Principal,
+    },
+    HandoverProposed {
+        role: Role,
+        new_controllers: Controllers,
+        proposed_by: Principal,
+    },
+    HandoverAccepted {
+        role: Role,
+        accepted_by: Principal,
+    },
+    HandoverCancelled {
+        role: Role,
+        cancelled_by: Principal,
+    },
+    ProposalCreated {
+        proposal_id: ProposalId,
+        proposer: Principal,
+        role: Role,
+        action: A,
+        expires_at: u64,
+    },
+    ProposalApproved {
+        proposal_id: ProposalId,
+        approver: Principal,
+        approvals: u32,
+    },
+    ProposalExecuted {
+        proposal_id: ProposalId,
+    },
+}
+
+/// Role, multisig, timelock and handover logic behind the endpoints of both tokens. A token
+/// provides its registries, its environment and its events, while the provided functions keep
+/// the rules of changing controllers identical for every token. Failures trap, as endpoints do.
+pub trait Governance {
+    /// The token's proposal action
+    type Action: Clone + 'static;
+
+    fn roles() -> &'static mut RoleRegistry;
+    fn proposals() -> &'static mut ProposalRegistry<Self::Action>;
+    fn timelock() -> &'static mut ControllersTimelock;
+    fn handovers() -> &'static mut HandoverRegistry;
+
+    fn time() -> u64;
+    fn trap(msg: &str) -> !;
+    fn log(msg: &str);
+
+    /// Enqueues the task which applies the scheduled update once `delay_nano` passes
+    fn schedule_update_task(update_id: ControllersUpdateId, delay_nano: u64) -> Option<TaskId>;
+    fn dequeue_task(task_id: TaskId);
+
+    fn emit(event: GovernanceEvent<Self::Action>);
+
+    /// Returns the role whose members have to approve the action
+    fn required_role(action: &Self::Action) -> Role;
+
+    /// Performs the approved action on behalf of the controller who proposed it
+    fn execute_action(action: Self::Action, proposer: Principal);
+
+    /// Lets the token clean up after principals which have just lost the role
+    fn on_role_members_removed(_role: &str, _removed: &[Principal]) {}
+
+    // ------------------ ROLES -------------------
+
+    /// Traps unless the caller administers the role, returning the admin role
+    fn check_admin_of(role: &str, caller: &Principal) -> Role {
+        let admin_role = Self::roles().get_admin_role(role);
+
+        if !Self::roles().has_role(&admin_role, caller) {
+            Self::trap(format!("The caller is not the {} controller", admin_role).as_str());
+        }
+
+        admin_role
+    }
+
+    fn grant_role(request: RoleRequest, caller: Principal) {
+        let admin_role = Self::check_admin_of(&request.role, &caller);
+
+        Self::approval_not_required(&admin_role);
+        Self::schedule_controllers_change(ControllersChange::GrantRole(request), caller);
+    }
+
+    fn revoke_role(request: RoleRequest, caller: Principal) {
+        let admin_role = Self::check_admin_of(&request.role, &caller);
+
+        if !Self::roles().has_role(&request.role, &request.principal) {
+            Self::trap(format!("Failed to revoke role {} - not a member", request.role).as_str());
+        }
+
+        Self::approval_not_required(&admin_role);
+        Self::schedule_role_revocation(request, caller);
+    }
+
+    fn schedule_role_revocation(
+        request: RoleRequest,
+        scheduled_by: Principal,
+    ) -> Option<ControllersUpdateId> {
+        Self::role_stays_controlled(&request);
+        Self::schedule_controllers_change(ControllersChange::RevokeRole(request), scheduled_by)
+    }
+
+    /// Returns the current admin role along with the id of the scheduled update
+    fn set_role_admin(
+        request: SetRoleAdminRequest,
+        caller: Principal,
+    ) -> (Role, Option<ControllersUpdateId>) {
+        let old_admin_role = Self::check_admin_of(&request.role, &caller);
+
+        Self::approval_not_required(&old_admin_role);
+
+        // nobody would be able to manage the role under the new admin role
+        if Self::roles()
+            .get_role_members(&request.admin_role)
+            .is_empty()
+        {
+            Self::trap(
+                format!(
+                    "Failed to set admin of role {} - role {} has no members",
+                    request.role, request.admin_role
+                )
+                .as_str(),
+            );
+        }
+
+        let scheduled_update_id =
+            Self::schedule_controllers_change(ControllersChange::SetRoleAdmin(request), caller);
+
+        (old_admin_role, scheduled_update_id)
+    }
+
+    /// Returns the current controllers of the role along with the id of the scheduled update
+    fn update_role_controllers(
+        request: UpdateRoleControllersRequest,
+        caller: Principal,
+    ) -> (Controllers, Option<ControllersUpdateId>) {
+        Self::approval_not_required(&Self::roles().get_admin_role(&request.role));
+
+        let old_controllers = Self::roles().get_role_members(&request.role);
+        let scheduled_update_id = Self::propose_role_controllers(request, caller);
+
+        (old_controllers, scheduled_update_id)
+    }
+
+    /// Traps if revoking the role from the principal would leave the role with fewer members
+    /// than it takes to approve its proposals
+    fn role_stays_controlled(request: &RoleRequest) {
+        let remaining = match Self::members_left_after_revoke(request) {
+            Some(remaining) => remaining,
+            None => return,
+        };
+
+        if !Self::keeps_quorum(&request.role, remaining) {
+            Self::trap(
+                format!(
+                    "Refusing to leave the {} role with {} controllers while {} approvals are required - lower the threshold or use the renounce flag instead",
+                    request.role,
+                    remaining,
+                    Self::proposals().get_threshold(&request.role)
+                )
+                .as_str(),
+            );
+        }
+    }
+
+    /// The number of members the role keeps once the principal's membership is revoked, or
+    /// `None` if the principal is not a member to begin with
+    fn members_left_after_revoke(request: &RoleRequest) -> Option<usize> {
+        let members = Self::roles().get_role_members(&request.role);
+
+        if members.contains(&request.principal) {
+            Some(members.len() - 1)
+        } else {
+            None
+        }
+    }
+
+    /// Whether that many members are still enough to approve the role's proposals. An empty
+    /// role never is, so only an explicit renouncement may leave a role without members.
+    fn keeps_quorum(role: &str, members_count: usize) -> bool {
+        members_count as u64 >= Self::proposals().get_threshold(role) as u64
+    }
+
+    // ------------------ HANDOVER -------------------
+
+    /// Starts a two-step handover of the role to the new controllers, or schedules the role
+    /// renouncement if the new controllers list is empty and the renounce flag is set
+    fn propose_role_controllers(
+        request: UpdateRoleControllersRequest,
+        proposed_by: Principal,
+    ) -> Option<ControllersUpdateId> {
+        if request.new_controllers.is_empty() {
+            if !request.renounce.unwrap_or_default() {
+                Self::trap(
+                    format!(
+                        "Refusing to leave the {} role without controllers - set the renounce flag to proceed",
+                        request.role
+                    )
+                    .as_str(),
+                );
+            }
+
+            return Self::schedule_controllers_change(
+                ControllersChange::SetControllers(request),
+                proposed_by,
+            );
+        }
+
+        if !Self::keeps_quorum(&request.role, distinct_count(&request.new_controllers)) {
+            Self::trap(
+                format!(
+                    "Refusing to hand the {} role over to fewer controllers than its approval threshold",
+                    request.role
+                )
+                .as_str(),
+            );
+        }
+
+        if let Err(e) = Self::handovers().propose(
+            request.role.clone(),
+            request.new_controllers.clone(),
+            proposed_by,
+            Self::time(),
+        ) {
+            Self::trap(format!("Failed to propose controllers handover - {}", e).as_str());
+        }
+
+        Self::emit(GovernanceEvent::HandoverProposed {
+            role: request.role.clone(),
+            new_controllers: request.new_controllers.clone(),
+            proposed_by,
+        });
+
+        // the proposer doesn't need to accept their own handover separately
+        if request.new_controllers.contains(&proposed_by) {
+            Self::accept_controllers_handover(&request.role, proposed_by)
+        } else {
+            None
+        }
+    }
+
+    fn accept_controllers_handover(role: &str, acceptor: Principal) -> Option<ControllersUpdateId> {
+        let new_controllers = match Self::handovers().accept(role, acceptor) {
+            Ok(it) => it,
+            Err(e) => Self::trap(format!("Failed to accept controllers handover - {}", e).as_str()),
+        };
+
+        Self::emit(GovernanceEvent::HandoverAccepted {
+            role: String::from(role),
+            accepted_by: acceptor,
+        });
+
+        new_controllers.and_then(|new_controllers| {
+            let change = ControllersChange::SetControllers(UpdateRoleControllersRequest {
+                role: String::from(role),
+                new_controllers,
+                renounce: None,
+            });
+
+            Self::schedule_controllers_change(change, acceptor)
+        })
+    }
+
+    fn cancel_controllers_handover(role: &str, caller: Principal) {
+        let roles = Self::roles();
+
+        if !roles.has_role(role, &caller) && !roles.is_admin_of(role, &caller) {
+            Self::trap(format!("The caller is not the {} controller", role).as_str());
+        }
+
+        match Self::handovers().cancel(role) {
+            Ok(_) => Self::emit(GovernanceEvent::HandoverCancelled {
+                role: String::from(role),
+                cancelled_by: caller,
+            }),
+            Err(e) => Self::trap(format!("Failed to cancel controllers handover - {}", e).as_str()),
+        }
+    }
+
+    // ------------------ TIMELOCK -------------------
+
+    fn cancel_controllers_update(update_id: ControllersUpdateId, caller: Principal) {
+        let timelock = Self::timelock();
+
+        let role = timelock
+            .get_scheduled_update(update_id)
+            .map(|it| it.change.get_role())
+            .unwrap_or_else(|| Self::trap("Scheduled controllers update not found"));
+
+        if !Self::roles().has_role(&role, &caller) {
+            Self::trap(format!("The caller is not the {} controller", role).as_str());
+        }
+
+        if let Some(task_id) = timelock.get_update_task(update_id) {
+            Self::dequeue_task(task_id);
+        }
+
+        match timelock.cancel(update_id) {
+            Ok(_) => Self::emit(GovernanceEvent::ControllersUpdateCancelled {
+                update_id,
+                role,
+                cancelled_by: caller,
+            }),
+            Err(e) => Self::trap(format!("Failed to cancel controllers update - {}", e).as_str()),
+        }
+    }
+
+    /// The caller is expected to be an admin, which is up to the endpoint's guard
+    fn set_controllers_update_delay(delay_nano: u64, caller: Principal) {
+        Self::approval_not_required(ADMIN_ROLE);
+
+        if let Err(e) = ControllersTimelock::check_delay(delay_nano) {
+            Self::trap(format!("Failed to set controllers update delay - {}", e).as_str());
+        }
+
+        // a shorter delay would rush the next changes, while a longer one would hold them
+        // back, so either way the current controllers get the current delay to react
+        Self::schedule_controllers_change(ControllersChange::SetDelay(delay_nano), caller);
+    }
+
+    /// Applies the change right away if there is no timelock delay, otherwise queues it
+    /// until the delay passes and returns the id of the scheduled update
+    fn schedule_controllers_change(
+        change: ControllersChange,
+        scheduled_by: Principal,
+    ) -> Option<ControllersUpdateId> {
+        let timelock = Self::timelock();
+
+        if timelock.delay_nano == 0 {
+            Self::apply_controllers_change(change);
+
+            return None;
+        }
+
+        let update = match timelock.schedule(change, scheduled_by, Self::time()) {
+            Ok(it) => it,
+            Err(e) => Self::trap(format!("Failed to schedule controllers update - {}", e).as_str()),
+        };
+
+        if let Some(task_id) = Self::schedule_update_task(update.id, timelock.delay_nano) {
+            timelock.register_update_task(update.id, task_id);
+        }
+
+        let update_id = update.id;
+        Self::emit(GovernanceEvent::ControllersUpdateScheduled(update));
+
+        Some(update_id)
+    }
+
+    /// Applies the scheduled update, once its task fires
+    fn apply_due_controllers_update(update_id: ControllersUpdateId) {
+        match Self::timelock().take_due(update_id, Self::time()) {
+            Ok(update) => Self::apply_controllers_change(update.change),
+            Err(e) => Self::log(format!("Controllers update failed with error: {}", e).as_str()),
+        };
+    }
+
+    fn apply_controllers_change(change: ControllersChange) {
+        let roles = Self::roles();
+
+        match change {
+            ControllersChange::SetControllers(request) => {
+                if request.new_controllers.is_empty() && !request.renounce.unwrap_or_default() {
+                    Self::log(
+                        format!(
+                            "Refusing to leave the {} role without controllers",
+                            request.role
+                        )
+                        .as_str(),
+                    );
+
+                    return;
+                }
+
+                // the threshold might have been raised since the change was scheduled
+                if !request.new_controllers.is_empty()
+                    && !Self::keeps_quorum(&request.role, distinct_count(&request.new_controllers))
+                {
+                    Self::log(
+                        format!(
+                            "Refusing to leave the {} role with fewer controllers than its approval threshold",
+                            request.role
+                        )
+                        .as_str(),
+                    );
+
+                    return;
+                }
+
+                let removed: Controllers = roles
+                    .set_role_members(&request.role, request.new_controllers.clone())
+                    .into_iter()
+                    .filter(|it| !request.new_controllers.contains(it))
+                    .collect();
+
+                Self::on_role_members_removed(&request.role, &removed);
+
+                Self::emit(GovernanceEvent::ControllersUpdated {
+                    role: request.role,
+                    new_controllers: request.new_controllers,
+                });
+            }
+            ControllersChange::GrantRole(request) => {
+                let new_controllers = roles.add_role_member(&request.role, request.principal);
+
+                Self::emit(GovernanceEvent::ControllersUpdated {
+                    role: request.role,
+                    new_controllers,
+                });
+            }
+            ControllersChange::RevokeRole(request) => {
+                // other revocations might have been applied since this one was scheduled
+                let keeps_quorum = Self::members_left_after_revoke(&request)
+                    .map(|remaining| Self::keeps_quorum(&request.role, remaining))
+                    .unwrap_or(true);
+
+                if !keeps_quorum {
+                    Self::log(
+                        format!(
+                            "Refusing to leave the {} role with fewer controllers than its approval threshold",
+                            request.role
+                        )
+                        .as_str(),
+                    );
+
+                    return;
+                }
+
+                match roles.remove_role_member(&request.role, &request.principal) {
+                    Some(new_controllers) => {
+                        Self::on_role_members_removed(&request.role, &[request.principal]);
+
+                        Self::emit(GovernanceEvent::ControllersUpdated {
+                            role: request.role,
+                            new_controllers,
+                        });
+                    }
+                    None => Self::log(
+                        format!("Failed to revoke role {} - not a member", request.role).as_str(),
+                    ),
+                }
+            }
+            ControllersChange::SetRoleAdmin(request) => {
+                if roles.get_role_members(&request.admin_role).is_empty() {
+                    Self::log(
+                        format!("Refusing to leave the {} role without admins", request.role)
+                            .as_str(),
+                    );
+
+                    return;
+                }
+
+                let old_admin_role =
+                    roles.replace_admin_role(&request.role, request.admin_role.clone());
+
+                Self::log(
+                    format!(
+                        "Admin of role {} changed from {} to {}",
+                        request.role, old_admin_role, request.admin_role
+                    )
+                    .as_str(),
+                );
+            }
+            ControllersChange::SetDelay(delay_nano) => Self::timelock().delay_nano = delay_nano,
+        }
+    }
+
+    // ------------------ MULTISIG -------------------
+
+    /// Returns the previous threshold
+    fn set_approval_threshold(role: &str, threshold: u32, caller: Principal) -> u32 {
+        let admin_role = Self::check_admin_of(role, &caller);
+
+        Self::approval_not_required(&admin_role);
+        Self::apply_approval_threshold(role, threshold)
+    }
+
+    fn apply_approval_threshold(role: &str, threshold: u32) -> u32 {
+        let members_count = Self::roles().get_role_members(role).len();
+
+        match Self::proposals().set_threshold(role, threshold, members_count) {
+            Ok(old_threshold) => old_threshold,
+            Err(e) => Self::trap(format!("Failed to set approval threshold - {}", e).as_str()),
+        }
+    }
+
+    /// Traps if the role is configured to execute privileged operations only through proposals
+    fn approval_not_required(role: &str) {
+        if Self::proposals().get_threshold(role) > 1 {
+            Self::trap(
+                format!(
+                    "The {} controllers should make a proposal for this operation",
+                    role
+                )
+                .as_str(),
+            );
+        }
+    }
+
+    /// Returns the id of the new proposal and whether it got executed right away
+    fn propose(
+        action: Self::Action,
+        ttl_nano: Option<u64>,
+        caller: Principal,
+    ) -> (ProposalId, bool) {
+        let now = Self::time();
+        let role = Self::required_role(&action);
+
+        if !Self::roles().has_role(&role, &caller) {
+            Self::trap(format!("The caller is not the {} controller", role).as_str());
+        }
+
+        let ttl_nano = ttl_nano.unwrap_or(DEFAULT_PROPOSAL_TTL_NANO);
+        let proposals = Self::proposals();
+
+        let proposal_id =
+            match proposals.create_proposal(caller, role.clone(), action.clone(), now, ttl_nano) {
+                Ok(id) => id,
+                Err(e) => Self::trap(format!("Failed to create proposal - {}", e).as_str()),
+            };
+
+        Self::emit(GovernanceEvent::ProposalCreated {
+            proposal_id,
+            proposer: caller,
+            role,
+            action,
+            expires_at: proposals.proposals[&proposal_id].expires_at,
+        });
+
+        (proposal_id, Self::try_execute_proposal(proposal_id, now))
+    }
+
+    /// Returns whether the proposal got executed
+    fn approve_proposal(proposal_id: ProposalId, caller: Principal) -> bool {
+        let now = Self::time();
+        let proposals = Self::proposals();
+
+        let proposal = proposals
+            .get_proposal(proposal_id, now)
+            .unwrap_or_else(|| Self::trap("Proposal not found"));
+
+        if !Self::roles().has_role(&proposal.role, &caller) {
+            Self::trap(format!("The caller is not the {} controller", proposal.role).as_str());
+        }
+
+        match proposals.approve(proposal_id, caller, now) {
+            Ok(approvals) => Self::emit(GovernanceEvent::ProposalApproved {
+                proposal_id,
+                approver: caller,
+                approvals,
+            }),
+            Err(e) => Self::trap(format!("Failed to approve proposal - {}", e).as_str()),
+        };
+
+        Self::try_execute_proposal(proposal_id, now)
+    }
+
+    fn try_execute_proposal(proposal_id: ProposalId, now: u64) -> bool {
+        let proposals = Self::proposals();
+
+        let (role, proposer) = match proposals.proposals.get(&proposal_id) {
+            Some(p) => (p.role.clone(), p.proposer),
+            None => return false,
+        };
+
+        let role_members = Self::roles().get_role_members(&role);
+
+        let action = match proposals.take_for_execution(proposal_id, now, &role_members) {
+            Some(a) => a,
+            None => return false,
+        };
+
+        Self::execute_action(action, proposer);
+        Self::emit(GovernanceEvent::ProposalExecuted { proposal_id });
+
+        true
+    }
+}
+
+fn distinct_count(controllers: &[Principal]) -> usize {
+    controllers.iter().collect::<BTreeSet<_>>().len()
+}
@@ -0,0 +1,132 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+
+use crate::types::{Controllers, ControllersHandover, Error, Role};
+
+/// Pending two-step controllers handovers, at most one per role. A handover is complete
+/// only once every new controller has accepted it, which guarantees that the role is
+/// never handed over to a mistyped or an inaccessible principal.
+#[derive(Default, CandidType, Deserialize)]
+pub struct HandoverRegistry {
+    pub handovers: BTreeMap<Role, ControllersHandover>,
+}
+
+impl HandoverRegistry {
+    /// Starts a new handover of the role, replacing the previous pending one if it exists
+    pub fn propose(
+        &mut self,
+        role: Role,
+        new_controllers: Controllers,
+        proposed_by: Principal,
+        now: u64,
+    ) -> Result<(), Error> {
+        let new_controllers: Controllers = new_controllers
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        if new_controllers.is_empty() {
+            return Err(Error::EmptyControllers);
+        }
+
+        self.handovers.insert(
+            role.clone(),
+            ControllersHandover {
+                role,
+                new_controllers,
+                accepted_by: Vec::new(),
+                proposed_by,
+                proposed_at: now,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Accepts the handover on behalf of one of the new controllers. Once all of them have
+    /// accepted, the handover is removed and the new controllers are returned.
+    pub fn accept(
+        &mut self,
+        role: &str,
+        acceptor: Principal,
+    ) -> Result<Option<Controllers>, Error> {
+        let handover = self
+            .handovers
+            .get_mut(role)
+            .ok_or(Error::HandoverNotFound)?;
+
+        if !handover.new_controllers.contains(&acceptor) {
+            return Err(Error::AccessDenied);
+        }
+
+        if handover.accepted_by.contains(&acceptor) {
+            return Err(Error::AlreadyAccepted);
+        }
+
+        handover.accepted_by.push(acceptor);
+
+        if handover.accepted_by.len() < handover.new_controllers.len() {
+            return Ok(None);
+        }
+
+        Ok(self.handovers.remove(role).map(|it| it.new_controllers))
+    }
+
+    pub fn cancel(&mut self, role: &str) -> Result<ControllersHandover, Error> {
+        self.handovers.remove(role).ok_or(Error::HandoverNotFound)
+    }
+
+    pub fn get_handovers(&self) -> Vec<ControllersHandover> {
+        self.handovers.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::handover::HandoverRegistry;
+    use crate::random_principal;
+
+    const MINT_ROLE: &str = "mint";
+
+    #[test]
+    fn handover_works_fine() {
+        let mut registry = HandoverRegistry::default();
+        let controller = random_principal();
+        let new_controller_1 = random_principal();
+        let new_controller_2 = random_principal();
+
+        registry
+            .propose(String::from(MINT_ROLE), vec![], controller, 0)
+            .err()
+            .unwrap();
+        registry
+            .propose(
+                String::from(MINT_ROLE),
+                vec![new_controller_1, new_controller_2, new_controller_1],
+                controller,
+                0,
+            )
+            .ok()
+            .unwrap();
+
+        registry.accept(MINT_ROLE, controller).err().unwrap();
+        assert!(registry
+            .accept(MINT_ROLE, new_controller_1)
+            .ok()
+            .unwrap()
+            .is_none());
+        registry.accept(MINT_ROLE, new_controller_1).err().unwrap();
+
+        let new_controllers = registry
+            .accept(MINT_ROLE, new_controller_2)
+            .ok()
+            .unwrap()
+            .unwrap();
+        assert_eq!(new_controllers.len(), 2);
+
+        assert!(registry.get_handovers().is_empty());
+        registry.cancel(MINT_ROLE).err().unwrap();
+    }
+}
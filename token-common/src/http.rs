@@ -0,0 +1,250 @@
+use ic_cdk::export::Principal;
+
+use crate::types::{HeaderField, HttpResponse};
+
+pub const DEFAULT_HTTP_PAGE_SIZE: usize = 100;
+
+/// Just enough of JSON to render the canister's data without pulling a serializer in.
+/// Amounts, timestamps and other 64-bit integers are converted into strings, because
+/// JavaScript numbers silently lose precision above 2^53 - use `Nat` only for small values.
+pub enum Json {
+    Null,
+    Bool(bool),
+    Nat(u32),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (String::from(key), value))
+                .collect(),
+        )
+    }
+
+    pub fn encode(&self) -> String {
+        let mut buf = String::new();
+        self.write(&mut buf);
+
+        buf
+    }
+
+    fn write(&self, buf: &mut String) {
+        match self {
+            Json::Null => buf.push_str("null"),
+            Json::Bool(b) => buf.push_str(if *b { "true" } else { "false" }),
+            Json::Nat(n) => buf.push_str(n.to_string().as_str()),
+            Json::Str(s) => write_str(s, buf),
+            Json::Array(items) => {
+                buf.push('[');
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        buf.push(',');
+                    }
+                    item.write(buf);
+                }
+                buf.push(']');
+            }
+            Json::Object(fields) => {
+                buf.push('{');
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        buf.push(',');
+                    }
+                    write_str(key, buf);
+                    buf.push(':');
+                    value.write(buf);
+                }
+                buf.push('}');
+            }
+        }
+    }
+}
+
+fn write_str(s: &str, buf: &mut String) {
+    buf.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if c.is_control() => buf.push_str(format!("\\u{:04x}", c as u32).as_str()),
+            c => buf.push(c),
+        }
+    }
+
+    buf.push('"');
+}
+
+impl From<u64> for Json {
+    fn from(n: u64) -> Self {
+        Json::Str(n.to_string())
+    }
+}
+
+impl From<i64> for Json {
+    fn from(i: i64) -> Self {
+        Json::Str(i.to_string())
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::Str(s)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::Str(String::from(s))
+    }
+}
+
+impl From<Principal> for Json {
+    fn from(p: Principal) -> Self {
+        Json::Str(p.to_text())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(o: Option<T>) -> Self {
+        match o {
+            Some(it) => it.into(),
+            None => Json::Null,
+        }
+    }
+}
+
+/// Splits the request url into non-empty path segments and query parameters
+pub fn parse_url(url: &str) -> (Vec<&str>, Vec<(&str, &str)>) {
+    let mut parts = url.splitn(2, '?');
+
+    let segments = parts
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|it| !it.is_empty())
+        .collect();
+
+    let params = parts
+        .next()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|it| !it.is_empty())
+        .map(|it| {
+            let mut pair = it.splitn(2, '=');
+            (
+                pair.next().unwrap_or_default(),
+                pair.next().unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    (segments, params)
+}
+
+pub fn get_param<'a>(params: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| *value)
+}
+
+/// Parses an optional numeric parameter, `Err` is returned for malformed values
+pub fn get_num_param(params: &[(&str, &str)], name: &str) -> Result<Option<u64>, HttpResponse> {
+    match get_param(params, name) {
+        None => Ok(None),
+        Some(value) => value
+            .parse::<u64>()
+            .map(Some)
+            .map_err(|_| error_response(400, format!("Invalid {} parameter", name).as_str())),
+    }
+}
+
+/// Parses an optional principal parameter, `Err` is returned for malformed values
+pub fn get_principal_param(
+    params: &[(&str, &str)],
+    name: &str,
+) -> Result<Option<Principal>, HttpResponse> {
+    match get_param(params, name) {
+        None => Ok(None),
+        Some(value) => parse_principal(value).map(Some),
+    }
+}
+
+pub fn parse_principal(text: &str) -> Result<Principal, HttpResponse> {
+    Principal::from_text(text).map_err(|_| error_response(400, "Invalid principal"))
+}
+
+pub fn json_response(status_code: u16, body: Vec<u8>) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: vec![content_type("application/json")],
+        body,
+    }
+}
+
+pub fn text_response(status_code: u16, mime_type: &str, body: String) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: vec![content_type(mime_type)],
+        body: body.into_bytes(),
+    }
+}
+
+pub fn error_response(status_code: u16, message: &str) -> HttpResponse {
+    let body = Json::object(vec![("error", message.into())]);
+
+    json_response(status_code, body.encode().into_bytes())
+}
+
+fn content_type(mime_type: &str) -> HeaderField {
+    (String::from("Content-Type"), String::from(mime_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::{get_num_param, get_param, parse_url, Json};
+
+    #[test]
+    fn json_and_urls_work_fine() {
+        let json = Json::object(vec![
+            ("name", "Test \"token\"\n".into()),
+            ("supply", u64::MAX.into()),
+            ("website", Json::from(None::<String>)),
+            (
+                "tags",
+                Json::Array(vec![(-1i64).into(), Json::Nat(8), Json::Bool(true)]),
+            ),
+        ]);
+
+        assert_eq!(
+            json.encode(),
+            "{\"name\":\"Test \\\"token\\\"\\n\",\"supply\":\"18446744073709551615\",\"website\":null,\"tags\":[\"-1\",8,true]}"
+        );
+
+        let (segments, params) = parse_url("/holders/?cursor=aaaaa-aa&limit=10");
+        assert_eq!(segments, vec!["holders"]);
+        assert_eq!(get_param(&params, "cursor"), Some("aaaaa-aa"));
+        assert_eq!(get_num_param(&params, "limit").ok().unwrap(), Some(10));
+        assert!(get_num_param(&params, "cursor").is_err());
+        assert_eq!(get_param(&params, "start"), None);
+
+        let (segments, params) = parse_url("/");
+        assert!(segments.is_empty());
+        assert!(params.is_empty());
+    }
+}
@@ -0,0 +1,50 @@
+//! Logic and API types shared by the currency and membership tokens. Everything which is
+//! specific to a token (its roles, its proposal actions) is passed in by the token itself.
+
+/// Low cycles detection
+pub mod cycles;
+
+/// Sequenced log of emitted events, optionally backed by an archive canister
+pub mod event_log;
+
+/// Role, multisig, timelock and handover logic behind the endpoints of both tokens
+pub mod governance;
+
+/// Two-step handovers of roles to new controllers
+pub mod handover;
+
+/// JSON rendering and url parsing for `http_request`
+pub mod http;
+
+/// Call counters, memory usage and the Prometheus text format
+pub mod metrics;
+
+/// M-of-N approvals of privileged operations
+pub mod proposals;
+
+/// Principals grouped into roles, each administered by some role
+pub mod roles;
+
+/// Controllers changes delayed by a cancellation window
+pub mod timelock;
+
+/// API related types used by both tokens
+pub mod types;
+
+/// Unlike `union_utils::random_principal_test`, which is derived from the current time, never
+/// repeats itself and never produces an invalid principal
+#[cfg(test)]
+pub(crate) fn random_principal() -> ic_cdk::export::candid::Principal {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(1);
+
+    let mut bytes = COUNTER
+        .fetch_add(1, Ordering::Relaxed)
+        .to_be_bytes()
+        .to_vec();
+    // the opaque id class
+    bytes.push(1);
+
+    ic_cdk::export::candid::Principal::from_slice(&bytes)
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use ic_cdk::export::candid::{CandidType, Deserialize};
+
+pub const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+/// Counters which are gathered while the canister runs. Only update calls are counted,
+/// since state changes made by queries are discarded. Tokens without recurrent tasks
+/// never record failed recurrent executions.
+#[derive(Default, CandidType, Deserialize)]
+pub struct Metrics {
+    pub call_counters: BTreeMap<String, u64>,
+    pub failed_recurrent_executions: u64,
+    pub last_heartbeat_at: Option<u64>,
+}
+
+impl Metrics {
+    pub fn record_call(&mut self, method: &str) {
+        *self.call_counters.entry(String::from(method)).or_insert(0) += 1;
+    }
+
+    pub fn record_failed_recurrent_execution(&mut self) {
+        self.failed_recurrent_executions += 1;
+    }
+
+    /// Called on every canister heartbeat, whether any scheduled task is due or not
+    pub fn record_heartbeat(&mut self, now: u64) {
+        self.last_heartbeat_at = Some(now);
+    }
+
+    pub fn get_call_counters(&self) -> Vec<(String, u64)> {
+        self.call_counters
+            .iter()
+            .map(|(method, count)| (method.clone(), *count))
+            .collect()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn heap_memory_bytes() -> u64 {
+    core::arch::wasm32::memory_size(0) as u64 * WASM_PAGE_SIZE
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn heap_memory_bytes() -> u64 {
+    0
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn stable_memory_bytes() -> u64 {
+    ic_cdk::api::stable::stable_size() as u64 * WASM_PAGE_SIZE
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn stable_memory_bytes() -> u64 {
+    0
+}
+
+/// Renders metrics in the Prometheus text exposition format
+pub struct PrometheusWriter {
+    prefix: &'static str,
+    buf: String,
+}
+
+impl PrometheusWriter {
+    pub fn new(prefix: &'static str) -> PrometheusWriter {
+        PrometheusWriter {
+            prefix,
+            buf: String::new(),
+        }
+    }
+
+    pub fn gauge(&mut self, name: &str, help: &str, value: u64) -> &mut Self {
+        self.header(name, help, "gauge");
+        self.buf
+            .push_str(format!("{}_{} {}\n", self.prefix, name, value).as_str());
+
+        self
+    }
+
+    pub fn counter(&mut self, name: &str, help: &str, value: u64) -> &mut Self {
+        self.header(name, help, "counter");
+        self.buf
+            .push_str(format!("{}_{} {}\n", self.prefix, name, value).as_str());
+
+        self
+    }
+
+    pub fn labeled_counter(
+        &mut self,
+        name: &str,
+        help: &str,
+        label: &str,
+        values: &[(String, u64)],
+    ) -> &mut Self {
+        self.header(name, help, "counter");
+
+        for (label_value, value) in values {
+            self.buf.push_str(
+                format!(
+                    "{}_{}{{{}=\"{}\"}} {}\n",
+                    self.prefix, name, label, label_value, value
+                )
+                .as_str(),
+            );
+        }
+
+        self
+    }
+
+    pub fn finish(&mut self) -> String {
+        std::mem::take(&mut self.buf)
+    }
+
+    fn header(&mut self, name: &str, help: &str, kind: &str) {
+        self.buf
+            .push_str(format!("# HELP {}_{} {}\n", self.prefix, name, help).as_str());
+        self.buf
+            .push_str(format!("# TYPE {}_{} {}\n", self.prefix, name, kind).as_str());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::{Metrics, PrometheusWriter};
+
+    #[test]
+    fn metrics_are_rendered_fine() {
+        let mut metrics = Metrics::default();
+        metrics.record_call("mint");
+        metrics.record_call("transfer");
+        metrics.record_call("mint");
+
+        assert_eq!(
+            metrics.get_call_counters(),
+            vec![(String::from("mint"), 2), (String::from("transfer"), 1)]
+        );
+
+        let text = PrometheusWriter::new("token")
+            .gauge("heap_memory_bytes", "Heap memory size", 65536)
+            .labeled_counter(
+                "calls_total",
+                "Update calls per method",
+                "method",
+                &metrics.get_call_counters(),
+            )
+            .finish();
+
+        assert_eq!(
+            text,
+            "# HELP token_heap_memory_bytes Heap memory size\n\
+             # TYPE token_heap_memory_bytes gauge\n\
+             token_heap_memory_bytes 65536\n\
+             # HELP token_calls_total Update calls per method\n\
+             # TYPE token_calls_total counter\n\
+             token_calls_total{method=\"mint\"} 2\n\
+             token_calls_total{method=\"transfer\"} 1\n"
+        );
+    }
+}
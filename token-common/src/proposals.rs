@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+
+use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+
+use crate::types::{Error, Proposal, ProposalId, ProposalStatus, Role};
+
+pub const DEFAULT_PROPOSAL_TTL_NANO: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+pub const MAX_PROPOSAL_TTL_NANO: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Keeps M-of-N approval thresholds for roles and the proposals waiting for approvals.
+/// Roles without an explicitly set threshold require a single approval, which means
+/// their members may call privileged endpoints directly. `A` is the token's proposal action.
+/// Executed and expired proposals are evicted once the next proposal is created.
+#[derive(CandidType, Deserialize)]
+pub struct ProposalRegistry<A> {
+    pub proposals: BTreeMap<ProposalId, Proposal<A>>,
+    pub thresholds: BTreeMap<Role, u32>,
+    pub proposal_id_counter: ProposalId,
+}
+
+impl<A> Default for ProposalRegistry<A> {
+    fn default() -> Self {
+        ProposalRegistry {
+            proposals: BTreeMap::new(),
+            thresholds: BTreeMap::new(),
+            proposal_id_counter: 0,
+        }
+    }
+}
+
+impl<A: Clone> ProposalRegistry<A> {
+    pub fn get_threshold(&self, role: &str) -> u32 {
+        self.thresholds.get(role).cloned().unwrap_or(1)
+    }
+
+    pub fn set_threshold(
+        &mut self,
+        role: &str,
+        threshold: u32,
+        role_members_count: usize,
+    ) -> Result<u32, Error> {
+        if threshold == 0 || threshold as usize > role_members_count {
+            return Err(Error::InvalidThreshold);
+        }
+
+        let old_threshold = self.get_threshold(role);
+        self.thresholds.insert(String::from(role), threshold);
+
+        Ok(old_threshold)
+    }
+
+    pub fn create_proposal(
+        &mut self,
+        proposer: Principal,
+        role: Role,
+        action: A,
+        now: u64,
+        ttl_nano: u64,
+    ) -> Result<ProposalId, Error> {
+        if ttl_nano == 0 || ttl_nano > MAX_PROPOSAL_TTL_NANO {
+            return Err(Error::InvalidTtl);
+        }
+
+        let expires_at = now.checked_add(ttl_nano).ok_or(Error::InvalidTtl)?;
+
+        self.evict_stale(now);
+
+        let id = self.proposal_id_counter;
+        self.proposal_id_counter += 1;
+
+        let threshold = self.get_threshold(&role);
+
+        self.proposals.insert(
+            id,
+            Proposal {
+                id,
+                role,
+                action,
+                proposer,
+                approvals: vec![proposer],
+                threshold,
+                created_at: now,
+                expires_at,
+                status: ProposalStatus::Pending,
+            },
+        );
+
+        Ok(id)
+    }
+
+    /// Drops the proposals which can't be approved anymore
+    fn evict_stale(&mut self, now: u64) {
+        self.proposals
+            .retain(|_, it| it.status == ProposalStatus::Pending && now < it.expires_at);
+    }
+
+    /// Adds the approval to the proposal, returning the number of collected approvals
+    pub fn approve(&mut self, id: ProposalId, approver: Principal, now: u64) -> Result<u32, Error> {
+        let proposal = self.proposals.get_mut(&id).ok_or(Error::ProposalNotFound)?;
+
+        if proposal.status != ProposalStatus::Pending {
+            return Err(Error::ProposalIsNotPending);
+        }
+
+        if now >= proposal.expires_at {
+            return Err(Error::ProposalExpired);
+        }
+
+        if proposal.approvals.contains(&approver) {
+            return Err(Error::AlreadyApproved);
+        }
+
+        proposal.approvals.push(approver);
+
+        Ok(proposal.approvals.len() as u32)
+    }
+
+    /// Only approvals of the current members of the proposal's role are counted, so approvals
+    /// of principals which have lost the role since then don't make the proposal executable
+    pub fn is_ready_for_execution(
+        &self,
+        id: ProposalId,
+        now: u64,
+        role_members: &[Principal],
+    ) -> bool {
+        match self.proposals.get(&id) {
+            Some(p) => {
+                let approvals = p
+                    .approvals
+                    .iter()
+                    .filter(|it| role_members.contains(it))
+                    .count();
+
+                p.status == ProposalStatus::Pending
+                    && now < p.expires_at
+                    && approvals as u32 >= self.get_threshold(&p.role)
+            }
+            None => false,
+        }
+    }
+
+    /// Marks the proposal as executed and returns its action
+    pub fn take_for_execution(
+        &mut self,
+        id: ProposalId,
+        now: u64,
+        role_members: &[Principal],
+    ) -> Option<A> {
+        if !self.is_ready_for_execution(id, now, role_members) {
+            return None;
+        }
+
+        let proposal = self.proposals.get_mut(&id).unwrap();
+        proposal.status = ProposalStatus::Executed;
+
+        Some(proposal.action.clone())
+    }
+
+    pub fn get_proposal(&self, id: ProposalId, now: u64) -> Option<Proposal<A>> {
+        self.proposals.get(&id).map(|it| self.actualize(it, now))
+    }
+
+    pub fn get_pending_proposals(&self, now: u64) -> Vec<Proposal<A>> {
+        self.proposals
+            .values()
+            .map(|it| self.actualize(it, now))
+            .filter(|it| it.status == ProposalStatus::Pending)
+            .collect()
+    }
+
+    fn actualize(&self, proposal: &Proposal<A>, now: u64) -> Proposal<A> {
+        let mut proposal = proposal.clone();
+        proposal.threshold = self.get_threshold(&proposal.role);
+
+        if proposal.status == ProposalStatus::Pending && now >= proposal.expires_at {
+            proposal.status = ProposalStatus::Expired;
+        }
+
+        proposal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::proposals::{ProposalRegistry, MAX_PROPOSAL_TTL_NANO};
+    use crate::random_principal;
+    use crate::types::ProposalStatus;
+
+    const MINT_ROLE: &str = "mint";
+
+    #[test]
+    fn approval_flow_works_fine() {
+        let mut registry = ProposalRegistry::<String>::default();
+        let controller_1 = random_principal();
+        let controller_2 = random_principal();
+
+        assert_eq!(registry.get_threshold(MINT_ROLE), 1);
+        registry.set_threshold(MINT_ROLE, 3, 2).err().unwrap();
+        registry.set_threshold(MINT_ROLE, 0, 2).err().unwrap();
+        registry.set_threshold(MINT_ROLE, 2, 2).ok().unwrap();
+
+        let action = String::from("grant the mint role");
+        let id = registry
+            .create_proposal(controller_1, String::from(MINT_ROLE), action, 0, 10)
+            .ok()
+            .unwrap();
+        let members = [controller_1, controller_2];
+
+        assert!(!registry.is_ready_for_execution(id, 1, &members));
+        registry.approve(id, controller_1, 1).err().unwrap();
+        registry.approve(id, controller_2, 1).ok().unwrap();
+        assert!(registry.is_ready_for_execution(id, 1, &members));
+
+        // approvals of former members don't count
+        assert!(!registry.is_ready_for_execution(id, 1, &[controller_1]));
+        assert!(registry
+            .take_for_execution(id, 2, &[controller_1])
+            .is_none());
+
+        registry.take_for_execution(id, 2, &members).unwrap();
+        assert!(registry.take_for_execution(id, 2, &members).is_none());
+        assert_eq!(
+            registry.get_proposal(id, 2).unwrap().status,
+            ProposalStatus::Executed
+        );
+
+        // the executed proposal stays readable until the next one is created
+        let action = String::from("revoke the mint role");
+        registry
+            .create_proposal(controller_2, String::from(MINT_ROLE), action, 3, 10)
+            .ok()
+            .unwrap();
+        assert!(registry.get_proposal(id, 3).is_none());
+    }
+
+    #[test]
+    fn proposals_expire() {
+        let mut registry = ProposalRegistry::<String>::default();
+        let controller_1 = random_principal();
+        let controller_2 = random_principal();
+
+        registry.set_threshold(MINT_ROLE, 2, 2).ok().unwrap();
+
+        let action = String::from("revoke the mint role");
+        let id = registry
+            .create_proposal(controller_1, String::from(MINT_ROLE), action.clone(), 0, 10)
+            .ok()
+            .unwrap();
+
+        assert_eq!(registry.get_pending_proposals(5).len(), 1);
+        assert!(registry.get_pending_proposals(10).is_empty());
+        assert_eq!(
+            registry.get_proposal(id, 10).unwrap().status,
+            ProposalStatus::Expired
+        );
+
+        registry.approve(id, controller_2, 10).err().unwrap();
+
+        // unbounded and overflowing lifetimes are refused
+        let role = String::from(MINT_ROLE);
+        registry
+            .create_proposal(controller_1, role.clone(), action.clone(), 10, 0)
+            .err()
+            .unwrap();
+        registry
+            .create_proposal(
+                controller_1,
+                role.clone(),
+                action.clone(),
+                10,
+                MAX_PROPOSAL_TTL_NANO + 1,
+            )
+            .err()
+            .unwrap();
+        registry
+            .create_proposal(controller_1, role.clone(), action.clone(), u64::MAX - 5, 10)
+            .err()
+            .unwrap();
+
+        // the expired proposal is evicted along with the next one being created
+        let next_id = registry
+            .create_proposal(controller_1, role, action, 10, 10)
+            .ok()
+            .unwrap();
+        assert!(registry.get_proposal(id, 10).is_none());
+        assert!(registry.get_proposal(next_id, 10).is_some());
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+
+use crate::types::{Controllers, Role, RoleInfo, ADMIN_ROLE};
+
+/// Generic role registry: each role is a set of principals administered by some other
+/// (or the same) role. Roles without an explicitly set admin role are administered by `admin`.
+#[derive(Default, CandidType, Deserialize)]
+pub struct RoleRegistry {
+    pub members: BTreeMap<Role, BTreeSet<Principal>>,
+    pub admin_roles: BTreeMap<Role, Role>,
+}
+
+impl RoleRegistry {
+    pub fn new(roles: &[&str], default_members: &[Principal]) -> RoleRegistry {
+        let mut registry = RoleRegistry::default();
+
+        for role in roles {
+            registry
+                .admin_roles
+                .insert(String::from(*role), String::from(*role));
+            registry.members.insert(
+                String::from(*role),
+                default_members.iter().cloned().collect(),
+            );
+        }
+
+        registry
+    }
+
+    pub fn has_role(&self, role: &str, principal: &Principal) -> bool {
+        match self.members.get(role) {
+            Some(members) => members.contains(principal),
+            None => false,
+        }
+    }
+
+    pub fn get_admin_role(&self, role: &str) -> Role {
+        self.admin_roles
+            .get(role)
+            .cloned()
+            .unwrap_or_else(|| String::from(ADMIN_ROLE))
+    }
+
+    pub fn is_admin_of(&self, role: &str, principal: &Principal) -> bool {
+        self.has_role(&self.get_admin_role(role), principal)
+    }
+
+    pub fn get_role_members(&self, role: &str) -> Controllers {
+        self.members
+            .get(role)
+            .map(|it| it.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get_roles(&self) -> Vec<RoleInfo> {
+        self.members
+            .keys()
+            .chain(self.admin_roles.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|role| RoleInfo {
+                role: role.clone(),
+                admin_role: self.get_admin_role(role),
+                members: self.get_role_members(role),
+            })
+            .collect()
+    }
+
+    /// Sets the admin role without checking any permissions, returning the previous one
+    pub fn replace_admin_role(&mut self, role: &str, admin_role: Role) -> Role {
+        let old_admin_role = self.get_admin_role(role);
+        self.admin_roles.insert(String::from(role), admin_role);
+
+        old_admin_role
+    }
+
+    /// Adds the member without checking any permissions, returning the new members of the role
+    pub fn add_role_member(&mut self, role: &str, principal: Principal) -> Controllers {
+        self.members
+            .entry(String::from(role))
+            .or_default()
+            .insert(principal);
+
+        self.get_role_members(role)
+    }
+
+    /// Removes the member without checking any permissions, returning the new members of the
+    /// role or `None` if the principal was not a member
+    pub fn remove_role_member(&mut self, role: &str, principal: &Principal) -> Option<Controllers> {
+        let removed = match self.members.get_mut(role) {
+            Some(members) => members.remove(principal),
+            None => false,
+        };
+
+        if removed {
+            Some(self.get_role_members(role))
+        } else {
+            None
+        }
+    }
+
+    /// Replaces all the members of the role at once, returning the previous ones
+    pub fn set_role_members(&mut self, role: &str, new_members: Controllers) -> Controllers {
+        let old_members = self.get_role_members(role);
+        self.members
+            .insert(String::from(role), new_members.into_iter().collect());
+
+        old_members
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::random_principal;
+    use crate::roles::RoleRegistry;
+    use crate::types::ADMIN_ROLE;
+
+    const MINT_ROLE: &str = "mint";
+
+    #[test]
+    fn role_management_works_fine() {
+        let controller = random_principal();
+        let user_1 = random_principal();
+        let user_2 = random_principal();
+
+        let mut registry = RoleRegistry::new(&[ADMIN_ROLE, MINT_ROLE], &[controller]);
+
+        assert!(registry.has_role(MINT_ROLE, &controller));
+        assert!(registry.is_admin_of(MINT_ROLE, &controller));
+        assert!(!registry.has_role(MINT_ROLE, &user_1));
+
+        let members = registry.add_role_member(MINT_ROLE, user_1);
+        assert_eq!(members.len(), 2);
+        assert!(registry.has_role(MINT_ROLE, &user_1));
+
+        // custom roles are administered by the admin role by default
+        assert_eq!(registry.get_admin_role("custom"), String::from(ADMIN_ROLE));
+        assert!(registry.is_admin_of("custom", &controller));
+        registry.add_role_member("custom", user_2);
+        assert!(registry.has_role("custom", &user_2));
+
+        let old_admin_role = registry.replace_admin_role("custom", String::from(MINT_ROLE));
+        assert_eq!(old_admin_role, String::from(ADMIN_ROLE));
+        assert!(registry.is_admin_of("custom", &user_1));
+
+        assert!(registry
+            .remove_role_member("custom", &user_2)
+            .unwrap()
+            .is_empty());
+        assert!(!registry.has_role("custom", &user_2));
+        assert!(registry.remove_role_member("custom", &user_2).is_none());
+
+        let old_members = registry.set_role_members(MINT_ROLE, vec![user_2]);
+        assert_eq!(old_members.len(), 2);
+        assert_eq!(registry.get_role_members(MINT_ROLE), vec![user_2]);
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+use ic_cron::types::TaskId;
+
+use crate::types::{ControllersChange, ControllersUpdateId, Error, ScheduledControllersUpdate};
+
+pub const MAX_CONTROLLERS_UPDATE_DELAY_NANO: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Queue of controllers changes, each of them is applied only after `delay_nano` passes,
+/// giving current controllers a chance to cancel it. A zero delay disables the timelock.
+#[derive(Default, CandidType, Deserialize)]
+pub struct ControllersTimelock {
+    pub delay_nano: u64,
+    pub scheduled_updates: BTreeMap<ControllersUpdateId, ScheduledControllersUpdate>,
+    pub update_tasks: BTreeMap<ControllersUpdateId, TaskId>,
+    pub update_id_counter: ControllersUpdateId,
+}
+
+impl ControllersTimelock {
+    pub fn new(delay_nano: u64) -> ControllersTimelock {
+        ControllersTimelock {
+            delay_nano,
+            ..Default::default()
+        }
+    }
+
+    /// An unbounded delay would let a single change freeze the controllers for good
+    pub fn check_delay(delay_nano: u64) -> Result<(), Error> {
+        if delay_nano > MAX_CONTROLLERS_UPDATE_DELAY_NANO {
+            return Err(Error::InvalidDelay);
+        }
+
+        Ok(())
+    }
+
+    pub fn schedule(
+        &mut self,
+        change: ControllersChange,
+        scheduled_by: Principal,
+        now: u64,
+    ) -> Result<ScheduledControllersUpdate, Error> {
+        let execute_at = now
+            .checked_add(self.delay_nano)
+            .ok_or(Error::InvalidDelay)?;
+
+        let id = self.update_id_counter;
+        self.update_id_counter += 1;
+
+        let update = ScheduledControllersUpdate {
+            id,
+            change,
+            scheduled_by,
+            scheduled_at: now,
+            execute_at,
+        };
+
+        self.scheduled_updates.insert(id, update.clone());
+
+        Ok(update)
+    }
+
+    pub fn register_update_task(&mut self, id: ControllersUpdateId, task_id: TaskId) {
+        self.update_tasks.insert(id, task_id);
+    }
+
+    pub fn get_scheduled_update(
+        &self,
+        id: ControllersUpdateId,
+    ) -> Option<&ScheduledControllersUpdate> {
+        self.scheduled_updates.get(&id)
+    }
+
+    pub fn get_update_task(&self, id: ControllersUpdateId) -> Option<TaskId> {
+        self.update_tasks.get(&id).cloned()
+    }
+
+    /// Removes the update from the queue, if its delay has already passed
+    pub fn take_due(
+        &mut self,
+        id: ControllersUpdateId,
+        now: u64,
+    ) -> Result<ScheduledControllersUpdate, Error> {
+        let update = self
+            .scheduled_updates
+            .get(&id)
+            .ok_or(Error::ControllersUpdateNotFound)?;
+
+        if now < update.execute_at {
+            return Err(Error::TimelockNotExpired);
+        }
+
+        self.update_tasks.remove(&id);
+
+        Ok(self.scheduled_updates.remove(&id).unwrap())
+    }
+
+    pub fn cancel(&mut self, id: ControllersUpdateId) -> Result<ScheduledControllersUpdate, Error> {
+        self.update_tasks.remove(&id);
+
+        self.scheduled_updates
+            .remove(&id)
+            .ok_or(Error::ControllersUpdateNotFound)
+    }
+
+    pub fn get_scheduled_updates(&self) -> Vec<ScheduledControllersUpdate> {
+        self.scheduled_updates.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::random_principal;
+    use crate::timelock::{ControllersTimelock, MAX_CONTROLLERS_UPDATE_DELAY_NANO};
+    use crate::types::ControllersChange;
+
+    #[test]
+    fn timelock_works_fine() {
+        let mut timelock = ControllersTimelock::new(10);
+        let controller = random_principal();
+
+        let update_1 = timelock
+            .schedule(ControllersChange::SetDelay(5), controller, 0)
+            .ok()
+            .unwrap();
+        let update_2 = timelock
+            .schedule(ControllersChange::SetDelay(20), controller, 5)
+            .ok()
+            .unwrap();
+
+        assert_eq!(update_1.execute_at, 10);
+        assert_eq!(timelock.get_scheduled_updates().len(), 2);
+
+        timelock.take_due(update_1.id, 9).err().unwrap();
+        timelock.take_due(update_1.id, 10).ok().unwrap();
+        timelock.take_due(update_1.id, 10).err().unwrap();
+
+        timelock.cancel(update_2.id).ok().unwrap();
+        timelock.cancel(update_2.id).err().unwrap();
+
+        assert!(timelock.get_scheduled_updates().is_empty());
+    }
+
+    #[test]
+    fn delays_are_bounded() {
+        ControllersTimelock::check_delay(MAX_CONTROLLERS_UPDATE_DELAY_NANO)
+            .ok()
+            .unwrap();
+        ControllersTimelock::check_delay(MAX_CONTROLLERS_UPDATE_DELAY_NANO + 1)
+            .err()
+            .unwrap();
+
+        // an update which would never become due is refused instead of wrapping around
+        let mut timelock = ControllersTimelock::new(10);
+        let controller = random_principal();
+
+        timelock
+            .schedule(ControllersChange::SetDelay(5), controller, u64::MAX - 5)
+            .err()
+            .unwrap();
+        assert!(timelock.get_scheduled_updates().is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt::{Display, Formatter};
+
+use ic_cdk::export::candid::{CandidType, Deserialize, Principal};
+
+/// Errors of the shared registries, tokens add their own errors on top
+#[derive(CandidType, Deserialize)]
+pub enum Error {
+    AccessDenied,
+    InvalidThreshold,
+    ProposalNotFound,
+    ProposalIsNotPending,
+    ProposalExpired,
+    AlreadyApproved,
+    InvalidTtl,
+    ControllersUpdateNotFound,
+    TimelockNotExpired,
+    InvalidDelay,
+    EmptyControllers,
+    HandoverNotFound,
+    AlreadyAccepted,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Error::AccessDenied => "AccessDenied",
+            Error::InvalidThreshold => "InvalidThreshold",
+            Error::ProposalNotFound => "ProposalNotFound",
+            Error::ProposalIsNotPending => "ProposalIsNotPending",
+            Error::ProposalExpired => "ProposalExpired",
+            Error::AlreadyApproved => "AlreadyApproved",
+            Error::InvalidTtl => "InvalidTtl",
+            Error::ControllersUpdateNotFound => "ControllersUpdateNotFound",
+            Error::TimelockNotExpired => "TimelockNotExpired",
+            Error::InvalidDelay => "InvalidDelay",
+            Error::EmptyControllers => "EmptyControllers",
+            Error::HandoverNotFound => "HandoverNotFound",
+            Error::AlreadyAccepted => "AlreadyAccepted",
+        };
+
+        f.write_str(str)
+    }
+}
+
+pub type Controllers = Vec<Principal>;
+
+pub type Role = String;
+pub type ProposalId = u64;
+pub type ControllersUpdateId = u64;
+pub type EventSeq = u64;
+
+/// Roles without an explicitly set admin role are administered by this one
+pub const ADMIN_ROLE: &str = "admin";
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct RoleInfo {
+    pub role: Role,
+    pub admin_role: Role,
+    pub members: Controllers,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct RoleRequest {
+    pub role: Role,
+    pub principal: Principal,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct SetRoleAdminRequest {
+    pub role: Role,
+    pub admin_role: Role,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct UpdateRoleControllersRequest {
+    pub role: Role,
+    pub new_controllers: Controllers,
+    pub renounce: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, CandidType, Deserialize)]
+pub enum ProposalStatus {
+    Pending,
+    Executed,
+    Expired,
+}
+
+/// Privileged operation `A` waiting for enough members of `role` to approve it
+#[derive(Clone, CandidType, Deserialize)]
+pub struct Proposal<A> {
+    pub id: ProposalId,
+    pub role: Role,
+    pub action: A,
+    pub proposer: Principal,
+    pub approvals: Controllers,
+    pub threshold: u32,
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub status: ProposalStatus,
+}
+
+/// Change of controllers which only takes effect once the timelock delay passes
+#[derive(Clone, CandidType, Deserialize)]
+pub enum ControllersChange {
+    SetControllers(UpdateRoleControllersRequest),
+    GrantRole(RoleRequest),
+    RevokeRole(RoleRequest),
+    SetRoleAdmin(SetRoleAdminRequest),
+    SetDelay(u64),
+}
+
+impl ControllersChange {
+    /// Returns the role whose members are able to cancel this change
+    pub fn get_role(&self) -> Role {
+        match self {
+            ControllersChange::SetControllers(r) => r.role.clone(),
+            ControllersChange::GrantRole(r) => r.role.clone(),
+            ControllersChange::RevokeRole(r) => r.role.clone(),
+            ControllersChange::SetRoleAdmin(r) => r.role.clone(),
+            ControllersChange::SetDelay(_) => String::from(ADMIN_ROLE),
+        }
+    }
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct ScheduledControllersUpdate {
+    pub id: ControllersUpdateId,
+    pub change: ControllersChange,
+    pub scheduled_by: Principal,
+    pub scheduled_at: u64,
+    pub execute_at: u64,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct ControllersHandover {
+    pub role: Role,
+    pub new_controllers: Controllers,
+    pub accepted_by: Controllers,
+    pub proposed_by: Principal,
+    pub proposed_at: u64,
+}
+
+/// Event stored in the event log, `payload` is the candid-encoded event itself
+#[derive(Clone, CandidType, Deserialize)]
+pub struct EventRecord {
+    pub seq: EventSeq,
+    pub name: String,
+    pub principals: Vec<Principal>,
+    pub payload: Vec<u8>,
+    pub timestamp: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct EventTopicFilter {
+    pub name: Option<String>,
+    pub principal: Option<Principal>,
+}
+
+pub type HeaderField = (String, String);
+
+#[derive(CandidType, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<HeaderField>,
+    pub body: Vec<u8>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<HeaderField>,
+    pub body: Vec<u8>,
+}
@@ -0,0 +1,89 @@
+/// Formats the raw amount of the token's smallest units the way people read it, e.g. `12345`
+/// with 2 decimals is `123.45`
+pub fn format_amount(qty: u64, decimals: u8) -> String {
+    if decimals == 0 {
+        return qty.to_string();
+    }
+
+    let digits = format!("{:0>width$}", qty, width = decimals as usize + 1);
+    let (int, frac) = digits.split_at(digits.len() - decimals as usize);
+    let frac = frac.trim_end_matches('0');
+
+    if frac.is_empty() {
+        String::from(int)
+    } else {
+        format!("{}.{}", int, frac)
+    }
+}
+
+/// Parses the amount written with the decimal point into the token's smallest units, e.g.
+/// `1.5` with 2 decimals is `150`
+pub fn parse_amount(amount: &str, decimals: u8) -> Result<u64, String> {
+    let (int, frac) = match amount.split_once('.') {
+        Some((int, frac)) => (int, frac),
+        None => (amount, ""),
+    };
+
+    if int.is_empty() && frac.is_empty() {
+        return Err(format!("Invalid amount '{}'", amount));
+    }
+
+    if frac.len() > decimals as usize {
+        return Err(format!(
+            "Amount '{}' has more than {} decimals",
+            amount, decimals
+        ));
+    }
+
+    let digits = format!("{}{:0<width$}", int, frac, width = decimals as usize);
+
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Invalid amount '{}'", amount));
+    }
+
+    digits
+        .parse::<u64>()
+        .map_err(|_| format!("Amount '{}' is too big", amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::amount::{format_amount, parse_amount};
+
+    #[test]
+    fn format_works_fine() {
+        assert_eq!(format_amount(12345, 2), "123.45");
+        assert_eq!(format_amount(12300, 2), "123");
+        assert_eq!(format_amount(5, 3), "0.005");
+        assert_eq!(format_amount(0, 8), "0");
+        assert_eq!(format_amount(42, 0), "42");
+        assert_eq!(format_amount(u64::MAX, 19), "1.8446744073709551615");
+    }
+
+    #[test]
+    fn parse_works_fine() {
+        assert_eq!(parse_amount("123.45", 2), Ok(12345));
+        assert_eq!(parse_amount("1.5", 2), Ok(150));
+        assert_eq!(parse_amount("7", 2), Ok(700));
+        assert_eq!(parse_amount(".5", 1), Ok(5));
+        assert_eq!(parse_amount("42", 0), Ok(42));
+
+        assert!(parse_amount("1.234", 2).is_err());
+        assert!(parse_amount("1.5", 0).is_err());
+        assert!(parse_amount("-1", 2).is_err());
+        assert!(parse_amount("1e5", 2).is_err());
+        assert!(parse_amount(".", 2).is_err());
+        assert!(parse_amount("", 2).is_err());
+        assert!(parse_amount("18446744073709551616", 0).is_err());
+    }
+
+    #[test]
+    fn round_trip_works_fine() {
+        for (qty, decimals) in [(1, 8), (100_000_000, 8), (123_456_789, 4), (10, 1)] {
+            assert_eq!(
+                parse_amount(&format_amount(qty, decimals), decimals),
+                Ok(qty)
+            );
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use std::path::PathBuf;
+
+use ic_agent::Agent;
+use ic_cdk::export::candid::Principal;
+use ic_cron::types::{Iterations, SchedulingInterval};
+use serde_json::json;
+use structopt::StructOpt;
+
+use currency_token_client::agent::CurrencyTokenAgentClient;
+use currency_token_client::api::CurrencyTokenApi;
+use currency_token_client::types::{
+    BurnRequest, DequeueRecurrentTaskRequest, GetBalanceOfRequest,
+    GetRecurrentTransferTasksRequest, InitRequest, TokenInfo, TransferEntry, TransferRequest,
+    UpdateControllersRequest, EVENT_LISTENERS_ROLE,
+};
+
+use crate::amount::{format_amount, parse_amount};
+use crate::install::install_code;
+use crate::output::{controllers_update_output, principals_output, roles_output, Output};
+use crate::{call_error, read_wasm, secs_to_nano};
+
+#[derive(StructOpt)]
+pub enum CurrencyCommand {
+    /// Installs the token's wasm into an empty canister and initializes it
+    DeployInit {
+        /// Path to the currency token's wasm module
+        #[structopt(long, parse(from_os_str))]
+        wasm: PathBuf,
+        #[structopt(long)]
+        name: String,
+        #[structopt(long)]
+        symbol: String,
+        #[structopt(long)]
+        decimals: u8,
+        /// Initial controller of every role, the caller if omitted (repeatable)
+        #[structopt(long = "controller", parse(try_from_str = Principal::from_text))]
+        controllers: Vec<Principal>,
+        /// Timelock of controllers changes, 0 (no timelock) if omitted
+        #[structopt(long)]
+        controllers_update_delay_secs: Option<u64>,
+    },
+    /// Mints tokens, amount is written with decimals applied
+    Mint {
+        #[structopt(parse(try_from_str = Principal::from_text))]
+        to: Principal,
+        amount: String,
+        #[structopt(flatten)]
+        recurrence: Recurrence,
+    },
+    /// Transfers the caller's tokens, amount is written with decimals applied
+    Transfer {
+        #[structopt(parse(try_from_str = Principal::from_text))]
+        to: Principal,
+        amount: String,
+        #[structopt(flatten)]
+        recurrence: Recurrence,
+    },
+    /// Burns the caller's tokens, amount is written with decimals applied
+    Burn { amount: String },
+    /// Prints the balance with decimals applied
+    Balance {
+        #[structopt(parse(try_from_str = Principal::from_text))]
+        owner: Principal,
+    },
+    /// Lists recurrent transfers of the owner
+    TransferTasks {
+        #[structopt(parse(try_from_str = Principal::from_text))]
+        owner: Principal,
+    },
+    /// Lists recurrent mints
+    MintTasks,
+    /// Cancels the caller's recurrent transfers, or recurrent mints with --mint
+    CancelTasks {
+        #[structopt(long)]
+        mint: bool,
+        task_ids: Vec<u64>,
+    },
+    /// Lists roles and their members
+    Controllers,
+    /// Replaces members of the role (info, mint, burn or event_listeners)
+    SetControllers {
+        role: String,
+        #[structopt(parse(try_from_str = Principal::from_text))]
+        controllers: Vec<Principal>,
+        /// Allows leaving the role without members
+        #[structopt(long)]
+        renounce: bool,
+    },
+    /// Lists members of the event_listeners role, who add and remove listeners of the token's events
+    ListenersControllers,
+    /// Replaces members of the event_listeners role, who add and remove listeners of the token's events
+    SetListenersControllers {
+        #[structopt(parse(try_from_str = Principal::from_text))]
+        controllers: Vec<Principal>,
+        /// Allows leaving the role without members
+        #[structopt(long)]
+        renounce: bool,
+    },
+}
+
+#[derive(StructOpt)]
+pub struct Recurrence {
+    /// Repeats the operation every this many seconds
+    #[structopt(long)]
+    interval_secs: Option<u64>,
+    /// Stops repeating after this many times, repeats forever if omitted
+    #[structopt(long, requires = "interval-secs")]
+    iterations: Option<u64>,
+}
+
+impl Recurrence {
+    fn to_interval(&self) -> Result<Option<SchedulingInterval>, String> {
+        match self.interval_secs {
+            Some(secs) => Ok(Some(SchedulingInterval {
+                duration_nano: secs_to_nano(secs)?,
+                iterations: match self.iterations {
+                    Some(n) => Iterations::Exact(n),
+                    None => Iterations::Infinite,
+                },
+            })),
+            None => Ok(None),
+        }
+    }
+}
+
+pub async fn execute(
+    agent: Agent,
+    canister_id: Principal,
+    command: CurrencyCommand,
+) -> Result<Output, String> {
+    let client = CurrencyTokenAgentClient::new(agent, canister_id);
+
+    match command {
+        CurrencyCommand::DeployInit {
+            wasm,
+            name,
+            symbol,
+            decimals,
+            controllers,
+            controllers_update_delay_secs,
+        } => {
+            let request = InitRequest {
+                info: TokenInfo {
+                    name,
+                    symbol,
+                    decimals,
+                    ..Default::default()
+                },
+                default_controllers: if controllers.is_empty() {
+                    None
+                } else {
+                    Some(controllers)
+                },
+                controllers_update_delay_nano: controllers_update_delay_secs
+                    .map(secs_to_nano)
+                    .transpose()?,
+            };
+
+            install_code(&client.agent, canister_id, read_wasm(&wasm)?, request).await?;
+
+            Ok(Output {
+                json: json!({ "canister_id": canister_id.to_text() }),
+                text: format!("Currency token installed into {}", canister_id),
+            })
+        }
+        CurrencyCommand::Mint {
+            to,
+            amount,
+            recurrence,
+        } => {
+            let qty = parse_amount(&amount, get_decimals(&client).await?)?;
+            let request = transfer_request(to, qty, recurrence.to_interval()?);
+
+            client.mint(request).await.map_err(call_error)?;
+
+            Ok(Output {
+                json: json!({ "to": to.to_text(), "qty": qty }),
+                text: format!("Minted {} to {}", amount, to),
+            })
+        }
+        CurrencyCommand::Transfer {
+            to,
+            amount,
+            recurrence,
+        } => {
+            let qty = parse_amount(&amount, get_decimals(&client).await?)?;
+            let request = transfer_request(to, qty, recurrence.to_interval()?);
+
+            client.transfer(request).await.map_err(call_error)?;
+
+            Ok(Output {
+                json: json!({ "to": to.to_text(), "qty": qty }),
+                text: format!("Transferred {} to {}", amount, to),
+            })
+        }
+        CurrencyCommand::Burn { amount } => {
+            let qty = parse_amount(&amount, get_decimals(&client).await?)?;
+            let request = BurnRequest {
+                qty,
+                event_payload: None,
+            };
+
+            client.burn(request).await.map_err(call_error)?;
+
+            Ok(Output {
+                json: json!({ "qty": qty }),
+                text: format!("Burned {}", amount),
+            })
+        }
+        CurrencyCommand::Balance { owner } => {
+            let decimals = get_decimals(&client).await?;
+            let (response,) = client
+                .get_balance_of(GetBalanceOfRequest {
+                    account_owner: owner,
+                })
+                .await
+                .map_err(call_error)?;
+            let balance = format_amount(response.balance, decimals);
+
+            Ok(Output {
+                json: json!({
+                    "owner": owner.to_text(),
+                    "qty": response.balance,
+                    "balance": balance,
+                }),
+                text: balance,
+            })
+        }
+        CurrencyCommand::TransferTasks { owner } => {
+            let decimals = get_decimals(&client).await?;
+            let (response,) = client
+                .get_recurrent_transfer_tasks(GetRecurrentTransferTasksRequest { owner })
+                .await
+                .map_err(call_error)?;
+
+            let tasks = response
+                .tasks
+                .iter()
+                .map(|t| task_output(t.task_id, t.to, t.qty, decimals, &t.scheduling_interval))
+                .collect();
+
+            Ok(tasks_output(tasks))
+        }
+        CurrencyCommand::MintTasks => {
+            let decimals = get_decimals(&client).await?;
+            let (response,) = client
+                .get_recurrent_mint_tasks()
+                .await
+                .map_err(call_error)?;
+
+            let tasks = response
+                .tasks
+                .iter()
+                .map(|t| task_output(t.task_id, t.to, t.qty, decimals, &t.scheduling_interval))
+                .collect();
+
+            Ok(tasks_output(tasks))
+        }
+        CurrencyCommand::CancelTasks { mint, task_ids } => {
+            let request = DequeueRecurrentTaskRequest {
+                task_ids: task_ids.clone(),
+            };
+
+            let (response,) = if mint {
+                client.dequeue_recurrent_mint_tasks(request).await
+            } else {
+                client.dequeue_recurrent_transfer_tasks(request).await
+            }
+            .map_err(call_error)?;
+
+            let results: Vec<_> = task_ids.into_iter().zip(response.succeed).collect();
+
+            Ok(Output {
+                json: json!(results
+                    .iter()
+                    .map(|(id, cancelled)| json!({ "task_id": id, "cancelled": cancelled }))
+                    .collect::<Vec<_>>()),
+                text: results
+                    .iter()
+                    .map(|(id, cancelled)| {
+                        let status = if *cancelled { "cancelled" } else { "not found" };
+                        format!("#{} {}", id, status)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            })
+        }
+        CurrencyCommand::Controllers => {
+            let (response,) = client.get_controllers().await.map_err(call_error)?;
+
+            let roles = response
+                .controllers
+                .into_iter()
+                .map(|it| (it.role, it.admin_role, it.members))
+                .collect();
+
+            Ok(roles_output(roles))
+        }
+        CurrencyCommand::SetControllers {
+            role,
+            controllers,
+            renounce,
+        } => {
+            let request = UpdateControllersRequest {
+                new_controllers: controllers,
+                renounce: Some(renounce),
+            };
+
+            let (response,) = match role.as_str() {
+                "info" => client.update_info_controller(request).await,
+                "mint" => client.update_mint_controller(request).await,
+                "burn" => client.update_burn_controller(request).await,
+                "event_listeners" => client.update_event_listeners_controller(request).await,
+                _ => return Err(format!("Unknown role '{}'", role)),
+            }
+            .map_err(call_error)?;
+
+            Ok(controllers_update_output(
+                &role,
+                response.old_controllers,
+                response.scheduled_update_id,
+            ))
+        }
+        CurrencyCommand::ListenersControllers => {
+            let (response,) = client.get_controllers().await.map_err(call_error)?;
+
+            let controllers = response
+                .controllers
+                .into_iter()
+                .find(|it| it.role == EVENT_LISTENERS_ROLE)
+                .map(|it| it.members)
+                .unwrap_or_default();
+
+            Ok(principals_output(controllers))
+        }
+        CurrencyCommand::SetListenersControllers {
+            controllers,
+            renounce,
+        } => {
+            let request = UpdateControllersRequest {
+                new_controllers: controllers,
+                renounce: Some(renounce),
+            };
+
+            let (response,) = client
+                .update_event_listeners_controller(request)
+                .await
+                .map_err(call_error)?;
+
+            Ok(controllers_update_output(
+                EVENT_LISTENERS_ROLE,
+                response.old_controllers,
+                response.scheduled_update_id,
+            ))
+        }
+    }
+}
+
+async fn get_decimals(client: &CurrencyTokenAgentClient) -> Result<u8, String> {
+    let (response,) = client.get_info().await.map_err(call_error)?;
+
+    Ok(response.info.decimals)
+}
+
+fn transfer_request(
+    to: Principal,
+    qty: u64,
+    recurrence: Option<SchedulingInterval>,
+) -> TransferRequest {
+    TransferRequest {
+        entries: vec![TransferEntry {
+            to,
+            qty,
+            event_payload: None,
+            recurrence,
+        }],
+    }
+}
+
+fn task_output(
+    task_id: u64,
+    to: Principal,
+    qty: u64,
+    decimals: u8,
+    interval: &SchedulingInterval,
+) -> (serde_json::Value, String) {
+    let iterations = match interval.iterations {
+        Iterations::Exact(n) => n.to_string(),
+        Iterations::Infinite => String::from("infinite"),
+    };
+
+    (
+        json!({
+            "task_id": task_id,
+            "to": to.to_text(),
+            "qty": qty,
+            "interval_nano": interval.duration_nano,
+            "iterations": iterations,
+        }),
+        format!(
+            "#{} {} to {} every {}s, iterations left: {}",
+            task_id,
+            format_amount(qty, decimals),
+            to,
+            interval.duration_nano / 1_000_000_000,
+            iterations
+        ),
+    )
+}
+
+fn tasks_output(tasks: Vec<(serde_json::Value, String)>) -> Output {
+    let (json, text): (Vec<_>, Vec<_>) = tasks.into_iter().unzip();
+
+    Output {
+        json: json!(json),
+        text: text.join("\n"),
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use garcon::Delay;
+use ic_agent::Agent;
+use ic_cdk::export::candid::{encode_args, encode_one, CandidType, Deserialize, Principal};
+
+#[derive(CandidType, Deserialize)]
+enum InstallMode {
+    #[serde(rename = "install")]
+    Install,
+}
+
+#[derive(CandidType, Deserialize)]
+struct InstallCodeArgument {
+    mode: InstallMode,
+    canister_id: Principal,
+    wasm_module: Vec<u8>,
+    arg: Vec<u8>,
+}
+
+/// Installs the token's wasm into the already created (empty) canister, initializing it with
+/// the request. The agent's identity has to be a controller of the canister.
+pub async fn install_code<T: CandidType>(
+    agent: &Agent,
+    canister_id: Principal,
+    wasm_module: Vec<u8>,
+    init_request: T,
+) -> Result<(), String> {
+    let argument = InstallCodeArgument {
+        mode: InstallMode::Install,
+        canister_id,
+        wasm_module,
+        arg: encode_args((init_request,)).map_err(|e| e.to_string())?,
+    };
+
+    let waiter = Delay::builder()
+        .throttle(Duration::from_millis(500))
+        .timeout(Duration::from_secs(5 * 60))
+        .build();
+
+    agent
+        .update(&Principal::management_canister(), "install_code")
+        .with_effective_canister_id(canister_id)
+        .with_arg(encode_one(argument).map_err(|e| e.to_string())?)
+        .call_and_wait(waiter)
+        .await
+        .map_err(|e| format!("Failed to install the code - {}", e))?;
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+//! Command line tool for operating currency and membership tokens, instead of hand-writing
+//! `dfx canister call` arguments
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use ic_agent::identity::{AnonymousIdentity, BasicIdentity};
+use ic_agent::Agent;
+use ic_cdk::api::call::RejectionCode;
+use ic_cdk::export::candid::Principal;
+use serde_json::json;
+use structopt::StructOpt;
+
+use currency_token_client::agent::create_agent;
+
+use crate::currency::CurrencyCommand;
+use crate::membership::MembershipCommand;
+use crate::output::Output;
+
+mod amount;
+mod currency;
+mod install;
+mod membership;
+mod output;
+
+#[derive(StructOpt)]
+#[structopt(name = "tokens-cli", about = "Operates currency and membership tokens")]
+struct Opts {
+    /// Url of the replica to send calls to
+    #[structopt(long, default_value = "https://ic0.app")]
+    url: String,
+    /// PEM file of the identity to sign calls with, calls are anonymous if omitted
+    #[structopt(long, parse(from_os_str))]
+    identity: Option<PathBuf>,
+    /// Trusts the root key fetched from the replica - only for local replicas
+    #[structopt(long)]
+    local: bool,
+    /// Prints results as JSON, for scripting
+    #[structopt(long)]
+    json: bool,
+    #[structopt(subcommand)]
+    token: TokenCommand,
+}
+
+#[derive(StructOpt)]
+enum TokenCommand {
+    /// Currency token operations
+    Currency {
+        #[structopt(long, parse(try_from_str = Principal::from_text))]
+        canister_id: Principal,
+        #[structopt(subcommand)]
+        command: CurrencyCommand,
+    },
+    /// Membership token operations
+    Membership {
+        #[structopt(long, parse(try_from_str = Principal::from_text))]
+        canister_id: Principal,
+        #[structopt(subcommand)]
+        command: MembershipCommand,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    let opts = Opts::from_args();
+
+    match run(opts.url, opts.identity, opts.local, opts.token).await {
+        Ok(output) => output.print(opts.json),
+        Err(e) => {
+            if opts.json {
+                eprintln!("{}", json!({ "error": e }));
+            } else {
+                eprintln!("Error: {}", e);
+            }
+
+            exit(1);
+        }
+    }
+}
+
+async fn run(
+    url: String,
+    identity: Option<PathBuf>,
+    local: bool,
+    token: TokenCommand,
+) -> Result<Output, String> {
+    let agent = connect(&url, identity, local).await?;
+
+    match token {
+        TokenCommand::Currency {
+            canister_id,
+            command,
+        } => currency::execute(agent, canister_id, command).await,
+        TokenCommand::Membership {
+            canister_id,
+            command,
+        } => membership::execute(agent, canister_id, command).await,
+    }
+}
+
+async fn connect(url: &str, identity: Option<PathBuf>, local: bool) -> Result<Agent, String> {
+    let agent = match identity {
+        Some(path) => {
+            let identity = BasicIdentity::from_pem_file(&path)
+                .map_err(|e| format!("Unable to read identity {:?} - {}", path, e))?;
+
+            create_agent(url, identity, local).await
+        }
+        None => create_agent(url, AnonymousIdentity, local).await,
+    };
+
+    agent.map_err(|e| format!("Unable to connect to {} - {}", url, e))
+}
+
+pub fn call_error((code, msg): (RejectionCode, String)) -> String {
+    format!("The call was rejected ({:?}) - {}", code, msg)
+}
+
+pub fn secs_to_nano(secs: u64) -> Result<u64, String> {
+    secs.checked_mul(1_000_000_000)
+        .ok_or_else(|| format!("{} seconds is too long a duration", secs))
+}
+
+pub fn read_wasm(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("Unable to read wasm {:?} - {}", path, e))
+}
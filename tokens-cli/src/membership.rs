@@ -0,0 +1,204 @@
+use std::path::PathBuf;
+
+use ic_agent::Agent;
+use ic_cdk::export::candid::Principal;
+use serde_json::json;
+use structopt::StructOpt;
+
+use membership_token_client::agent::MembershipTokenAgentClient;
+use membership_token_client::api::MembershipTokenApi;
+use membership_token_client::types::{
+    InitRequest, IsMemberRequest, IssueRevokeMembershipsRequest, UpdateControllerRequest,
+    EVENT_LISTENERS_ROLE,
+};
+
+use crate::install::install_code;
+use crate::output::{controllers_update_output, principals_output, roles_output, Output};
+use crate::{call_error, read_wasm, secs_to_nano};
+
+#[derive(StructOpt)]
+pub enum MembershipCommand {
+    /// Installs the token's wasm into an empty canister and initializes it
+    DeployInit {
+        /// Path to the membership token's wasm module
+        #[structopt(long, parse(from_os_str))]
+        wasm: PathBuf,
+        /// Initial controller of every role, the caller if omitted (repeatable)
+        #[structopt(long = "controller", parse(try_from_str = Principal::from_text))]
+        controllers: Vec<Principal>,
+        /// Timelock of controllers changes, 0 (no timelock) if omitted
+        #[structopt(long)]
+        controllers_update_delay_secs: Option<u64>,
+    },
+    /// Issues memberships, which the principals have to accept
+    Issue {
+        #[structopt(parse(try_from_str = Principal::from_text), required = true)]
+        principals: Vec<Principal>,
+    },
+    /// Revokes memberships
+    Revoke {
+        #[structopt(parse(try_from_str = Principal::from_text), required = true)]
+        principals: Vec<Principal>,
+    },
+    /// Prints whether the principal is a member
+    IsMember {
+        #[structopt(parse(try_from_str = Principal::from_text))]
+        principal: Principal,
+    },
+    /// Lists roles and their members
+    Controllers,
+    /// Replaces members of the role (issue, revoke or event_listeners)
+    SetControllers {
+        role: String,
+        #[structopt(parse(try_from_str = Principal::from_text))]
+        controllers: Vec<Principal>,
+        /// Allows leaving the role without members
+        #[structopt(long)]
+        renounce: bool,
+    },
+    /// Lists members of the event_listeners role, who are allowed to subscribe to the token's events
+    ListenersControllers,
+    /// Replaces members of the event_listeners role, who are allowed to subscribe to the token's events
+    SetListenersControllers {
+        #[structopt(parse(try_from_str = Principal::from_text))]
+        controllers: Vec<Principal>,
+        /// Allows leaving the role without members
+        #[structopt(long)]
+        renounce: bool,
+    },
+}
+
+pub async fn execute(
+    agent: Agent,
+    canister_id: Principal,
+    command: MembershipCommand,
+) -> Result<Output, String> {
+    let client = MembershipTokenAgentClient::new(agent, canister_id);
+
+    match command {
+        MembershipCommand::DeployInit {
+            wasm,
+            controllers,
+            controllers_update_delay_secs,
+        } => {
+            let request = InitRequest {
+                default_controllers: if controllers.is_empty() {
+                    None
+                } else {
+                    Some(controllers)
+                },
+                controllers_update_delay_nano: controllers_update_delay_secs
+                    .map(secs_to_nano)
+                    .transpose()?,
+            };
+
+            install_code(&client.agent, canister_id, read_wasm(&wasm)?, request).await?;
+
+            Ok(Output {
+                json: json!({ "canister_id": canister_id.to_text() }),
+                text: format!("Membership token installed into {}", canister_id),
+            })
+        }
+        MembershipCommand::Issue { principals } => {
+            client
+                .issue_memberships(IssueRevokeMembershipsRequest {
+                    principals: principals.clone(),
+                })
+                .await
+                .map_err(call_error)?;
+
+            Ok(principals_output(principals))
+        }
+        MembershipCommand::Revoke { principals } => {
+            client
+                .revoke_memberships(IssueRevokeMembershipsRequest {
+                    principals: principals.clone(),
+                })
+                .await
+                .map_err(call_error)?;
+
+            Ok(principals_output(principals))
+        }
+        MembershipCommand::IsMember { principal } => {
+            let (response,) = client
+                .is_member(IsMemberRequest { prin: principal })
+                .await
+                .map_err(call_error)?;
+
+            Ok(Output {
+                json: json!({
+                    "principal": principal.to_text(),
+                    "is_member": response.is_member,
+                }),
+                text: response.is_member.to_string(),
+            })
+        }
+        MembershipCommand::Controllers => {
+            let (response,) = client.get_controllers().await.map_err(call_error)?;
+
+            let roles = response
+                .controllers
+                .into_iter()
+                .map(|it| (it.role, it.admin_role, it.members))
+                .collect();
+
+            Ok(roles_output(roles))
+        }
+        MembershipCommand::SetControllers {
+            role,
+            controllers,
+            renounce,
+        } => {
+            let request = UpdateControllerRequest {
+                new_controllers: controllers,
+                renounce: Some(renounce),
+            };
+
+            let (response,) = match role.as_str() {
+                "issue" => client.update_issue_controller(request).await,
+                "revoke" => client.update_revoke_controller(request).await,
+                "event_listeners" => client.update_event_listeners_controller(request).await,
+                _ => return Err(format!("Unknown role '{}'", role)),
+            }
+            .map_err(call_error)?;
+
+            Ok(controllers_update_output(
+                &role,
+                response.old_controllers,
+                response.scheduled_update_id,
+            ))
+        }
+        MembershipCommand::ListenersControllers => {
+            let (response,) = client.get_controllers().await.map_err(call_error)?;
+
+            let controllers = response
+                .controllers
+                .into_iter()
+                .find(|it| it.role == EVENT_LISTENERS_ROLE)
+                .map(|it| it.members)
+                .unwrap_or_default();
+
+            Ok(principals_output(controllers))
+        }
+        MembershipCommand::SetListenersControllers {
+            controllers,
+            renounce,
+        } => {
+            let request = UpdateControllerRequest {
+                new_controllers: controllers,
+                renounce: Some(renounce),
+            };
+
+            let (response,) = client
+                .update_event_listeners_controller(request)
+                .await
+                .map_err(call_error)?;
+
+            Ok(controllers_update_output(
+                EVENT_LISTENERS_ROLE,
+                response.old_controllers,
+                response.scheduled_update_id,
+            ))
+        }
+    }
+}
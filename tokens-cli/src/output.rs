@@ -0,0 +1,78 @@
+use ic_cdk::export::candid::Principal;
+use serde_json::{json, Value};
+
+/// Result of a command, printed as JSON in the JSON mode and as text otherwise
+pub struct Output {
+    pub json: Value,
+    pub text: String,
+}
+
+impl Output {
+    pub fn print(&self, json: bool) {
+        if json {
+            println!("{}", self.json);
+        } else if !self.text.is_empty() {
+            println!("{}", self.text);
+        }
+    }
+}
+
+fn to_texts(principals: &[Principal]) -> Vec<String> {
+    principals.iter().map(Principal::to_text).collect()
+}
+
+pub fn principals_output(principals: Vec<Principal>) -> Output {
+    let texts = to_texts(&principals);
+
+    Output {
+        json: json!(texts),
+        text: texts.join("\n"),
+    }
+}
+
+/// Roles as (role, admin role, members)
+pub fn roles_output(roles: Vec<(String, String, Vec<Principal>)>) -> Output {
+    Output {
+        json: json!(roles
+            .iter()
+            .map(|(role, admin_role, members)| json!({
+                "role": role,
+                "admin_role": admin_role,
+                "members": to_texts(members),
+            }))
+            .collect::<Vec<_>>()),
+        text: roles
+            .iter()
+            .map(|(role, admin_role, members)| {
+                format!(
+                    "{} (administered by {}): {}",
+                    role,
+                    admin_role,
+                    to_texts(members).join(", ")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// Controllers updates are either applied right away or scheduled, if the token has a timelock
+pub fn controllers_update_output(
+    role: &str,
+    old_controllers: Vec<Principal>,
+    scheduled_update_id: Option<u64>,
+) -> Output {
+    let text = match scheduled_update_id {
+        Some(id) => format!("Update of the {} controllers is scheduled as #{}", role, id),
+        None => format!("The {} controllers are updated", role),
+    };
+
+    Output {
+        json: json!({
+            "role": role,
+            "old_controllers": to_texts(&old_controllers),
+            "scheduled_update_id": scheduled_update_id,
+        }),
+        text,
+    }
+}